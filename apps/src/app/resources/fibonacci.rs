@@ -1,18 +1,117 @@
 use crate::app::resources::{fibchain_error_to_axum_response, Resource};
+use crate::app::use_case::fibonacci::FibonacciGenerateNumberUseCase;
+use crate::domain::job::{Job, JobId, JobTransition};
+use crate::domain::provider::{ProofOptions, ProverMetadata, ReceiptKind};
+use crate::infra::receipt_archive::{ArchiveLookup, ArchivedReceipt, ArchivedSubmission};
+use crate::infra::redaction;
 use crate::prelude::*;
-use axum::extract::{Query, State};
+use alloy_primitives::TxHash;
+use axum::extract::{ConnectInfo, Path, Query, State};
 use axum::http::StatusCode;
 use axum::response::IntoResponse;
-use axum::routing::get;
+use axum::routing::{get, post};
 use axum::{Json, Router};
+use std::net::SocketAddr;
 use tracing::instrument;
 
 #[derive(Clone)]
 pub struct FibonacciResource;
 
-#[derive(Debug, Clone, Copy, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Deserialize)]
 struct GenerateAndPublishQueryParameters {
     pub iterations: u16,
+    /// Guest version to prove, the default version when unset.
+    pub version: Option<String>,
+    /// Proves again even when a cached proof for the same input exists.
+    #[serde(default)]
+    pub bypass_cache: bool,
+    /// Only `groth16` receipts can be submitted on-chain.
+    #[serde(default)]
+    pub receipt_kind: ReceiptKind,
+}
+
+impl GenerateAndPublishQueryParameters {
+    fn proof_options(&self, generator: &FibonacciGenerateNumberUseCase) -> Result<ProofOptions> {
+        Ok(ProofOptions {
+            bypass_cache: self.bypass_cache,
+            receipt_kind: self.receipt_kind,
+            image_id: Some(generator.guest_image_id(self.version.as_deref())?),
+        })
+    }
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+struct EstimateQueryParameters {
+    pub iterations: u16,
+    /// Guest version to execute, the default version when unset.
+    pub version: Option<String>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+struct EstimateResponse {
+    iterations: u16,
+    fibonacci_number: String,
+    segments: usize,
+    total_cycles: u64,
+    user_cycles: u64,
+    paging_cycles: u64,
+    reserved_cycles: u64,
+    execution_ms: u64,
+    /// Gas `increaseCounter` would use.
+    gas_estimate: u64,
+    /// Whether the gas was approximated, as no proof for the same input is cached yet.
+    gas_estimate_approximate: bool,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+struct ProofResponse {
+    iterations: u16,
+    receipt_kind: &'static str,
+    fibonacci_number: String,
+    journal: String,
+    /// Seal the contract verifies, only set for `groth16` receipts.
+    seal: Option<String>,
+    image_id: String,
+    /// Hex of the bincode encoded `risc0_zkvm::Receipt`.
+    receipt: String,
+    cycles: u64,
+    prover: ProverMetadata,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+struct ArchivedReceiptResponse {
+    image_id: String,
+    journal_digest: String,
+    iterations: u16,
+    input: String,
+    fibonacci_number: String,
+    receipt_kind: ReceiptKind,
+    seal: Option<String>,
+    /// Hex of the bincode encoded `risc0_zkvm::Receipt`.
+    receipt: String,
+    archived_at: u64,
+    prover: ProverMetadata,
+    submissions: Vec<ArchivedSubmission>,
+}
+
+impl From<ArchivedReceipt> for ArchivedReceiptResponse {
+    fn from(archived: ArchivedReceipt) -> Self {
+        let metadata = archived.metadata;
+
+        Self {
+            image_id: hex::encode(metadata.image_id),
+            journal_digest: hex::encode(metadata.journal_digest),
+            iterations: metadata.iterations,
+            input: metadata.input,
+            fibonacci_number: metadata.fibonacci_number,
+            receipt_kind: metadata.receipt_kind,
+            seal: archived.seal.map(hex::encode),
+            receipt: hex::encode(archived.receipt),
+            archived_at: metadata.archived_at,
+            prover: metadata.prover,
+            submissions: metadata.submissions,
+        }
+    }
 }
 
 #[derive(Debug, Clone, serde::Serialize)]
@@ -20,21 +119,117 @@ struct GenerateNumberResponse {
     transaction_hash: String,
 }
 
+#[derive(Debug, Clone, serde::Serialize)]
+struct JobResponse {
+    id: JobId,
+    iterations: u16,
+    bypass_cache: bool,
+    /// Image id of the guest version the job proves.
+    image_id: Option<String>,
+    status: &'static str,
+    fibonacci_number: Option<String>,
+    transaction_hash: Option<String>,
+    block_number: Option<u64>,
+    gas_used: Option<u64>,
+    error: Option<String>,
+    /// Attempts made by the current stage, or the stage that failed.
+    attempts: u32,
+    /// Attempts retried over every stage.
+    retries: u32,
+    created_at: u64,
+    updated_at: u64,
+    queue: JobQueueResponse,
+    transitions: Vec<JobTransitionResponse>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+struct JobQueueResponse {
+    /// Zero based position while the job waits for a free prover.
+    position: Option<usize>,
+    /// Jobs currently waiting for a free prover.
+    length: usize,
+    /// Time the job waited for a prover once it got one.
+    wait_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+struct JobTransitionResponse {
+    status: &'static str,
+    transaction_hash: Option<String>,
+    at: u64,
+}
+
+impl JobResponse {
+    fn new(
+        job: Job,
+        transitions: Vec<JobTransition>,
+        generator: &FibonacciGenerateNumberUseCase,
+    ) -> Self {
+        Self {
+            id: job.id,
+            iterations: job.iterations,
+            bypass_cache: job.options.bypass_cache,
+            image_id: job.options.image_id.map(hex::encode),
+            status: job.status.as_str(),
+            fibonacci_number: job.proof.map(|proof| proof.journal.to_string()),
+            transaction_hash: job.transaction_hash.map(hex::encode),
+            block_number: job.block_number,
+            gas_used: job.gas_used,
+            error: job.error,
+            attempts: job.attempts,
+            retries: job.retries,
+            created_at: job.created_at,
+            updated_at: job.updated_at,
+            queue: JobQueueResponse {
+                position: generator.queue_position(job.id),
+                length: generator.queue_length(),
+                wait_ms: job.queue_wait_ms,
+            },
+            transitions: transitions
+                .into_iter()
+                .map(|transition| JobTransitionResponse {
+                    status: transition.status.as_str(),
+                    transaction_hash: transition.status.transaction_hash().map(hex::encode),
+                    at: transition.at,
+                })
+                .collect(),
+        }
+    }
+}
+
 impl Resource for FibonacciResource {
     fn routes() -> Router<AppState> {
-        Router::new().route("/", get(Self::generate_number))
+        Router::new()
+            .route("/", get(Self::generate_number))
+            .route("/estimate", get(Self::estimate))
+            .route("/proof", get(Self::prove))
+            .route("/jobs", post(Self::submit_job))
+            .route("/jobs/{id}", get(Self::get_job).delete(Self::cancel_job))
+            .route("/archive/jobs/{id}", get(Self::get_archived_job))
+            .route(
+                "/archive/transactions/{hash}",
+                get(Self::get_archived_transaction),
+            )
     }
 }
 
 impl FibonacciResource {
-    #[instrument(skip(state))]
+    #[instrument(
+        skip(state, client),
+        fields(client = redaction::client_id(&client.ip().to_string()))
+    )]
     async fn generate_number(
         State(state): State<AppState>,
+        ConnectInfo(client): ConnectInfo<SocketAddr>,
         Query(query): Query<GenerateAndPublishQueryParameters>,
     ) -> AxumResult<axum::response::Response> {
+        let options = match query.proof_options(&state.fibonacci_number_generator) {
+            Ok(options) => options,
+            Err(error) => return Ok(fibchain_error_to_axum_response(&error)),
+        };
         let generation_result = state
             .fibonacci_number_generator
-            .execute(query.iterations)
+            .execute(query.iterations, options)
             .await;
 
         match generation_result {
@@ -46,4 +241,185 @@ impl FibonacciResource {
             Err(error) => Ok(fibchain_error_to_axum_response(&error)),
         }
     }
+
+    #[instrument(
+        skip(state, client),
+        fields(client = redaction::client_id(&client.ip().to_string()))
+    )]
+    async fn prove(
+        State(state): State<AppState>,
+        ConnectInfo(client): ConnectInfo<SocketAddr>,
+        Query(query): Query<GenerateAndPublishQueryParameters>,
+    ) -> AxumResult<axum::response::Response> {
+        let options = match query.proof_options(&state.fibonacci_number_generator) {
+            Ok(options) => options,
+            Err(error) => return Ok(fibchain_error_to_axum_response(&error)),
+        };
+        let proof = match state
+            .fibonacci_number_generator
+            .prove(query.iterations, options)
+            .await
+        {
+            Ok(proof) => proof,
+            Err(error) => return Ok(fibchain_error_to_axum_response(&error)),
+        };
+        let receipt = match bincode::serialize(&proof.receipt) {
+            Ok(receipt) => receipt,
+            Err(e) => {
+                let error = FibchainError::ZkVM(format!("failed to encode receipt: {}", e));
+                return Ok(fibchain_error_to_axum_response(&error));
+            }
+        };
+
+        let response = ProofResponse {
+            iterations: proof.iterations,
+            receipt_kind: proof.receipt_kind.as_str(),
+            fibonacci_number: proof.journal.to_string(),
+            journal: hex::encode(&proof.journal_bytes),
+            seal: proof.seal.map(hex::encode),
+            image_id: hex::encode(proof.image_id),
+            receipt: hex::encode(receipt),
+            cycles: proof.cycles,
+            prover: proof.prover,
+        };
+        Ok((StatusCode::OK, Json(response)).into_response())
+    }
+
+    #[instrument(
+        skip(state, client),
+        fields(client = redaction::client_id(&client.ip().to_string()))
+    )]
+    async fn estimate(
+        State(state): State<AppState>,
+        ConnectInfo(client): ConnectInfo<SocketAddr>,
+        Query(query): Query<EstimateQueryParameters>,
+    ) -> AxumResult<axum::response::Response> {
+        let image_id = match state
+            .fibonacci_number_generator
+            .guest_image_id(query.version.as_deref())
+        {
+            Ok(image_id) => image_id,
+            Err(error) => return Ok(fibchain_error_to_axum_response(&error)),
+        };
+
+        match state
+            .fibonacci_number_generator
+            .estimate(query.iterations, Some(image_id))
+            .await
+        {
+            Ok((report, gas_estimate)) => {
+                let response = EstimateResponse {
+                    iterations: query.iterations,
+                    fibonacci_number: report.journal.to_string(),
+                    segments: report.segments,
+                    total_cycles: report.total_cycles,
+                    user_cycles: report.user_cycles,
+                    paging_cycles: report.paging_cycles,
+                    reserved_cycles: report.reserved_cycles,
+                    execution_ms: report.duration.as_millis() as u64,
+                    gas_estimate: gas_estimate.gas,
+                    gas_estimate_approximate: gas_estimate.approximate,
+                };
+                Ok((StatusCode::OK, Json(response)).into_response())
+            }
+            Err(error) => Ok(fibchain_error_to_axum_response(&error)),
+        }
+    }
+
+    #[instrument(
+        skip(state, client),
+        fields(client = redaction::client_id(&client.ip().to_string()))
+    )]
+    async fn submit_job(
+        State(state): State<AppState>,
+        ConnectInfo(client): ConnectInfo<SocketAddr>,
+        Query(query): Query<GenerateAndPublishQueryParameters>,
+    ) -> AxumResult<axum::response::Response> {
+        let options = match query.proof_options(&state.fibonacci_number_generator) {
+            Ok(options) => options,
+            Err(error) => return Ok(fibchain_error_to_axum_response(&error)),
+        };
+        match state
+            .fibonacci_number_generator
+            .submit(query.iterations, options)
+            .await
+        {
+            Ok(job) => {
+                let transitions = vec![JobTransition {
+                    status: job.status,
+                    at: job.updated_at,
+                }];
+                let response =
+                    JobResponse::new(job, transitions, &state.fibonacci_number_generator);
+                Ok((StatusCode::ACCEPTED, Json(response)).into_response())
+            }
+            Err(error) => Ok(fibchain_error_to_axum_response(&error)),
+        }
+    }
+
+    #[instrument(skip(state))]
+    async fn get_job(
+        State(state): State<AppState>,
+        Path(id): Path<JobId>,
+    ) -> AxumResult<axum::response::Response> {
+        match state.fibonacci_number_generator.job(id).await {
+            Ok(Some((job, transitions))) => {
+                let response =
+                    JobResponse::new(job, transitions, &state.fibonacci_number_generator);
+                Ok((StatusCode::OK, Json(response)).into_response())
+            }
+            Ok(None) => Err(StatusCode::NOT_FOUND),
+            Err(error) => Ok(fibchain_error_to_axum_response(&error)),
+        }
+    }
+
+    #[instrument(skip(state))]
+    async fn cancel_job(
+        State(state): State<AppState>,
+        Path(id): Path<JobId>,
+    ) -> AxumResult<axum::response::Response> {
+        match state.fibonacci_number_generator.cancel(id).await {
+            Ok(Some((job, transitions))) => {
+                let response =
+                    JobResponse::new(job, transitions, &state.fibonacci_number_generator);
+                Ok((StatusCode::ACCEPTED, Json(response)).into_response())
+            }
+            Ok(None) => Err(StatusCode::NOT_FOUND),
+            Err(error) => Ok(fibchain_error_to_axum_response(&error)),
+        }
+    }
+
+    #[instrument(skip(state))]
+    async fn get_archived_job(
+        State(state): State<AppState>,
+        Path(id): Path<JobId>,
+    ) -> AxumResult<axum::response::Response> {
+        Self::archived_receipt(&state, ArchiveLookup::Job(id)).await
+    }
+
+    #[instrument(skip(state))]
+    async fn get_archived_transaction(
+        State(state): State<AppState>,
+        Path(hash): Path<TxHash>,
+    ) -> AxumResult<axum::response::Response> {
+        Self::archived_receipt(&state, ArchiveLookup::Transaction(hash)).await
+    }
+
+    async fn archived_receipt(
+        state: &AppState,
+        lookup: ArchiveLookup,
+    ) -> AxumResult<axum::response::Response> {
+        match state
+            .fibonacci_number_generator
+            .archived_receipt(lookup)
+            .await
+        {
+            Ok(Some(archived)) => {
+                let response = ArchivedReceiptResponse::from(archived);
+                Ok((StatusCode::OK, Json(response)).into_response())
+            }
+            Ok(None) => Err(StatusCode::NOT_FOUND),
+            Err(error) => Ok(fibchain_error_to_axum_response(&error)),
+        }
+    }
 }
@@ -0,0 +1,55 @@
+use crate::app::resources::{fibchain_error_to_axum_response, Resource};
+use crate::prelude::*;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::{Json, Router};
+use tracing::instrument;
+
+#[derive(Clone)]
+pub struct HealthResource;
+
+#[derive(Debug, Clone, serde::Serialize)]
+struct ReadyResponse {
+    status: &'static str,
+    contracts: Vec<DeployedGuestResponse>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+struct DeployedGuestResponse {
+    contract: String,
+    version: String,
+    /// Image id the contract accepts, the same as its guest version's.
+    image_id: String,
+}
+
+impl Resource for HealthResource {
+    fn routes() -> Router<AppState> {
+        Router::new().route("/ready", get(Self::ready))
+    }
+}
+
+impl HealthResource {
+    /// Ready once every configured contract accepts the proofs of its guest version.
+    #[instrument(skip(state))]
+    async fn ready(State(state): State<AppState>) -> AxumResult<axum::response::Response> {
+        match state.fibonacci_number_generator.check_image_ids().await {
+            Ok(deployed) => {
+                let response = ReadyResponse {
+                    status: "ready",
+                    contracts: deployed
+                        .into_iter()
+                        .map(|guest| DeployedGuestResponse {
+                            contract: guest.contract.unwrap_or_default().to_string(),
+                            version: guest.version,
+                            image_id: guest.image_id.to_string(),
+                        })
+                        .collect(),
+                };
+                Ok((StatusCode::OK, Json(response)).into_response())
+            }
+            Err(error) => Ok(fibchain_error_to_axum_response(&error)),
+        }
+    }
+}
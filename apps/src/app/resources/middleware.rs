@@ -0,0 +1,199 @@
+use crate::app::resources::ErrorMessageResponse;
+use crate::infra::config::Config;
+use crate::infra::rate_limit::RateLimiter;
+use crate::infra::redaction;
+use axum::extract::{ConnectInfo, FromRef, Request, State};
+use axum::http::{header, HeaderValue, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::{Json, Router};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use subtle::ConstantTimeEq;
+use tokio::sync::watch;
+use tracing::warn;
+
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Puts the routes behind the API key check and the rate limiter. The rate limiter runs first,
+/// so requests refused for an unknown API key still count and keys cannot be guessed at will.
+pub fn protect<S>(routes: Router<S>, state: S) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+    watch::Receiver<Config>: FromRef<S>,
+    Arc<RateLimiter>: FromRef<S>,
+{
+    routes
+        .layer(middleware::from_fn_with_state(state.clone(), authenticate))
+        .layer(middleware::from_fn_with_state(state, rate_limit))
+}
+
+/// Rejects requests without a known `x-api-key` header, unless no API keys are configured.
+pub async fn authenticate(
+    State(config): State<watch::Receiver<Config>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let authorized = {
+        let config = config.borrow();
+        let api_keys = &config.auth.api_keys;
+
+        api_keys.is_empty() || api_key(&request).is_some_and(|key| is_known_api_key(api_keys, key))
+    };
+
+    if !authorized {
+        warn!(
+            api_key = api_key(&request).map(redaction::key),
+            "Rejected request with a missing or unknown API key"
+        );
+
+        let response = ErrorMessageResponse {
+            message: "missing or invalid API key".to_string(),
+        };
+
+        return (StatusCode::UNAUTHORIZED, Json(response)).into_response();
+    }
+
+    next.run(request).await
+}
+
+/// Limits how many requests each client makes per minute, identifying clients by their API key
+/// when it is a known one or, otherwise, by their IP address.
+pub async fn rate_limit(
+    State(config): State<watch::Receiver<Config>>,
+    State(rate_limiter): State<Arc<RateLimiter>>,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let (requests_per_minute, client_id) = {
+        let config = config.borrow();
+        (
+            config.limits.requests_per_minute,
+            client_id(&config.auth.api_keys, api_key(&request), client.ip()),
+        )
+    };
+
+    if let Err(retry_after) = rate_limiter.check(&client_id, requests_per_minute) {
+        warn!(
+            client = redaction::client_id(&client_id),
+            requests_per_minute = requests_per_minute,
+            "Rejected request over the rate limit"
+        );
+
+        let response = ErrorMessageResponse {
+            message: "rate limit exceeded".to_string(),
+        };
+
+        let mut response = (StatusCode::TOO_MANY_REQUESTS, Json(response)).into_response();
+        response.headers_mut().insert(
+            header::RETRY_AFTER,
+            HeaderValue::from(retry_after.as_secs().max(1)),
+        );
+        return response;
+    }
+
+    next.run(request).await
+}
+
+fn api_key(request: &Request) -> Option<&str> {
+    request
+        .headers()
+        .get(API_KEY_HEADER)
+        .and_then(|value| value.to_str().ok())
+}
+
+/// Compares the key with every known one in constant time, so response times do not tell how
+/// much of a key matched.
+fn is_known_api_key(api_keys: &[String], key: &str) -> bool {
+    api_keys.iter().fold(false, |known, api_key| {
+        known | bool::from(api_key.as_bytes().ct_eq(key.as_bytes()))
+    })
+}
+
+/// An unchecked header would let a client pick a new identity on every request, so only a known
+/// API key identifies the client.
+fn client_id(api_keys: &[String], api_key: Option<&str>, ip: IpAddr) -> String {
+    match api_key {
+        Some(key) if is_known_api_key(api_keys, key) => redaction::client_id(key),
+        _ => ip.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::transports::http::reqwest;
+    use axum::routing::get;
+    use std::net::Ipv4Addr;
+
+    const IP: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+    #[test]
+    fn test_api_key_must_match_a_known_one() {
+        let api_keys = vec!["first-key".to_string(), "second-key".to_string()];
+
+        assert!(is_known_api_key(&api_keys, "second-key"));
+        assert!(!is_known_api_key(&api_keys, "second-ke"));
+        assert!(!is_known_api_key(&api_keys, "second-key2"));
+        assert!(!is_known_api_key(&[], "first-key"));
+    }
+
+    #[test]
+    fn test_unknown_api_keys_are_limited_by_ip() {
+        let api_keys = vec!["first-key".to_string()];
+
+        assert_eq!(
+            client_id(&api_keys, Some("first-key"), IP),
+            redaction::client_id("first-key")
+        );
+        assert_eq!(client_id(&api_keys, Some("random"), IP), "127.0.0.1");
+        assert_eq!(client_id(&[], Some("first-key"), IP), "127.0.0.1");
+        assert_eq!(client_id(&api_keys, None, IP), "127.0.0.1");
+    }
+
+    #[derive(Clone, FromRef)]
+    struct TestState {
+        config: watch::Receiver<Config>,
+        rate_limiter: Arc<RateLimiter>,
+    }
+
+    #[tokio::test]
+    async fn test_unknown_api_keys_are_rate_limited() {
+        let mut config = Config::default();
+        config.auth.api_keys = vec!["a-very-secret-api-key".to_string()];
+        config.limits.requests_per_minute = 2;
+        let state = TestState {
+            config: watch::channel(config).1,
+            rate_limiter: Arc::new(RateLimiter::new()),
+        };
+        let routes = protect(
+            Router::new().route("/", get(|| async { "ok" })),
+            state.clone(),
+        )
+        .with_state(state);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(
+                listener,
+                routes.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .await
+        });
+
+        let client = reqwest::Client::new();
+        let mut statuses = Vec::new();
+        for guess in ["guess-1", "guess-2", "guess-3"] {
+            let response = client
+                .get(format!("http://{}/", address))
+                .header(API_KEY_HEADER, guess)
+                .send()
+                .await
+                .unwrap();
+            statuses.push(response.status().as_u16());
+        }
+
+        assert_eq!(statuses, [401, 401, 429]);
+    }
+}
@@ -1,10 +1,13 @@
 use crate::prelude::{AppState, FibchainError};
 use alloy::transports::http::reqwest::StatusCode;
+use axum::http::{header, HeaderValue};
 use axum::response::IntoResponse;
 use axum::{Json, Router};
-use tracing::error;
+use tracing::{error, info, warn};
 
 pub mod fibonacci;
+pub mod health;
+pub mod middleware;
 
 #[derive(Debug, Clone, serde::Serialize)]
 struct ErrorMessageResponse {
@@ -29,6 +32,18 @@ pub fn fibchain_error_to_axum_response(error: &FibchainError) -> axum::response:
 
             (StatusCode::INTERNAL_SERVER_ERROR, Json(response)).into_response()
         }
+        FibchainError::RemoteProver(message) => {
+            error!(
+                "Failed to generate fibonacci number due to a remote prover error: {}",
+                message
+            );
+
+            let response = ErrorMessageResponse {
+                message: message.to_owned(),
+            };
+
+            (StatusCode::BAD_GATEWAY, Json(response)).into_response()
+        }
         FibchainError::Alloy(cause) => {
             error!(
                 "Failed to generate fibonacci number due to an alloy error: {}",
@@ -53,6 +68,203 @@ pub fn fibchain_error_to_axum_response(error: &FibchainError) -> axum::response:
 
             (StatusCode::BAD_GATEWAY, Json(response)).into_response()
         }
+        FibchainError::Config(cause) => {
+            error!(
+                "Failed to generate fibonacci number due to a configuration error: {}",
+                cause
+            );
+
+            let response = ErrorMessageResponse {
+                message: cause.to_string(),
+            };
+
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(response)).into_response()
+        }
+        FibchainError::Storage(message) => {
+            error!(
+                "Failed to generate fibonacci number due to a storage error: {}",
+                message
+            );
+
+            let response = ErrorMessageResponse {
+                message: message.to_owned(),
+            };
+
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(response)).into_response()
+        }
+        FibchainError::InvalidJobTransition { .. } => {
+            error!(
+                "Failed to generate fibonacci number due to a job lifecycle error: {}",
+                error
+            );
+
+            let response = ErrorMessageResponse {
+                message: error.to_string(),
+            };
+
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(response)).into_response()
+        }
+        FibchainError::ProverQueueFull { retry_after } => {
+            warn!("Rejected fibonacci number generation because the prover queue is full");
+
+            let response = ErrorMessageResponse {
+                message: error.to_string(),
+            };
+
+            let mut response = (StatusCode::SERVICE_UNAVAILABLE, Json(response)).into_response();
+            response.headers_mut().insert(
+                header::RETRY_AFTER,
+                HeaderValue::from(retry_after.as_secs().max(1)),
+            );
+            response
+        }
+        FibchainError::ShuttingDown => {
+            error!("Rejected fibonacci number generation because the service is shutting down");
+
+            let response = ErrorMessageResponse {
+                message: error.to_string(),
+            };
+
+            (StatusCode::SERVICE_UNAVAILABLE, Json(response)).into_response()
+        }
+        FibchainError::SubmissionDeferred { job_id } => {
+            warn!(
+                job_id = %job_id,
+                "Deferred the submission of a generated proof until the chain is available"
+            );
+
+            let response = ErrorMessageResponse {
+                message: error.to_string(),
+            };
+
+            (StatusCode::ACCEPTED, Json(response)).into_response()
+        }
+        FibchainError::JobCancelled { job_id } => {
+            info!(job_id = %job_id, "Fibonacci number generation was cancelled by request");
+
+            let response = ErrorMessageResponse {
+                message: error.to_string(),
+            };
+
+            (StatusCode::CONFLICT, Json(response)).into_response()
+        }
+        FibchainError::NotCancellable { job_id, status } => {
+            warn!(
+                job_id = %job_id,
+                status = status,
+                "Refused to cancel a job that can no longer be cancelled"
+            );
+
+            let response = ErrorMessageResponse {
+                message: error.to_string(),
+            };
+
+            (StatusCode::CONFLICT, Json(response)).into_response()
+        }
+        FibchainError::UnverifiableReceiptKind { kind } => {
+            info!(
+                receipt_kind = kind,
+                "Refused to submit a receipt the contract cannot verify"
+            );
+
+            let response = ErrorMessageResponse {
+                message: error.to_string(),
+            };
+
+            (StatusCode::BAD_REQUEST, Json(response)).into_response()
+        }
+        FibchainError::ArchiveDisabled => {
+            info!("Refused to look up a receipt because the archive is disabled");
+
+            let response = ErrorMessageResponse {
+                message: error.to_string(),
+            };
+
+            (StatusCode::NOT_FOUND, Json(response)).into_response()
+        }
+        FibchainError::ImageIdMismatch { .. } => {
+            error!(
+                "Refused to submit a proof the contract would reject: {}",
+                error
+            );
+
+            let response = ErrorMessageResponse {
+                message: error.to_string(),
+            };
+
+            (StatusCode::SERVICE_UNAVAILABLE, Json(response)).into_response()
+        }
+        FibchainError::UnknownGuestVersion { method, version } => {
+            info!(
+                method = method,
+                version = version,
+                "Refused to prove an unknown guest version"
+            );
+
+            let response = ErrorMessageResponse {
+                message: error.to_string(),
+            };
+
+            (StatusCode::BAD_REQUEST, Json(response)).into_response()
+        }
+        FibchainError::GuestNotDeployed { image_id } => {
+            info!(
+                image_id = %image_id,
+                "Refused to submit a proof no configured contract accepts"
+            );
+
+            let response = ErrorMessageResponse {
+                message: error.to_string(),
+            };
+
+            (StatusCode::BAD_REQUEST, Json(response)).into_response()
+        }
+        FibchainError::CycleLimitExceeded { limit } => {
+            info!(
+                limit = limit,
+                "Refused to prove past the session cycle limit"
+            );
+
+            let response = ErrorMessageResponse {
+                message: error.to_string(),
+            };
+
+            (StatusCode::UNPROCESSABLE_ENTITY, Json(response)).into_response()
+        }
+        FibchainError::ProvingTimeout { timeout } => {
+            warn!(
+                timeout_secs = timeout.as_secs(),
+                "Abandoned a proof that ran past the proving timeout"
+            );
+
+            let response = ErrorMessageResponse {
+                message: error.to_string(),
+            };
+
+            (StatusCode::UNPROCESSABLE_ENTITY, Json(response)).into_response()
+        }
+        FibchainError::InvalidIterations { iterations, max } => {
+            info!(
+                iterations = iterations,
+                max_iterations = max,
+                "Refused out of range iterations"
+            );
+
+            let response = ErrorMessageResponse {
+                message: error.to_string(),
+            };
+
+            (StatusCode::BAD_REQUEST, Json(response)).into_response()
+        }
+        FibchainError::Cancelled => {
+            warn!("Fibonacci number generation was cancelled before it finished");
+
+            let response = ErrorMessageResponse {
+                message: error.to_string(),
+            };
+
+            (StatusCode::SERVICE_UNAVAILABLE, Json(response)).into_response()
+        }
         FibchainError::Generic(message) => {
             error!(
                 "Failed to generate fibonacci number due to a generic error: {}",
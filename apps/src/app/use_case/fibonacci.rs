@@ -1,37 +1,1500 @@
-use crate::domain::provider::{IFibonacciEthereumProvider, IFibonacciRiscZeroProvider};
+use crate::domain::event::{EventBus, JobEvent};
+use crate::domain::job::{Job, JobId, JobStatus, JobTransition};
+use crate::domain::provider::{
+    ExecutionReport, FibonacciProof, GasEstimate, IFibonacciEthereumProvider,
+    IFibonacciRiscZeroProvider, ProofOptions, ReceiptKind, TransactionConfirmation,
+};
+use crate::domain::store::IJobStore;
+use crate::infra::config::RetryConfig;
+use crate::infra::guest_registry::{GuestProgram, GuestRegistry, FIBONACCI_METHOD};
+use crate::infra::job_cancellation::{JobCancellations, Registration};
+use crate::infra::prover_queue::{ProverPermit, ProverQueue};
+use crate::infra::receipt_archive::{ArchiveLookup, ArchivedReceipt, ReceiptArchive};
+use crate::infra::retry::RetryStage;
+use crate::infra::shutdown::Shutdown;
 use crate::prelude::*;
-use alloy_primitives::TxHash;
+use alloy_primitives::{Address, TxHash, B256};
+use std::collections::HashMap;
+use std::future::Future;
 use std::pin::Pin;
-use std::sync::Arc;
-use tracing::{info, instrument};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, info_span, instrument, warn, Instrument};
+
+/// Events a subscriber may fall behind on before it misses some.
+const EVENT_CAPACITY: usize = 1024;
 
 #[derive(Clone)]
 pub struct FibonacciGenerateNumberUseCase {
     fibonacci_risc_zero_provider: Arc<Pin<Box<dyn IFibonacciRiscZeroProvider + Sync + Send>>>,
     fibonacci_ethereum_provider: Arc<Pin<Box<dyn IFibonacciEthereumProvider + Sync + Send>>>,
+    job_store: Arc<Pin<Box<dyn IJobStore + Sync + Send>>>,
+    prover_queue: ProverQueue,
+    guests: Arc<GuestRegistry>,
+    archive: Option<Arc<ReceiptArchive>>,
+    retry: RetryConfig,
+    shutdown: Shutdown,
+    cancellations: JobCancellations,
+    events: EventBus,
+    /// Image id each contract accepts, once read.
+    deployed_image_ids: Arc<Mutex<HashMap<Address, B256>>>,
 }
 
 impl FibonacciGenerateNumberUseCase {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         fibonacci_risc_zero_provider: Arc<Pin<Box<dyn IFibonacciRiscZeroProvider + Sync + Send>>>,
         fibonacci_ethereum_provider: Arc<Pin<Box<dyn IFibonacciEthereumProvider + Sync + Send>>>,
+        job_store: Arc<Pin<Box<dyn IJobStore + Sync + Send>>>,
+        prover_queue: ProverQueue,
+        guests: Arc<GuestRegistry>,
+        archive: Option<Arc<ReceiptArchive>>,
+        retry: RetryConfig,
+        shutdown: Shutdown,
     ) -> Self {
         Self {
             fibonacci_risc_zero_provider,
             fibonacci_ethereum_provider,
+            job_store,
+            prover_queue,
+            guests,
+            archive,
+            retry,
+            shutdown,
+            cancellations: JobCancellations::new(),
+            events: EventBus::new(EVENT_CAPACITY),
+            deployed_image_ids: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
+    /// Runs a new job to completion, returning the hash of the confirmed transaction. The job
+    /// runs in the background like submitted ones, so it goes on when the caller goes away.
     #[instrument(skip(self))]
-    pub async fn execute(&self, iterations: u16) -> Result<TxHash> {
+    pub async fn execute(&self, iterations: u16, options: ProofOptions) -> Result<TxHash> {
+        let job = self.create_job(iterations, options).await?;
+
         info!("Executing Fibonacci number generation use-case");
-        let (seal, fibonacci_number) = self
+        let use_case = self.clone();
+        tokio::spawn(self.shutdown.track(async move { use_case.run(job).await }))
+            .await
+            .map_err(|e| FibchainError::Generic(e.into()))?
+    }
+
+    /// Persists a new job and runs it in the background, returning it right away.
+    #[instrument(skip(self))]
+    pub async fn submit(&self, iterations: u16, options: ProofOptions) -> Result<Job> {
+        let job = self.create_job(iterations, options).await?;
+        self.spawn(job.clone());
+
+        Ok(job)
+    }
+
+    /// Proves the computation without submitting it, for consumers that verify receipts
+    /// off-chain and may prefer a cheaper receipt kind. Waits for a free prover like jobs do.
+    #[instrument(skip(self))]
+    pub async fn prove(&self, iterations: u16, options: ProofOptions) -> Result<FibonacciProof> {
+        if self.shutdown.is_draining() {
+            return Err(FibchainError::ShuttingDown);
+        }
+
+        let id = JobId::random();
+        self.prover_queue
+            .try_enqueue(id)
+            .map_err(|retry_after| FibchainError::ProverQueueFull { retry_after })?;
+        let cancellation = self.shutdown.cancellation();
+
+        self.shutdown
+            .track(async {
+                let _permit = tokio::select! {
+                    permit = self.prover_queue.wait_turn(id) => permit,
+                    _ = cancellation.cancelled() => return Err(FibchainError::Cancelled),
+                };
+
+                self.fibonacci_risc_zero_provider
+                    .generate_proof(iterations, options, cancellation.clone())
+                    .await
+            })
+            .await
+    }
+
+    /// Executes the guest version without proving it, along with the gas submitting its proof
+    /// would use. The verifier rejects any seal but a valid one, so the gas is only estimated
+    /// against the contract once a proof for the same input is cached and a contract accepts the
+    /// version, and approximated otherwise.
+    #[instrument(skip(self))]
+    pub async fn estimate(
+        &self,
+        iterations: u16,
+        image_id: Option<B256>,
+    ) -> Result<(ExecutionReport, GasEstimate)> {
+        let report = self
             .fibonacci_risc_zero_provider
-            .generate_proof(iterations)
+            .dry_run(iterations, image_id)
             .await?;
+        let cached = self
+            .fibonacci_risc_zero_provider
+            .cached_proof(iterations, ReceiptKind::Groth16, image_id)
+            .await;
+        let deployed = cached.and_then(|proof| {
+            let contract = self.guests.by_image_id(proof.image_id)?.contract?;
+            Some((contract, proof))
+        });
+        let estimated = match deployed {
+            Some((contract, proof)) => self
+                .fibonacci_ethereum_provider
+                .estimate_increase_counter_gas(contract, &proof)
+                .await
+                .ok(),
+            None => None,
+        };
+        let gas = match estimated {
+            Some(gas) => GasEstimate {
+                gas,
+                approximate: false,
+            },
+            None => GasEstimate {
+                gas: self
+                    .fibonacci_ethereum_provider
+                    .approximate_increase_counter_gas(report.journal),
+                approximate: true,
+            },
+        };
+
+        Ok((report, gas))
+    }
+
+    /// Image id of the pinned guest version, or of the default version when none is pinned.
+    pub fn guest_image_id(&self, version: Option<&str>) -> Result<B256> {
+        self.guests
+            .resolve(FIBONACCI_METHOD, version)
+            .map(|guest| guest.image_id)
+    }
+
+    /// Reads the image id every configured contract accepts and compares it with the image id
+    /// of its guest version, refusing submissions of the versions that differ. Returns the
+    /// versions submitted on-chain.
+    pub async fn check_image_ids(&self) -> Result<Vec<GuestProgram>> {
+        let deployed: Vec<GuestProgram> = self.guests.deployed().cloned().collect();
+        for contract in deployed.iter().filter_map(|guest| guest.contract) {
+            let image_id = self.fibonacci_ethereum_provider.image_id(contract).await?;
+            self.deployed_image_ids
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(contract, image_id);
+        }
+        for guest in &deployed {
+            self.contract_for(guest.image_id)?;
+        }
+
+        Ok(deployed)
+    }
+
+    /// The contract accepting proofs of the guest version. Fails when none is configured, or
+    /// once the contract is known to accept another guest, as submitting would revert after
+    /// paying for the proof.
+    fn contract_for(&self, image_id: B256) -> Result<Address> {
+        let contract = self
+            .guests
+            .by_image_id(image_id)
+            .and_then(|guest| guest.contract)
+            .ok_or(FibchainError::GuestNotDeployed { image_id })?;
+        let deployed = self
+            .deployed_image_ids
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&contract)
+            .copied();
+
+        match deployed {
+            Some(deployed) if deployed != image_id => Err(FibchainError::ImageIdMismatch {
+                contract,
+                expected: image_id,
+                deployed,
+            }),
+            _ => Ok(contract),
+        }
+    }
+
+    /// The archived receipt of a job, or of the job that sent a transaction.
+    pub async fn archived_receipt(&self, lookup: ArchiveLookup) -> Result<Option<ArchivedReceipt>> {
+        let archive = self.archive.clone().ok_or(FibchainError::ArchiveDisabled)?;
+
+        tokio::task::spawn_blocking(move || archive.get(lookup))
+            .await
+            .map_err(|e| FibchainError::Storage(e.to_string()))?
+            .map_err(|e| FibchainError::Storage(format!("failed to read archived receipt: {}", e)))
+    }
+
+    /// Receives the events of every job from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<JobEvent> {
+        self.events.subscribe()
+    }
+
+    pub async fn job(&self, id: JobId) -> Result<Option<(Job, Vec<JobTransition>)>> {
+        let Some(job) = self.job_store.get(id).await? else {
+            return Ok(None);
+        };
+        let transitions = self.job_store.transitions(id).await?;
+
+        Ok(Some((job, transitions)))
+    }
+
+    /// Cancels a job. A queued job leaves the queue and a running proof stops at the next segment,
+    /// while a sent transaction is replaced by an empty one when configured. Jobs that started
+    /// submitting refuse otherwise. Returns the job as it was when cancelled, `None` when unknown.
+    #[instrument(skip(self))]
+    pub async fn cancel(&self, id: JobId) -> Result<Option<(Job, Vec<JobTransition>)>> {
+        let Some((job, transitions)) = self.job(id).await? else {
+            return Ok(None);
+        };
+        let not_cancellable = |status: JobStatus| FibchainError::NotCancellable {
+            job_id: id,
+            status: status.as_str(),
+        };
+
+        match job.status {
+            status if status.is_cancellable() => {
+                if !self.cancellations.cancel(id) {
+                    return Err(not_cancellable(JobStatus::Submitting));
+                }
+            }
+            JobStatus::Sent(transaction_hash) => {
+                let Some(replacement) = self
+                    .fibonacci_ethereum_provider
+                    .cancel_transaction(transaction_hash)
+                    .await?
+                else {
+                    return Err(not_cancellable(job.status));
+                };
+                self.cancellations.replace(id, replacement);
+            }
+            status => return Err(not_cancellable(status)),
+        }
+
+        info!(status = job.status.as_str(), "Job cancellation requested");
+        Ok(Some((job, transitions)))
+    }
+
+    /// Zero based position of a job waiting for a free prover.
+    pub fn queue_position(&self, id: JobId) -> Option<usize> {
+        self.prover_queue.position(id)
+    }
+
+    pub fn queue_length(&self) -> usize {
+        self.prover_queue.waiting()
+    }
+
+    /// Picks up the jobs a previous run left unfinished from the stage they were in, returning
+    /// how many were resumed. Jobs awaiting the chain are left to the submitter.
+    pub async fn resume_unfinished(&self) -> Result<usize> {
+        let mut resumed = 0;
+
+        for job in self.job_store.unfinished().await? {
+            if job.status == JobStatus::AwaitingChain {
+                info!(job_id = %job.id, "Job keeps waiting for the chain");
+                continue;
+            }
+
+            info!(
+                job_id = %job.id,
+                status = job.status.as_str(),
+                "Resuming unfinished job"
+            );
+            self.spawn(job);
+            resumed += 1;
+        }
+
+        Ok(resumed)
+    }
+
+    /// Submits the jobs waiting for the chain once it answers again, returning how many were
+    /// picked up.
+    pub async fn submit_deferred(&self) -> Result<usize> {
+        let deferred: Vec<Job> = self
+            .job_store
+            .unfinished()
+            .await?
+            .into_iter()
+            .filter(|job| job.status == JobStatus::AwaitingChain)
+            .collect();
+        if deferred.is_empty() || !self.fibonacci_ethereum_provider.is_available().await {
+            return Ok(0);
+        }
+
+        let submitted = deferred.len();
+        for mut job in deferred {
+            info!(job_id = %job.id, "The chain is back, submitting deferred job");
+            job.error = None;
+            job.transition(JobStatus::Submitting)?;
+            self.job_store.update(&job).await?;
+            self.spawn(job);
+        }
+
+        Ok(submitted)
+    }
+
+    /// Checks every `interval` for deferred jobs to submit, until the shutdown begins.
+    pub async fn run_submitter(self, interval: Duration) {
+        loop {
+            tokio::select! {
+                _ = tokio::time::sleep(interval) => {}
+                _ = self.shutdown.draining() => return,
+            }
+
+            match self.submit_deferred().await {
+                Ok(0) => {}
+                Ok(submitted) => info!(submitted, "Submitting deferred jobs"),
+                Err(e) => error!("Failed to submit deferred jobs: {}", e),
+            }
+        }
+    }
+
+    /// Removes expired archive entries every `interval`, until the shutdown begins.
+    pub async fn run_archive_pruner(self, interval: Duration) {
+        let Some(archive) = self.archive.clone() else {
+            return;
+        };
+
+        loop {
+            tokio::select! {
+                _ = tokio::time::sleep(interval) => {}
+                _ = self.shutdown.draining() => return,
+            }
+
+            let archive = archive.clone();
+            match tokio::task::spawn_blocking(move || archive.prune()).await {
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => error!("Failed to prune archived receipts: {}", e),
+                Err(e) => error!("Failed to prune archived receipts: {}", e),
+            }
+        }
+    }
+
+    /// Keeps the receipt of a sent proof for audits. The transaction is already sent, so failing
+    /// to archive it is only logged.
+    async fn archive(&self, job_id: JobId, proof: FibonacciProof, transaction_hash: TxHash) {
+        let Some(archive) = self.archive.clone() else {
+            return;
+        };
+
+        let archived =
+            tokio::task::spawn_blocking(move || archive.insert(&proof, job_id, transaction_hash))
+                .await;
+        match archived {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => error!(job_id = %job_id, "Failed to archive the receipt: {}", e),
+            Err(e) => error!(job_id = %job_id, "Failed to archive the receipt: {}", e),
+        }
+    }
+
+    async fn create_job(&self, iterations: u16, options: ProofOptions) -> Result<Job> {
+        if self.shutdown.is_draining() {
+            return Err(FibchainError::ShuttingDown);
+        }
+
+        if !options.receipt_kind.is_verifiable_on_chain() {
+            return Err(FibchainError::UnverifiableReceiptKind {
+                kind: options.receipt_kind.as_str(),
+            });
+        }
+        // Pinned on the job, so it keeps its version if the default one changes before it ends.
+        let image_id = match options.image_id {
+            Some(image_id) => image_id,
+            None => self.guest_image_id(None)?,
+        };
+        self.contract_for(image_id)?;
+
+        let mut job = Job::new(iterations);
+        job.options = ProofOptions {
+            image_id: Some(image_id),
+            ..options
+        };
+        self.prover_queue
+            .try_enqueue(job.id)
+            .map_err(|retry_after| FibchainError::ProverQueueFull { retry_after })?;
+        if let Err(e) = self.job_store.create(&job).await {
+            self.prover_queue.remove(job.id);
+            return Err(e);
+        }
+        self.events.publish(JobEvent::ProofRequested {
+            job_id: job.id,
+            iterations,
+        });
+
+        Ok(job)
+    }
+
+    fn spawn(&self, job: Job) {
+        let use_case = self.clone();
+        let job_id = job.id;
+
+        tokio::spawn(self.shutdown.track(async move {
+            match use_case.run(job).await {
+                Ok(_)
+                | Err(FibchainError::Cancelled)
+                | Err(FibchainError::JobCancelled { .. })
+                | Err(FibchainError::SubmissionDeferred { .. }) => {}
+                Err(e) => error!(job_id = %job_id, "Job failed: {}", e),
+            }
+        }));
+    }
+
+    /// Advances the job one stage at a time, persisting every transition, until it is confirmed,
+    /// fails or is cancelled by request. A job stopped by the shutdown resumes from its stage on
+    /// the next start, and a job whose submission keeps failing with retryable errors waits for
+    /// the chain.
+    #[instrument(skip(self, job), fields(job_id = %job.id, iterations = job.iterations))]
+    async fn run(&self, mut job: Job) -> Result<TxHash> {
+        let cancellation = self.shutdown.cancellation();
+        let registration = self.cancellations.register(job.id, cancellation.clone());
+        let mut prover = None;
+
+        loop {
+            match job.status {
+                JobStatus::Confirmed => {
+                    return job.transaction_hash.ok_or_else(|| {
+                        FibchainError::Storage(format!(
+                            "confirmed job {} has no transaction hash",
+                            job.id
+                        ))
+                    });
+                }
+                JobStatus::Failed => {
+                    return Err(FibchainError::Generic(color_eyre::eyre::eyre!(job
+                        .error
+                        .clone()
+                        .unwrap_or_default())));
+                }
+                JobStatus::AwaitingChain => {
+                    return Err(FibchainError::SubmissionDeferred { job_id: job.id });
+                }
+                JobStatus::Cancelled => {
+                    self.prover_queue.remove(job.id);
+                    info!(
+                        monotonic_counter.jobs_cancelled = 1_u64,
+                        "Job cancelled by request"
+                    );
+                    return Err(FibchainError::JobCancelled { job_id: job.id });
+                }
+                _ => {}
+            }
+
+            let cancel = match job.status {
+                JobStatus::Proved => !registration.start_submitting(),
+                status => status.is_cancellable() && registration.is_requested(),
+            };
+            let next = if cancel {
+                Ok(JobStatus::Cancelled)
+            } else {
+                self.advance(&mut job, &mut prover, &registration, &cancellation)
+                    .await
+            };
+
+            let next = match next {
+                Ok(next) => next,
+                Err(FibchainError::Cancelled)
+                    if job.status.is_cancellable() && registration.is_requested() =>
+                {
+                    JobStatus::Cancelled
+                }
+                // The sent transaction was replaced, wait for either of them.
+                Err(FibchainError::Cancelled)
+                    if matches!(job.status, JobStatus::Sent(_))
+                        && registration.replacement().is_some()
+                        && !self.shutdown.is_aborting() =>
+                {
+                    continue;
+                }
+                Err(FibchainError::Cancelled) => {
+                    warn!(status = job.status.as_str(), "Job cancelled");
+                    return Err(FibchainError::Cancelled);
+                }
+                Err(e) if job.status == JobStatus::Submitting && e.is_retryable() => {
+                    warn!(
+                        monotonic_counter.deferred_submissions = 1_u64,
+                        "The chain is unavailable, keeping the proof until it is back: {}", e
+                    );
+                    job.error = Some(e.to_string());
+                    job.transition(JobStatus::AwaitingChain)?;
+                    self.job_store.update(&job).await?;
+
+                    return Err(FibchainError::SubmissionDeferred { job_id: job.id });
+                }
+                Err(e) => {
+                    job.error = Some(e.to_string());
+                    job.transition(JobStatus::Failed)?;
+                    self.job_store.update(&job).await?;
+                    self.events.publish(JobEvent::JobFailed {
+                        job_id: job.id,
+                        code: e.code(),
+                    });
+
+                    return Err(e);
+                }
+            };
+
+            job.transition(next)?;
+            self.job_store.update(&job).await?;
+            info!(status = job.status.as_str(), "Job advanced");
+        }
+    }
+
+    /// Does the work of the job's current stage and returns the stage that follows it. Requested
+    /// jobs wait in the prover queue and hold on to their prover until the proof is generated.
+    async fn advance(
+        &self,
+        job: &mut Job,
+        prover: &mut Option<ProverPermit>,
+        registration: &Registration,
+        cancellation: &CancellationToken,
+    ) -> Result<JobStatus> {
+        match job.status {
+            JobStatus::Requested => {
+                let permit = tokio::select! {
+                    permit = self.prover_queue.wait_turn(job.id) => permit,
+                    _ = cancellation.cancelled() => return Err(FibchainError::Cancelled),
+                };
+                job.queue_wait_ms = Some(permit.waited().as_millis() as u64);
+                *prover = Some(permit);
+
+                Ok(JobStatus::Proving)
+            }
+            JobStatus::Proving => {
+                if prover.is_none() {
+                    *prover = Some(tokio::select! {
+                        permit = self.prover_queue.wait_turn(job.id) => permit,
+                        _ = cancellation.cancelled() => return Err(FibchainError::Cancelled),
+                    });
+                }
+                let (iterations, options) = (job.iterations, job.options);
+                let started_at = Instant::now();
+                let proof = self
+                    .with_retries(RetryStage::Proving, job, cancellation, || {
+                        self.fibonacci_risc_zero_provider.generate_proof(
+                            iterations,
+                            options,
+                            cancellation.clone(),
+                        )
+                    })
+                    .await?;
+                *prover = None;
+                self.events.publish(JobEvent::ProofGenerated {
+                    job_id: job.id,
+                    cycles: proof.cycles,
+                    duration: started_at.elapsed(),
+                });
+
+                job.proof = Some(proof);
+
+                Ok(JobStatus::Proved)
+            }
+            JobStatus::Proved => Ok(JobStatus::Submitting),
+            JobStatus::Submitting => {
+                let Some(proof) = job.proof.clone() else {
+                    return Err(FibchainError::Storage(format!(
+                        "job {} reached submission without a stored proof",
+                        job.id
+                    )));
+                };
+                let contract = self.contract_for(proof.image_id)?;
+
+                // Signed once and stored before it is broadcast, so broadcasting again after a lost
+                // answer or a restart cannot take a new nonce and send the proof twice.
+                let signed = match job.signed_transaction.clone() {
+                    Some(signed) => signed,
+                    None => {
+                        let signed = self
+                            .with_retries(RetryStage::Send, job, cancellation, || {
+                                self.fibonacci_ethereum_provider
+                                    .sign_increase_counter(contract, &proof)
+                            })
+                            .await?;
+                        job.signed_transaction = Some(signed.clone());
+                        self.job_store.update(job).await?;
+
+                        signed
+                    }
+                };
+                let sent = self
+                    .with_retries(RetryStage::Send, job, cancellation, || {
+                        self.fibonacci_ethereum_provider.broadcast(&signed)
+                    })
+                    .await?;
+                self.archive(job.id, proof, sent.transaction_hash).await;
+                self.events.publish(JobEvent::TxSent {
+                    job_id: job.id,
+                    hash: sent.transaction_hash,
+                    nonce: sent.nonce,
+                });
+
+                Ok(JobStatus::Sent(sent.transaction_hash))
+            }
+            JobStatus::Sent(transaction_hash) => {
+                let confirmation = match registration.replacement() {
+                    None => {
+                        let confirming =
+                            self.with_retries(RetryStage::Confirmation, job, cancellation, || {
+                                self.fibonacci_ethereum_provider
+                                    .wait_for_confirmation(transaction_hash)
+                            });
+                        tokio::select! {
+                            confirmation = confirming => confirmation?,
+                            _ = cancellation.cancelled() => return Err(FibchainError::Cancelled),
+                        }
+                    }
+                    Some(replacement) => {
+                        let (confirmation, replaced) = self
+                            .with_retries(
+                                RetryStage::Confirmation,
+                                job,
+                                &self.shutdown.cancellation(),
+                                || {
+                                    first_confirmed(
+                                        self.fibonacci_ethereum_provider
+                                            .wait_for_confirmation(transaction_hash),
+                                        self.fibonacci_ethereum_provider
+                                            .wait_for_confirmation(replacement),
+                                    )
+                                },
+                            )
+                            .await?;
+                        if replaced {
+                            info!(
+                                replacement_hash = hex::encode(replacement.0),
+                                "The replacement transaction was mined first"
+                            );
+                            return Ok(JobStatus::Cancelled);
+                        }
+
+                        confirmation
+                    }
+                };
+
+                self.events.publish(JobEvent::TxConfirmed {
+                    job_id: job.id,
+                    hash: confirmation.transaction_hash,
+                    block: confirmation.block_number,
+                    gas: confirmation.gas_used,
+                });
+                job.transaction_hash = Some(confirmation.transaction_hash);
+                job.block_number = confirmation.block_number;
+                job.gas_used = Some(confirmation.gas_used);
+
+                Ok(JobStatus::Confirmed)
+            }
+            JobStatus::AwaitingChain
+            | JobStatus::Confirmed
+            | JobStatus::Failed
+            | JobStatus::Cancelled => Err(FibchainError::InvalidJobTransition {
+                from: job.status.as_str(),
+                to: job.status.as_str(),
+            }),
+        }
+    }
+
+    /// Runs the stage's operation, retrying retryable errors as the stage's policy allows. Every
+    /// attempt gets its own span and is recorded on the job, which is persisted before each retry.
+    async fn with_retries<T, F, Fut>(
+        &self,
+        stage: RetryStage,
+        job: &mut Job,
+        cancellation: &CancellationToken,
+        mut operation: F,
+    ) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let policy = self.retry.policy(stage);
+        let started_at = Instant::now();
+
+        loop {
+            job.attempts += 1;
+            let attempt = job.attempts;
+            let error = match operation()
+                .instrument(info_span!("attempt", stage = stage.as_str(), attempt))
+                .await
+            {
+                Ok(value) => return Ok(value),
+                Err(e) => e,
+            };
+
+            let backoff = if error.is_retryable() {
+                policy.next_backoff(attempt, started_at.elapsed())
+            } else {
+                None
+            };
+            let Some(backoff) = backoff else {
+                return Err(error);
+            };
+
+            warn!(
+                monotonic_counter.job_retries = 1_u64,
+                stage = stage.as_str(),
+                attempt,
+                backoff_ms = backoff.as_millis() as u64,
+                "Attempt failed, retrying: {}",
+                error
+            );
+            job.retries += 1;
+            self.job_store.update(job).await?;
+
+            tokio::select! {
+                _ = tokio::time::sleep(backoff) => {}
+                _ = cancellation.cancelled() => return Err(FibchainError::Cancelled),
+            }
+        }
+    }
+}
+
+/// Waits for the first of two transactions to be confirmed, telling whether it was the second
+/// one. Fails once both failed.
+async fn first_confirmed(
+    first: impl Future<Output = Result<TransactionConfirmation>>,
+    second: impl Future<Output = Result<TransactionConfirmation>>,
+) -> Result<(TransactionConfirmation, bool)> {
+    tokio::pin!(first, second);
+    let (mut first_failed, mut second_failed) = (false, false);
+
+    loop {
+        tokio::select! {
+            result = &mut first, if !first_failed => match result {
+                Ok(confirmation) => return Ok((confirmation, false)),
+                Err(e) if second_failed => return Err(e),
+                Err(_) => first_failed = true,
+            },
+            result = &mut second, if !second_failed => match result {
+                Ok(confirmation) => return Ok((confirmation, true)),
+                Err(e) if first_failed => return Err(e),
+                Err(_) => second_failed = true,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::provider::{SentTransaction, SignedTransaction};
+    use crate::infra::retry::RetryPolicy;
+    use crate::infra::store::job_memory::InMemoryJobStore;
+    use crate::infra::store::job_sqlite::SqliteJobStore;
+    use alloy::providers::PendingTransactionError;
+    use alloy::transports::{RpcError, TransportErrorKind};
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use tokio::sync::Notify;
+
+    const TRANSACTION_HASH: TxHash = TxHash::repeat_byte(0x42);
+    const REPLACEMENT_HASH: TxHash = TxHash::repeat_byte(0x43);
+
+    #[derive(Default)]
+    struct Calls {
+        proofs: AtomicUsize,
+        /// Signed transactions, each with the next nonce.
+        signatures: AtomicUsize,
+        sends: AtomicUsize,
+        broadcasts: Mutex<Vec<TxHash>>,
+        confirmations: AtomicUsize,
+        /// Confirmations that fail with a retryable error before one succeeds.
+        flaky_confirmations: AtomicUsize,
+        /// Broadcasts that reach the node but fail with a retryable error before one succeeds.
+        flaky_sends: AtomicUsize,
+        chain_down: AtomicBool,
+        /// Sent transactions stay pending and can be replaced.
+        replaceable: AtomicBool,
+        /// A proof is cached for every input.
+        cached: AtomicBool,
+        /// The contract accepts the proofs of another guest.
+        stale_contract: AtomicBool,
+        /// Proving waits for `proof_released` once it has started.
+        hold_proofs: AtomicBool,
+        proof_released: Notify,
+    }
+
+    fn take_one(left: &AtomicUsize) -> bool {
+        left.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| {
+            left.checked_sub(1)
+        })
+        .is_ok()
+    }
+
+    struct FakeProvider(Arc<Calls>);
+
+    #[async_trait::async_trait]
+    impl IFibonacciRiscZeroProvider for FakeProvider {
+        async fn generate_proof(
+            &self,
+            iterations: u16,
+            options: ProofOptions,
+            _: CancellationToken,
+        ) -> Result<FibonacciProof> {
+            self.0.proofs.fetch_add(1, Ordering::SeqCst);
+            if self.0.hold_proofs.load(Ordering::SeqCst) {
+                self.0.proof_released.notified().await;
+            }
+            let proof = FibonacciProof::fake(iterations, options.receipt_kind, 65_536);
+            Ok(FibonacciProof {
+                image_id: options.image_id.unwrap_or(proof.image_id),
+                ..proof
+            })
+        }
+
+        async fn dry_run(&self, iterations: u16, _: Option<B256>) -> Result<ExecutionReport> {
+            Ok(ExecutionReport {
+                journal: iterations as u128,
+                segments: 1,
+                total_cycles: 65_536,
+                user_cycles: 12_000,
+                paging_cycles: 4_000,
+                reserved_cycles: 49_536,
+                duration: Duration::ZERO,
+            })
+        }
+
+        async fn cached_proof(
+            &self,
+            iterations: u16,
+            receipt_kind: ReceiptKind,
+            _: Option<B256>,
+        ) -> Option<FibonacciProof> {
+            self.0
+                .cached
+                .load(Ordering::SeqCst)
+                .then(|| FibonacciProof::fake(iterations, receipt_kind, 0))
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl IFibonacciEthereumProvider for FakeProvider {
+        async fn image_id(&self, _: Address) -> Result<B256> {
+            if self.0.stale_contract.load(Ordering::SeqCst) {
+                return Ok(B256::repeat_byte(0x02));
+            }
+            Ok(B256::repeat_byte(0x01))
+        }
+
+        async fn estimate_increase_counter_gas(
+            &self,
+            _: Address,
+            _: &FibonacciProof,
+        ) -> Result<u64> {
+            Ok(250_000)
+        }
+
+        fn approximate_increase_counter_gas(&self, _: u128) -> u64 {
+            280_000
+        }
+
+        async fn sign_increase_counter(
+            &self,
+            _: Address,
+            _: &FibonacciProof,
+        ) -> Result<SignedTransaction> {
+            let nonce = 7 + self.0.signatures.fetch_add(1, Ordering::SeqCst) as u64;
+            let transaction_hash = match nonce {
+                7 => TRANSACTION_HASH,
+                nonce => TxHash::with_last_byte(nonce as u8),
+            };
+            Ok(SignedTransaction {
+                transaction_hash,
+                nonce,
+                raw: nonce.to_be_bytes().to_vec(),
+            })
+        }
+
+        async fn broadcast(&self, transaction: &SignedTransaction) -> Result<SentTransaction> {
+            self.0.sends.fetch_add(1, Ordering::SeqCst);
+            self.0
+                .broadcasts
+                .lock()
+                .unwrap()
+                .push(transaction.transaction_hash);
+            if take_one(&self.0.flaky_sends) {
+                return Err(FibchainError::Alloy(
+                    alloy::contract::Error::TransportError(RpcError::Transport(
+                        TransportErrorKind::BackendGone,
+                    )),
+                ));
+            }
+            Ok(SentTransaction {
+                transaction_hash: transaction.transaction_hash,
+                nonce: transaction.nonce,
+            })
+        }
+
+        async fn wait_for_confirmation(
+            &self,
+            transaction_hash: TxHash,
+        ) -> Result<TransactionConfirmation> {
+            self.0.confirmations.fetch_add(1, Ordering::SeqCst);
+            if transaction_hash == TRANSACTION_HASH && self.0.replaceable.load(Ordering::SeqCst) {
+                std::future::pending::<()>().await;
+            }
+            if take_one(&self.0.flaky_confirmations) {
+                return Err(FibchainError::AlloyPendingTransaction(
+                    PendingTransactionError::FailedToRegister,
+                ));
+            }
+            Ok(TransactionConfirmation {
+                transaction_hash,
+                block_number: Some(7),
+                gas_used: 21_000,
+            })
+        }
+
+        async fn is_available(&self) -> bool {
+            !self.0.chain_down.load(Ordering::SeqCst)
+        }
+
+        async fn cancel_transaction(&self, _: TxHash) -> Result<Option<TxHash>> {
+            Ok(self
+                .0
+                .replaceable
+                .load(Ordering::SeqCst)
+                .then_some(REPLACEMENT_HASH))
+        }
+    }
+
+    fn use_case() -> (FibonacciGenerateNumberUseCase, Arc<Calls>) {
+        use_case_with_store(Box::pin(InMemoryJobStore::new()))
+    }
 
-        self.fibonacci_ethereum_provider
-            .increase_counter(fibonacci_number, seal)
+    fn use_case_with_store(
+        job_store: Pin<Box<dyn IJobStore + Sync + Send>>,
+    ) -> (FibonacciGenerateNumberUseCase, Arc<Calls>) {
+        let calls = Arc::new(Calls::default());
+        let use_case = FibonacciGenerateNumberUseCase::new(
+            Arc::new(Box::pin(FakeProvider(calls.clone()))),
+            Arc::new(Box::pin(FakeProvider(calls.clone()))),
+            Arc::new(job_store),
+            ProverQueue::new(1, 4),
+            Arc::new(GuestRegistry::fake()),
+            None,
+            RetryConfig {
+                proving: RetryPolicy::new(1, 0, 0, 60),
+                send: RetryPolicy::new(1, 0, 0, 60),
+                confirmation: RetryPolicy::new(3, 0, 0, 60),
+            },
+            Shutdown::new(),
+        );
+
+        (use_case, calls)
+    }
+
+    async fn stored_job(use_case: &FibonacciGenerateNumberUseCase, stages: &[JobStatus]) -> Job {
+        let mut job = Job::new(5);
+        use_case.job_store.create(&job).await.unwrap();
+        for stage in stages {
+            job.transition(*stage).unwrap();
+        }
+        job.proof = Some(FibonacciProof::fake(5, ReceiptKind::Groth16, 65_536));
+        use_case.job_store.update(&job).await.unwrap();
+
+        job
+    }
+
+    #[tokio::test]
+    async fn test_new_job_goes_through_every_stage() {
+        let (use_case, calls) = use_case();
+
+        let transaction_hash = use_case.execute(5, ProofOptions::default()).await.unwrap();
+
+        assert_eq!(transaction_hash, TRANSACTION_HASH);
+        assert_eq!(calls.proofs.load(Ordering::SeqCst), 1);
+        assert_eq!(calls.sends.load(Ordering::SeqCst), 1);
+        assert_eq!(calls.confirmations.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_executed_job_goes_on_when_the_caller_goes_away() {
+        let (use_case, calls) = use_case();
+        calls.hold_proofs.store(true, Ordering::SeqCst);
+        let caller = tokio::spawn({
+            let use_case = use_case.clone();
+            async move { use_case.execute(5, ProofOptions::default()).await }
+        });
+        while calls.proofs.load(Ordering::SeqCst) == 0 {
+            tokio::task::yield_now().await;
+        }
+
+        caller.abort();
+        assert!(caller.await.unwrap_err().is_cancelled());
+        calls.proof_released.notify_one();
+        use_case.shutdown.begin();
+
+        assert!(use_case.shutdown.drain(Duration::from_secs(5)).await);
+        assert_eq!(calls.sends.load(Ordering::SeqCst), 1);
+        assert_eq!(calls.confirmations.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_job_events_are_published_in_order() {
+        let (use_case, _) = use_case();
+        let mut events = use_case.subscribe();
+
+        use_case.execute(5, ProofOptions::default()).await.unwrap();
+
+        let mut names = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            match &event {
+                JobEvent::ProofGenerated { cycles, .. } => assert_eq!(*cycles, 65_536),
+                JobEvent::TxSent { hash, nonce, .. } => {
+                    assert_eq!((*hash, *nonce), (TRANSACTION_HASH, 7))
+                }
+                JobEvent::TxConfirmed { block, gas, .. } => {
+                    assert_eq!((*block, *gas), (Some(7), 21_000))
+                }
+                _ => {}
+            }
+            names.push(event.name());
+        }
+        assert_eq!(
+            names,
+            [
+                "proof_requested",
+                "proof_generated",
+                "tx_sent",
+                "tx_confirmed"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_gas_is_approximated_until_a_proof_is_cached() {
+        let (use_case, calls) = use_case();
+
+        let (report, gas) = use_case.estimate(5, None).await.unwrap();
+        assert_eq!(report.journal, 5);
+        assert_eq!(
+            gas,
+            GasEstimate {
+                gas: 280_000,
+                approximate: true
+            }
+        );
+
+        calls.cached.store(true, Ordering::SeqCst);
+        let (_, gas) = use_case.estimate(5, None).await.unwrap();
+        assert_eq!(
+            gas,
+            GasEstimate {
+                gas: 250_000,
+                approximate: false
+            }
+        );
+        assert_eq!(calls.proofs.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_only_groth16_receipts_are_submitted() {
+        let (use_case, calls) = use_case();
+        let options = ProofOptions {
+            receipt_kind: ReceiptKind::Succinct,
+            ..ProofOptions::default()
+        };
+
+        let result = use_case.submit(5, options).await;
+        assert!(
+            matches!(
+                result,
+                Err(FibchainError::UnverifiableReceiptKind { kind: "succinct" })
+            ),
+            "Expected an unverifiable receipt kind, got: {:?}",
+            result
+        );
+
+        let proof = use_case.prove(5, options).await.unwrap();
+        assert_eq!(proof.seal, None);
+        assert_eq!(calls.proofs.load(Ordering::SeqCst), 1);
+        assert_eq!(calls.sends.load(Ordering::SeqCst), 0);
+        assert_eq!(use_case.queue_length(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_sent_receipt_is_archived() {
+        let (mut use_case, _) = use_case();
+        let result = use_case
+            .archived_receipt(ArchiveLookup::Job(JobId::random()))
+            .await;
+        assert!(
+            matches!(result, Err(FibchainError::ArchiveDisabled)),
+            "Expected a disabled archive, got: {:?}",
+            result
+        );
+
+        let directory = std::env::temp_dir().join(format!("fibchain-archive-{}", JobId::random()));
+        use_case.archive = Some(Arc::new(ReceiptArchive::new(directory.clone(), None, None)));
+        let transaction_hash = use_case.execute(5, ProofOptions::default()).await.unwrap();
+
+        let archived = use_case
+            .archived_receipt(ArchiveLookup::Transaction(transaction_hash))
+            .await
+            .unwrap()
+            .expect("The receipt should be archived");
+        let job_id = archived.metadata.submissions[0].job_id;
+        let by_job = use_case
+            .archived_receipt(ArchiveLookup::Job(job_id))
             .await
+            .unwrap();
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(by_job, Some(archived.clone()));
+        assert_eq!(archived.seal, Some(vec![0xaa]));
+        assert_eq!(archived.metadata.fibonacci_number, "5");
+    }
+
+    #[tokio::test]
+    async fn test_submissions_are_refused_while_the_image_id_differs() {
+        let (use_case, calls) = use_case();
+        calls.stale_contract.store(true, Ordering::SeqCst);
+
+        let checked = use_case.check_image_ids().await;
+        assert!(
+            matches!(checked, Err(FibchainError::ImageIdMismatch { deployed, .. }) if deployed == B256::repeat_byte(0x02)),
+            "Expected an image id mismatch, got: {:?}",
+            checked
+        );
+        let submitted = use_case.submit(5, ProofOptions::default()).await;
+        assert!(
+            matches!(submitted, Err(FibchainError::ImageIdMismatch { .. })),
+            "Expected an image id mismatch, got: {:?}",
+            submitted
+        );
+        assert!(use_case.prove(5, ProofOptions::default()).await.is_ok());
+
+        calls.stale_contract.store(false, Ordering::SeqCst);
+        let deployed = use_case.check_image_ids().await.unwrap();
+        assert_eq!(deployed.len(), 1);
+        assert_eq!(deployed[0].image_id, B256::repeat_byte(0x01));
+        assert!(use_case.execute(5, ProofOptions::default()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_pinned_version_is_proven_and_submitted_only_when_deployed() {
+        let (use_case, _) = use_case();
+        let v2 = use_case.guest_image_id(Some("v2")).unwrap();
+        let options = ProofOptions {
+            image_id: Some(v2),
+            ..ProofOptions::default()
+        };
+
+        assert_eq!(use_case.prove(5, options).await.unwrap().image_id, v2);
+        let submitted = use_case.submit(5, options).await;
+        assert!(
+            matches!(submitted, Err(FibchainError::GuestNotDeployed { image_id }) if image_id == v2),
+            "Expected an undeployed guest, got: {:?}",
+            submitted
+        );
+        assert!(matches!(
+            use_case.guest_image_id(Some("v3")),
+            Err(FibchainError::UnknownGuestVersion { .. })
+        ));
+
+        let job = use_case.submit(5, ProofOptions::default()).await.unwrap();
+        assert_eq!(job.options.image_id, Some(B256::repeat_byte(0x01)));
+    }
+
+    #[tokio::test]
+    async fn test_full_prover_queue_rejects_new_jobs() {
+        let (use_case, calls) = use_case();
+        for _ in 0..4 {
+            use_case.prover_queue.try_enqueue(JobId::random()).unwrap();
+        }
+
+        let result = use_case.submit(5, ProofOptions::default()).await;
+
+        assert!(
+            matches!(result, Err(FibchainError::ProverQueueFull { .. })),
+            "Expected a full queue, got: {:?}",
+            result
+        );
+        assert_eq!(use_case.queue_length(), 4);
+        assert_eq!(calls.proofs.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_job_proved_with_the_first_schema_is_proved_again_and_submitted() {
+        let (use_case, calls) =
+            use_case_with_store(Box::pin(SqliteJobStore::with_first_schema_job(5)));
+        let job = use_case.job_store.unfinished().await.unwrap().remove(0);
+
+        let transaction_hash = use_case.run(job.clone()).await.unwrap();
+
+        assert_eq!(transaction_hash, TRANSACTION_HASH);
+        let (job, _) = use_case.job(job.id).await.unwrap().unwrap();
+        assert_eq!(job.status, JobStatus::Confirmed);
+        assert!(job.proof.is_some(), "The new proof should be stored");
+        assert_eq!(calls.proofs.load(Ordering::SeqCst), 1);
+        assert_eq!(calls.sends.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_proved_job_is_submitted_without_proving_again() {
+        let (use_case, calls) = use_case();
+        let job = stored_job(&use_case, &[JobStatus::Proving, JobStatus::Proved]).await;
+
+        use_case.run(job.clone()).await.unwrap();
+
+        let (job, _) = use_case.job(job.id).await.unwrap().unwrap();
+        assert_eq!(job.status, JobStatus::Confirmed);
+        assert_eq!(job.block_number, Some(7));
+        assert_eq!(calls.proofs.load(Ordering::SeqCst), 0);
+        assert_eq!(calls.sends.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_lost_broadcast_is_retried_with_the_same_nonce() {
+        let (mut use_case, calls) = use_case();
+        use_case.retry.send = RetryPolicy::new(3, 0, 0, 60);
+        calls.flaky_sends.store(1, Ordering::SeqCst);
+        let job = stored_job(&use_case, &[JobStatus::Proving, JobStatus::Proved]).await;
+
+        let transaction_hash = use_case.run(job).await.unwrap();
+
+        assert_eq!(transaction_hash, TRANSACTION_HASH);
+        assert_eq!(calls.signatures.load(Ordering::SeqCst), 1);
+        assert_eq!(
+            *calls.broadcasts.lock().unwrap(),
+            [TRANSACTION_HASH, TRANSACTION_HASH]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_submission_sends_the_stored_transaction() {
+        let (use_case, calls) = use_case();
+        let stored = TxHash::repeat_byte(0x01);
+        let mut job = stored_job(
+            &use_case,
+            &[JobStatus::Proving, JobStatus::Proved, JobStatus::Submitting],
+        )
+        .await;
+        job.signed_transaction = Some(SignedTransaction {
+            transaction_hash: stored,
+            nonce: 3,
+            raw: vec![0x02],
+        });
+        use_case.job_store.update(&job).await.unwrap();
+
+        assert_eq!(use_case.resume_unfinished().await.unwrap(), 1);
+        use_case.shutdown.begin();
+        assert!(use_case.shutdown.drain(Duration::from_secs(5)).await);
+
+        let (job, _) = use_case.job(job.id).await.unwrap().unwrap();
+        assert_eq!(job.status, JobStatus::Confirmed);
+        assert_eq!(job.transaction_hash, Some(stored));
+        assert_eq!(calls.signatures.load(Ordering::SeqCst), 0);
+        assert_eq!(*calls.broadcasts.lock().unwrap(), [stored]);
+    }
+
+    #[tokio::test]
+    async fn test_sent_job_waits_for_the_known_transaction() {
+        let (use_case, calls) = use_case();
+        let known = TxHash::repeat_byte(0x01);
+        let job = stored_job(
+            &use_case,
+            &[
+                JobStatus::Proving,
+                JobStatus::Proved,
+                JobStatus::Submitting,
+                JobStatus::Sent(known),
+            ],
+        )
+        .await;
+
+        let transaction_hash = use_case.run(job).await.unwrap();
+
+        assert_eq!(transaction_hash, known);
+        assert_eq!(calls.proofs.load(Ordering::SeqCst), 0);
+        assert_eq!(calls.sends.load(Ordering::SeqCst), 0);
+        assert_eq!(calls.confirmations.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_job_keeps_its_stage() {
+        let (use_case, calls) = use_case();
+        let _busy = use_case.prover_queue.wait_turn(JobId::random()).await;
+        let job = stored_job(&use_case, &[JobStatus::Proving]).await;
+
+        use_case.shutdown.abort();
+        let result = use_case.run(job.clone()).await;
+
+        assert!(matches!(result, Err(FibchainError::Cancelled)));
+        let (job, _) = use_case.job(job.id).await.unwrap().unwrap();
+        assert_eq!(job.status, JobStatus::Proving);
+        assert_eq!(job.error, None);
+        assert_eq!(calls.proofs.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_retryable_errors_are_retried_and_recorded() {
+        let (use_case, calls) = use_case();
+        calls.flaky_confirmations.store(2, Ordering::SeqCst);
+        let job = stored_job(
+            &use_case,
+            &[
+                JobStatus::Proving,
+                JobStatus::Proved,
+                JobStatus::Submitting,
+                JobStatus::Sent(TRANSACTION_HASH),
+            ],
+        )
+        .await;
+
+        use_case.run(job.clone()).await.unwrap();
+
+        let (job, _) = use_case.job(job.id).await.unwrap().unwrap();
+        assert_eq!(job.status, JobStatus::Confirmed);
+        assert_eq!(job.retries, 2);
+        assert_eq!(calls.confirmations.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_job_fails_once_attempts_are_used_up() {
+        let (use_case, calls) = use_case();
+        calls.flaky_confirmations.store(5, Ordering::SeqCst);
+        let job = stored_job(
+            &use_case,
+            &[
+                JobStatus::Proving,
+                JobStatus::Proved,
+                JobStatus::Submitting,
+                JobStatus::Sent(TRANSACTION_HASH),
+            ],
+        )
+        .await;
+
+        let mut events = use_case.subscribe();
+
+        assert!(use_case.run(job.clone()).await.is_err());
+
+        let (job, _) = use_case.job(job.id).await.unwrap().unwrap();
+        assert_eq!(job.status, JobStatus::Failed);
+        assert_eq!(job.attempts, 3);
+        assert_eq!(job.retries, 2);
+        assert_eq!(
+            events.try_recv().unwrap(),
+            JobEvent::JobFailed {
+                job_id: job.id,
+                code: "pending_transaction"
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_proof_is_kept_until_the_chain_is_back() {
+        let (use_case, calls) = use_case();
+        calls.flaky_sends.store(1, Ordering::SeqCst);
+        calls.chain_down.store(true, Ordering::SeqCst);
+        let job = stored_job(&use_case, &[JobStatus::Proving, JobStatus::Proved]).await;
+
+        let result = use_case.run(job.clone()).await;
+
+        assert!(
+            matches!(result, Err(FibchainError::SubmissionDeferred { .. })),
+            "Expected a deferred submission, got: {:?}",
+            result
+        );
+        let (deferred, _) = use_case.job(job.id).await.unwrap().unwrap();
+        assert_eq!(deferred.status, JobStatus::AwaitingChain);
+        assert_eq!(deferred.proof, job.proof);
+        assert_eq!(use_case.submit_deferred().await.unwrap(), 0);
+
+        calls.chain_down.store(false, Ordering::SeqCst);
+        assert_eq!(use_case.submit_deferred().await.unwrap(), 1);
+        for _ in 0..100 {
+            let (job, _) = use_case.job(job.id).await.unwrap().unwrap();
+            if job.status == JobStatus::Confirmed {
+                assert_eq!(job.error, None);
+                assert_eq!(calls.proofs.load(Ordering::SeqCst), 0);
+                assert_eq!(calls.sends.load(Ordering::SeqCst), 2);
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("Deferred job was not confirmed");
+    }
+
+    #[tokio::test]
+    async fn test_queued_job_is_cancelled() {
+        let (use_case, calls) = use_case();
+        let _busy = use_case.prover_queue.wait_turn(JobId::random()).await;
+        let job = use_case.submit(5, ProofOptions::default()).await.unwrap();
+
+        use_case.cancel(job.id).await.unwrap().unwrap();
+
+        for _ in 0..100 {
+            let (job, _) = use_case.job(job.id).await.unwrap().unwrap();
+            if job.status == JobStatus::Cancelled {
+                assert_eq!(use_case.queue_length(), 0);
+                assert_eq!(calls.proofs.load(Ordering::SeqCst), 0);
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("Queued job was not cancelled");
+    }
+
+    #[tokio::test]
+    async fn test_submitting_job_refuses_cancellation() {
+        let (use_case, _) = use_case();
+        let submitting = stored_job(
+            &use_case,
+            &[JobStatus::Proving, JobStatus::Proved, JobStatus::Submitting],
+        )
+        .await;
+        let sent = stored_job(
+            &use_case,
+            &[
+                JobStatus::Proving,
+                JobStatus::Proved,
+                JobStatus::Submitting,
+                JobStatus::Sent(TRANSACTION_HASH),
+            ],
+        )
+        .await;
+
+        for job in [submitting, sent] {
+            let result = use_case.cancel(job.id).await;
+            assert!(
+                matches!(result, Err(FibchainError::NotCancellable { .. })),
+                "Expected the cancellation to be refused, got: {:?}",
+                result
+            );
+        }
+        assert!(use_case.cancel(JobId::random()).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_sent_job_is_cancelled_by_replacing_its_transaction() {
+        let (use_case, calls) = use_case();
+        calls.replaceable.store(true, Ordering::SeqCst);
+        let job = stored_job(
+            &use_case,
+            &[
+                JobStatus::Proving,
+                JobStatus::Proved,
+                JobStatus::Submitting,
+                JobStatus::Sent(TRANSACTION_HASH),
+            ],
+        )
+        .await;
+        let running = tokio::spawn({
+            let use_case = use_case.clone();
+            let job = job.clone();
+            async move { use_case.run(job).await }
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        use_case.cancel(job.id).await.unwrap().unwrap();
+
+        assert!(matches!(
+            running.await.unwrap(),
+            Err(FibchainError::JobCancelled { .. })
+        ));
+        let (job, _) = use_case.job(job.id).await.unwrap().unwrap();
+        assert_eq!(job.status, JobStatus::Cancelled);
+        assert_eq!(job.transaction_hash, Some(TRANSACTION_HASH));
     }
 }
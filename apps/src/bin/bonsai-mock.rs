@@ -0,0 +1,59 @@
+use apps::infra::bonsai_mock::BonsaiMock;
+use apps::infra::config::ProverBackend;
+use clap::Parser;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tracing::info;
+
+/// Command line flags of the Bonsai mock.
+#[derive(Debug, Parser)]
+#[command(
+    version,
+    about = "Serves the Bonsai REST API from this machine, for tests and offline development"
+)]
+struct MockCli {
+    /// Address the mock binds to.
+    #[arg(
+        long,
+        env = "BONSAI_MOCK_LISTEN_ADDR",
+        default_value = "127.0.0.1:8091"
+    )]
+    listen_addr: SocketAddr,
+
+    /// Prover backend the sessions are proven with, `local` or `dev_mode`.
+    #[arg(long, default_value = "dev_mode")]
+    backend: ProverBackend,
+}
+
+#[tokio::main]
+pub async fn main() -> color_eyre::Result<()> {
+    color_eyre::install().ok();
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info")),
+        )
+        .init();
+    let cli = MockCli::parse();
+    if !matches!(cli.backend, ProverBackend::Local | ProverBackend::DevMode) {
+        color_eyre::eyre::bail!(
+            "the Bonsai mock proves with `local` or `dev_mode`, not `{}`",
+            cli.backend.as_str()
+        );
+    }
+
+    let listener = tokio::net::TcpListener::bind(cli.listen_addr).await?;
+    let url = url::Url::parse(&format!("http://{}", listener.local_addr()?))?;
+    let mock = Arc::new(BonsaiMock::new(url.clone(), cli.backend));
+    info!(
+        url = %url,
+        backend = cli.backend.as_str(),
+        "Bonsai mock started, point BONSAI_API_URL to it"
+    );
+
+    axum::serve(listener, mock.routes())
+        .with_graceful_shutdown(apps::infra::shutdown::signal())
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,69 @@
+use apps::infra::config::{Cli, Config, ProverBackend};
+use apps::infra::guest_registry::GuestRegistry;
+use apps::infra::observability;
+use apps::infra::prover_worker::ProverWorker;
+use apps::infra::shutdown;
+use clap::Parser;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// Command line flags of the prover worker. The prover, guests and telemetry are configured with
+/// the same file and variables as the service.
+#[derive(Debug, Parser)]
+#[command(
+    version,
+    about = "Generates RISC Zero proofs for a fibchain service using the `remote` prover backend"
+)]
+struct WorkerCli {
+    /// Path to the TOML config file. Defaults to `fibchain.toml` when it exists.
+    #[arg(long, env = "FIBCHAIN_CONFIG")]
+    config: Option<std::path::PathBuf>,
+
+    /// Address the worker binds to.
+    #[arg(long, env = "WORKER_LISTEN_ADDR", default_value = "127.0.0.1:8090")]
+    listen_addr: SocketAddr,
+
+    /// Log filter directives, using the `RUST_LOG` syntax.
+    #[arg(long)]
+    log_filter: Option<String>,
+}
+
+#[tokio::main]
+pub async fn main() -> color_eyre::Result<()> {
+    dotenvy::dotenv().ok();
+    color_eyre::install().ok();
+    let cli = WorkerCli::parse();
+
+    let config = Config::load_worker(&Cli {
+        config: cli.config,
+        log_filter: cli.log_filter,
+        ..Cli::default()
+    })?;
+    let _guard = observability::setup_tracing(&config.telemetry);
+    if config.prover.backend == ProverBackend::DevMode {
+        warn!("Proofs are faked by the dev_mode prover backend, only a mock verifier accepts them");
+    }
+    let guests = Arc::new(GuestRegistry::load(&config)?);
+
+    // Proofs queue on the worker like on the service, past that the host tries another worker.
+    let worker = Arc::new(ProverWorker::new(
+        config.prover.backend,
+        guests,
+        config.prover.workers,
+        config.prover.workers + config.prover.max_queued,
+    ));
+    let listener = tokio::net::TcpListener::bind(cli.listen_addr).await?;
+    info!(
+        listen_addr = %cli.listen_addr,
+        backend = config.prover.backend.as_str(),
+        threads = config.prover.workers,
+        "Prover worker started"
+    );
+
+    axum::serve(listener, worker.routes())
+        .with_graceful_shutdown(shutdown::signal())
+        .await?;
+
+    Ok(())
+}
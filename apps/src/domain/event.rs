@@ -0,0 +1,108 @@
+use crate::domain::job::JobId;
+use alloy_primitives::TxHash;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+/// Something that happened to a job on its way to the chain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JobEvent {
+    ProofRequested {
+        job_id: JobId,
+        iterations: u16,
+    },
+    ProofGenerated {
+        job_id: JobId,
+        /// Cycles the zkVM ran, `0` when a cached proof was reused.
+        cycles: u64,
+        /// Time spent proving, retries included.
+        duration: Duration,
+    },
+    TxSent {
+        job_id: JobId,
+        hash: TxHash,
+        nonce: u64,
+    },
+    TxConfirmed {
+        job_id: JobId,
+        hash: TxHash,
+        block: Option<u64>,
+        gas: u64,
+    },
+    JobFailed {
+        job_id: JobId,
+        /// Kind of the error that failed the job, see [`crate::prelude::FibchainError::code`].
+        code: &'static str,
+    },
+}
+
+impl JobEvent {
+    pub fn job_id(&self) -> JobId {
+        match self {
+            JobEvent::ProofRequested { job_id, .. }
+            | JobEvent::ProofGenerated { job_id, .. }
+            | JobEvent::TxSent { job_id, .. }
+            | JobEvent::TxConfirmed { job_id, .. }
+            | JobEvent::JobFailed { job_id, .. } => *job_id,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            JobEvent::ProofRequested { .. } => "proof_requested",
+            JobEvent::ProofGenerated { .. } => "proof_generated",
+            JobEvent::TxSent { .. } => "tx_sent",
+            JobEvent::TxConfirmed { .. } => "tx_confirmed",
+            JobEvent::JobFailed { .. } => "job_failed",
+        }
+    }
+}
+
+/// Broadcasts job events to in-process subscribers, which should subscribe at startup to see
+/// every event. A subscriber falling more than the capacity behind misses the oldest events.
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<JobEvent>,
+}
+
+impl EventBus {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+
+        Self { sender }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<JobEvent> {
+        self.sender.subscribe()
+    }
+
+    /// Sends the event to every current subscriber, dropping it when there is none.
+    pub fn publish(&self, event: JobEvent) {
+        let _ = self.sender.send(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_every_subscriber_receives_published_events() {
+        let bus = EventBus::new(8);
+        bus.publish(JobEvent::ProofRequested {
+            job_id: JobId::random(),
+            iterations: 5,
+        });
+        let mut first = bus.subscribe();
+        let mut second = bus.subscribe();
+
+        let event = JobEvent::JobFailed {
+            job_id: JobId::random(),
+            code: "zkvm",
+        };
+        bus.publish(event.clone());
+
+        assert_eq!(first.recv().await.unwrap(), event);
+        assert_eq!(second.recv().await.unwrap(), event);
+        assert!(first.try_recv().is_err());
+    }
+}
@@ -0,0 +1,290 @@
+use crate::domain::provider::{FibonacciProof, ProofOptions, SignedTransaction};
+use crate::prelude::FibchainError;
+use alloy_primitives::TxHash;
+use alloy_sol_types::SolValue;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
+pub struct JobId(Uuid);
+
+impl JobId {
+    pub fn random() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+impl Display for JobId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.0.hyphenated().fmt(f)
+    }
+}
+
+impl FromStr for JobId {
+    type Err = uuid::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Uuid::parse_str(value).map(Self)
+    }
+}
+
+/// Stage a job is in. Every job starts as `Requested` and moves forward one stage at a time until
+/// it is `Confirmed`, or `Failed` from any unfinished stage. Users may cancel it until it starts
+/// submitting, or once sent by replacing its transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobStatus {
+    Requested,
+    Proving,
+    Proved,
+    Submitting,
+    /// The proof is kept until the chain is reachable again to submit it.
+    AwaitingChain,
+    Sent(TxHash),
+    Confirmed,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobStatus::Requested => "requested",
+            JobStatus::Proving => "proving",
+            JobStatus::Proved => "proved",
+            JobStatus::Submitting => "submitting",
+            JobStatus::AwaitingChain => "awaiting_chain",
+            JobStatus::Sent(_) => "sent",
+            JobStatus::Confirmed => "confirmed",
+            JobStatus::Failed => "failed",
+            JobStatus::Cancelled => "cancelled",
+        }
+    }
+
+    /// Rebuilds a status from its name and, for `sent`, the hash of the sent transaction.
+    pub fn from_parts(name: &str, transaction_hash: Option<TxHash>) -> Result<Self, String> {
+        match (name, transaction_hash) {
+            ("requested", _) => Ok(JobStatus::Requested),
+            ("proving", _) => Ok(JobStatus::Proving),
+            ("proved", _) => Ok(JobStatus::Proved),
+            ("submitting", _) => Ok(JobStatus::Submitting),
+            ("awaiting_chain", _) => Ok(JobStatus::AwaitingChain),
+            ("sent", Some(transaction_hash)) => Ok(JobStatus::Sent(transaction_hash)),
+            ("sent", None) => Err("job status `sent` requires a transaction hash".to_string()),
+            ("confirmed", _) => Ok(JobStatus::Confirmed),
+            ("failed", _) => Ok(JobStatus::Failed),
+            ("cancelled", _) => Ok(JobStatus::Cancelled),
+            (other, _) => Err(format!("unknown job status `{}`", other)),
+        }
+    }
+
+    pub fn transaction_hash(&self) -> Option<TxHash> {
+        match self {
+            JobStatus::Sent(transaction_hash) => Some(*transaction_hash),
+            _ => None,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            JobStatus::Confirmed | JobStatus::Failed | JobStatus::Cancelled
+        )
+    }
+
+    /// Whether the job can be cancelled without touching the chain.
+    pub fn is_cancellable(&self) -> bool {
+        matches!(
+            self,
+            JobStatus::Requested | JobStatus::Proving | JobStatus::Proved
+        )
+    }
+
+    pub fn can_transition_to(&self, next: &JobStatus) -> bool {
+        matches!(
+            (self, next),
+            (JobStatus::Requested, JobStatus::Proving)
+                | (JobStatus::Proving, JobStatus::Proved)
+                | (JobStatus::Proved, JobStatus::Submitting)
+                | (JobStatus::Submitting, JobStatus::Sent(_))
+                | (JobStatus::Submitting, JobStatus::AwaitingChain)
+                | (JobStatus::AwaitingChain, JobStatus::Submitting)
+                | (JobStatus::Sent(_), JobStatus::Confirmed)
+                | (JobStatus::Sent(_), JobStatus::Cancelled)
+        ) || (!self.is_finished() && *next == JobStatus::Failed)
+            || (self.is_cancellable() && *next == JobStatus::Cancelled)
+    }
+}
+
+/// A request to prove a fibonacci number and publish it on-chain, along with everything produced
+/// on the way so the work survives restarts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Job {
+    pub id: JobId,
+    pub iterations: u16,
+    /// ABI encoded guest input.
+    pub input: Vec<u8>,
+    pub options: ProofOptions,
+    pub status: JobStatus,
+    pub proof: Option<FibonacciProof>,
+    pub transaction_hash: Option<TxHash>,
+    /// The proof submission, kept from before it is broadcast so an interrupted job sends the
+    /// same transaction again.
+    pub signed_transaction: Option<SignedTransaction>,
+    pub block_number: Option<u64>,
+    pub gas_used: Option<u64>,
+    pub error: Option<String>,
+    /// Time spent waiting for a free prover, in milliseconds.
+    pub queue_wait_ms: Option<u64>,
+    /// Attempts made by the current stage, kept when the job fails.
+    pub attempts: u32,
+    /// Attempts retried over every stage.
+    pub retries: u32,
+    /// Unix timestamp in milliseconds.
+    pub created_at: u64,
+    /// Unix timestamp in milliseconds.
+    pub updated_at: u64,
+}
+
+impl Job {
+    pub fn new(iterations: u16) -> Self {
+        let now = now_millis();
+
+        Self {
+            id: JobId::random(),
+            iterations,
+            input: iterations.abi_encode(),
+            options: ProofOptions::default(),
+            status: JobStatus::Requested,
+            proof: None,
+            transaction_hash: None,
+            signed_transaction: None,
+            block_number: None,
+            gas_used: None,
+            error: None,
+            queue_wait_ms: None,
+            attempts: 0,
+            retries: 0,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// Moves the job to its next stage, refusing transitions the lifecycle does not allow.
+    pub fn transition(&mut self, next: JobStatus) -> crate::prelude::Result<()> {
+        if !self.status.can_transition_to(&next) {
+            return Err(FibchainError::InvalidJobTransition {
+                from: self.status.as_str(),
+                to: next.as_str(),
+            });
+        }
+
+        if let Some(transaction_hash) = next.transaction_hash() {
+            self.transaction_hash = Some(transaction_hash);
+        }
+        if next != JobStatus::Failed {
+            self.attempts = 0;
+        }
+        self.status = next;
+        self.updated_at = now_millis();
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JobTransition {
+    pub status: JobStatus,
+    /// Unix timestamp in milliseconds.
+    pub at: u64,
+}
+
+pub fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_job_moves_through_every_stage() {
+        let transaction_hash = TxHash::repeat_byte(0x01);
+        let mut job = Job::new(5);
+
+        for next in [
+            JobStatus::Proving,
+            JobStatus::Proved,
+            JobStatus::Submitting,
+            JobStatus::Sent(transaction_hash),
+            JobStatus::Confirmed,
+        ] {
+            job.transition(next).unwrap();
+        }
+
+        assert_eq!(job.status, JobStatus::Confirmed);
+        assert_eq!(job.transaction_hash, Some(transaction_hash));
+    }
+
+    #[test]
+    fn test_deferred_submission_goes_back_to_submitting() {
+        let mut job = Job::new(5);
+        for next in [
+            JobStatus::Proving,
+            JobStatus::Proved,
+            JobStatus::Submitting,
+            JobStatus::AwaitingChain,
+        ] {
+            job.transition(next).unwrap();
+        }
+
+        assert!(!job.status.is_finished());
+        assert!(job.transition(JobStatus::Sent(TxHash::ZERO)).is_err());
+        job.transition(JobStatus::Submitting).unwrap();
+    }
+
+    #[test]
+    fn test_job_is_only_cancelled_before_submitting_or_once_sent() {
+        let mut job = Job::new(5);
+        job.transition(JobStatus::Proving).unwrap();
+        job.transition(JobStatus::Cancelled).unwrap();
+        assert!(job.status.is_finished());
+
+        let mut job = Job::new(5);
+        for next in [JobStatus::Proving, JobStatus::Proved, JobStatus::Submitting] {
+            job.transition(next).unwrap();
+        }
+        assert!(job.transition(JobStatus::Cancelled).is_err());
+        job.transition(JobStatus::Sent(TxHash::ZERO)).unwrap();
+        job.transition(JobStatus::Cancelled).unwrap();
+    }
+
+    #[test]
+    fn test_job_refuses_skipping_stages_and_leaving_final_ones() {
+        let mut job = Job::new(5);
+
+        assert!(job.transition(JobStatus::Submitting).is_err());
+        assert_eq!(job.status, JobStatus::Requested);
+
+        job.transition(JobStatus::Failed).unwrap();
+        assert!(job.transition(JobStatus::Failed).is_err());
+        assert!(job.transition(JobStatus::Proving).is_err());
+    }
+
+    #[test]
+    fn test_status_round_trips_through_its_parts() {
+        let sent = JobStatus::Sent(TxHash::repeat_byte(0x02));
+
+        assert_eq!(
+            JobStatus::from_parts(sent.as_str(), sent.transaction_hash()),
+            Ok(sent)
+        );
+        assert!(JobStatus::from_parts("sent", None).is_err());
+        assert!(JobStatus::from_parts("pending", None).is_err());
+    }
+}
@@ -1 +1,4 @@
+pub mod event;
+pub mod job;
 pub mod provider;
+pub mod store;
@@ -1,12 +1,276 @@
 use crate::prelude::*;
-use alloy_primitives::TxHash;
+use alloy_primitives::{Address, TxHash, B256};
+use risc0_zkvm::Receipt;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+/// A proof submission signed with a fixed nonce. Broadcasting it again cannot send the proof
+/// twice, so it is kept until the node has it.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SignedTransaction {
+    pub transaction_hash: TxHash,
+    pub nonce: u64,
+    /// EIP-2718 encoding, as `eth_sendRawTransaction` takes it.
+    pub raw: Vec<u8>,
+}
+
+/// A transaction accepted by the node, not mined yet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SentTransaction {
+    pub transaction_hash: TxHash,
+    pub nonce: u64,
+}
+
+/// Receipt details of a transaction that reached the required confirmations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransactionConfirmation {
+    pub transaction_hash: TxHash,
+    pub block_number: Option<u64>,
+    pub gas_used: u64,
+}
 
 #[async_trait::async_trait]
 pub trait IFibonacciEthereumProvider {
-    async fn increase_counter(&self, fibonacci_number: u128, seal: Vec<u8>) -> Result<TxHash>;
+    /// Image id of the guest whose proofs the contract accepts.
+    async fn image_id(&self, contract: Address) -> Result<B256>;
+
+    /// Gas `increaseCounter` would use with this proof, which fails when the verifier rejects it.
+    async fn estimate_increase_counter_gas(
+        &self,
+        contract: Address,
+        proof: &FibonacciProof,
+    ) -> Result<u64>;
+
+    /// Gas `increaseCounter` would roughly use with any valid Groth16 seal, for inputs that have
+    /// no proof yet. It does not reach the chain.
+    fn approximate_increase_counter_gas(&self, journal: u128) -> u64;
+
+    /// Signs the submission of the proof to the contract at the wallet's next nonce, without
+    /// sending it.
+    async fn sign_increase_counter(
+        &self,
+        contract: Address,
+        proof: &FibonacciProof,
+    ) -> Result<SignedTransaction>;
+
+    /// Sends a signed transaction without waiting for it to be mined. It succeeds when the node
+    /// already has the transaction, so a broadcast whose answer was lost can be retried.
+    async fn broadcast(&self, transaction: &SignedTransaction) -> Result<SentTransaction>;
+
+    /// Waits until a sent transaction has the required confirmations.
+    async fn wait_for_confirmation(
+        &self,
+        transaction_hash: TxHash,
+    ) -> Result<TransactionConfirmation>;
+
+    /// Whether the chain answers requests, used to know when deferred submissions can resume.
+    async fn is_available(&self) -> bool;
+
+    /// Replaces a sent transaction with an empty one at the same nonce and higher fees, returning
+    /// the replacement's hash, or `None` when replacing is disabled or the transaction is mined.
+    async fn cancel_transaction(&self, transaction_hash: TxHash) -> Result<Option<TxHash>>;
+}
+
+/// Where and when a proof was generated.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ProverMetadata {
+    /// Prover backend, as named in the configuration.
+    pub backend: String,
+    pub zkvm_version: String,
+    /// Unix timestamp in milliseconds.
+    pub generated_at: u64,
+    pub duration_ms: u64,
+}
+
+/// A proof that the guest computed a fibonacci number, with the receipt it was extracted from so
+/// it can be verified again or submitted later.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct FibonacciProof {
+    pub iterations: u16,
+    /// ABI encoded guest input.
+    pub input: Vec<u8>,
+    /// Fibonacci number decoded from the journal.
+    pub journal: u128,
+    pub journal_bytes: Vec<u8>,
+    /// Seal the contract verifies, only set for [`ReceiptKind::Groth16`] receipts.
+    pub seal: Option<Vec<u8>>,
+    /// Image id of the guest that was proven.
+    pub image_id: B256,
+    pub receipt_kind: ReceiptKind,
+    pub receipt: Receipt,
+    /// Cycles the zkVM ran, `0` when a cached proof was reused.
+    pub cycles: u64,
+    pub prover: ProverMetadata,
+}
+
+impl FibonacciProof {
+    /// The seal to submit on-chain, refused for receipts the contract cannot verify.
+    pub fn on_chain_seal(&self) -> Result<&[u8]> {
+        self.seal
+            .as_deref()
+            .ok_or(FibchainError::UnverifiableReceiptKind {
+                kind: self.receipt_kind.as_str(),
+            })
+    }
+}
+
+/// Receipts are not comparable, the seal and journal already tell two proofs apart.
+impl PartialEq for FibonacciProof {
+    fn eq(&self, other: &Self) -> bool {
+        self.iterations == other.iterations
+            && self.input == other.input
+            && self.journal_bytes == other.journal_bytes
+            && self.seal == other.seal
+            && self.image_id == other.image_id
+            && self.receipt_kind == other.receipt_kind
+            && self.cycles == other.cycles
+            && self.prover == other.prover
+    }
+}
+
+/// Result of executing the guest without proving it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecutionReport {
+    pub journal: u128,
+    pub segments: usize,
+    /// Cycles a prover would go through, padding and paging included.
+    pub total_cycles: u64,
+    /// Cycles the guest code itself ran.
+    pub user_cycles: u64,
+    pub paging_cycles: u64,
+    pub reserved_cycles: u64,
+    pub duration: Duration,
+}
+
+/// Gas submitting a proof would use, estimated against the contract once a proof exists and
+/// approximated before that.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GasEstimate {
+    pub gas: u64,
+    pub approximate: bool,
+}
+
+/// Kind of receipt a proof is wrapped in, from the largest and quickest to generate to the
+/// smallest, which is the only one the contract verifies.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiptKind {
+    /// One receipt per segment.
+    Composite,
+    /// Segments compressed into a single STARK receipt.
+    Succinct,
+    /// A succinct receipt wrapped in a Groth16 SNARK, verifiable on-chain.
+    #[default]
+    Groth16,
+}
+
+impl ReceiptKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Composite => "composite",
+            Self::Succinct => "succinct",
+            Self::Groth16 => "groth16",
+        }
+    }
+
+    pub fn is_verifiable_on_chain(&self) -> bool {
+        *self == Self::Groth16
+    }
+}
+
+/// Per request settings for generating a proof.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProofOptions {
+    /// Proves again instead of reusing a cached proof for the same input.
+    pub bypass_cache: bool,
+    pub receipt_kind: ReceiptKind,
+    /// Image id of the guest version to prove, the default version when unset.
+    pub image_id: Option<B256>,
 }
 
 #[async_trait::async_trait]
 pub trait IFibonacciRiscZeroProvider {
-    async fn generate_proof(&self, iterations: u16) -> Result<(Vec<u8>, u128)>;
+    /// Proves the computation, giving up with [`FibchainError::Cancelled`] once `cancellation`
+    /// is cancelled.
+    async fn generate_proof(
+        &self,
+        iterations: u16,
+        options: ProofOptions,
+        cancellation: CancellationToken,
+    ) -> Result<FibonacciProof>;
+
+    /// Executes the guest version without proving it, to know what proving would cost.
+    async fn dry_run(&self, iterations: u16, image_id: Option<B256>) -> Result<ExecutionReport>;
+
+    /// A previously generated proof for the same input, receipt kind and guest version, if one
+    /// is cached.
+    async fn cached_proof(
+        &self,
+        iterations: u16,
+        receipt_kind: ReceiptKind,
+        image_id: Option<B256>,
+    ) -> Option<FibonacciProof>;
+}
+
+#[cfg(test)]
+impl FibonacciProof {
+    /// A proof with a fake receipt, and a seal for receipts verifiable on-chain.
+    pub fn fake(iterations: u16, receipt_kind: ReceiptKind, cycles: u64) -> Self {
+        use alloy_sol_types::SolValue;
+        use risc0_zkvm::sha::Digest;
+        use risc0_zkvm::{FakeReceipt, InnerReceipt, MaybePruned};
+
+        let journal = iterations as u128;
+        let claim = MaybePruned::Pruned(Digest::ZERO);
+
+        Self {
+            iterations,
+            input: iterations.abi_encode(),
+            journal,
+            journal_bytes: journal.abi_encode(),
+            seal: receipt_kind.is_verifiable_on_chain().then(|| vec![0xaa]),
+            image_id: B256::repeat_byte(0x01),
+            receipt_kind,
+            receipt: Receipt::new(
+                InnerReceipt::Fake(FakeReceipt::new(claim)),
+                journal.abi_encode(),
+            ),
+            cycles,
+            prover: ProverMetadata {
+                backend: "local".to_string(),
+                zkvm_version: risc0_zkvm::VERSION.to_string(),
+                generated_at: 1_700_000_000_000,
+                duration_ms: 1_500,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_proof_round_trips_through_bincode() {
+        let proof = FibonacciProof::fake(10, ReceiptKind::Groth16, 65_536);
+
+        let decoded: FibonacciProof =
+            bincode::deserialize(&bincode::serialize(&proof).unwrap()).unwrap();
+
+        assert_eq!(decoded, proof);
+        assert_eq!(decoded.journal, 10);
+        assert_eq!(decoded.receipt.journal.bytes, proof.journal_bytes);
+    }
+
+    #[test]
+    fn test_only_groth16_proofs_have_an_on_chain_seal() {
+        let groth16 = FibonacciProof::fake(10, ReceiptKind::Groth16, 0);
+        let succinct = FibonacciProof::fake(10, ReceiptKind::Succinct, 0);
+
+        assert_eq!(groth16.on_chain_seal().unwrap(), [0xaa]);
+        assert!(matches!(
+            succinct.on_chain_seal(),
+            Err(FibchainError::UnverifiableReceiptKind { kind: "succinct" })
+        ));
+    }
 }
@@ -0,0 +1,19 @@
+use crate::domain::job::{Job, JobId, JobTransition};
+use crate::prelude::*;
+
+#[async_trait::async_trait]
+pub trait IJobStore {
+    /// Persists a new job and records its initial status as the first transition.
+    async fn create(&self, job: &Job) -> Result<()>;
+
+    /// Persists the job, recording a transition when its status changed.
+    async fn update(&self, job: &Job) -> Result<()>;
+
+    async fn get(&self, id: JobId) -> Result<Option<Job>>;
+
+    /// Status transitions of a job, oldest first.
+    async fn transitions(&self, id: JobId) -> Result<Vec<JobTransition>>;
+
+    /// Jobs that have not completed or failed yet, oldest first.
+    async fn unfinished(&self) -> Result<Vec<Job>>;
+}
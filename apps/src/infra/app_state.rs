@@ -1,44 +1,101 @@
 use crate::app::use_case::fibonacci::FibonacciGenerateNumberUseCase;
+use crate::domain::provider::IFibonacciRiscZeroProvider;
+use crate::domain::store::IJobStore;
+use crate::infra::config::{Config, ProverBackend, StorageBackend};
+use crate::infra::guest_registry::GuestRegistry;
+use crate::infra::prover_queue::ProverQueue;
 use crate::infra::provider::fibonacci_ethereum::FibonacciEthereumProvider;
+use crate::infra::provider::fibonacci_remote::FibonacciRemoteProvider;
 use crate::infra::provider::fibonacci_risc_zero::FibonacciRiscZeroProvider;
+use crate::infra::rate_limit::RateLimiter;
+use crate::infra::receipt_archive::ReceiptArchive;
+use crate::infra::shutdown::Shutdown;
+use crate::infra::store::job_memory::InMemoryJobStore;
+use crate::infra::store::job_sqlite::SqliteJobStore;
+use crate::prelude::*;
 use alloy::network::EthereumWallet;
 use alloy::signers::local::PrivateKeySigner;
-use alloy_primitives::Address;
+use axum::extract::FromRef;
 use std::pin::Pin;
-use std::str::FromStr;
 use std::sync::Arc;
+use tokio::sync::watch;
 
-#[derive(Clone)]
+#[derive(Clone, FromRef)]
 pub struct AppState {
     pub fibonacci_number_generator: FibonacciGenerateNumberUseCase,
+    pub config: watch::Receiver<Config>,
+    pub rate_limiter: Arc<RateLimiter>,
 }
 
-pub fn create_state() -> AppState {
-    let private_key_hex =
-        std::env::var("ETH_WALLET_PRIVATE_KEY").expect("ETH_WALLET_PRIVATE_KEY must be set");
-    let private_key_hex = private_key_hex.split_at(2).1;
-    let private_key = hex::decode(private_key_hex).expect("invalid private key");
-
-    let rpc_url = std::env::var("RPC_URL").expect("RPC_URL must be set");
-    let rpc_url = rpc_url.parse().expect("invalid RPC URL");
-
-    let contract_hex = std::env::var("ETH_CONTRACT").expect("ETH_CONTRACT must be set");
-    let contract_address = Address::from_str(&contract_hex).expect("invalid contract address");
+pub fn create_state(config_watch: watch::Receiver<Config>, shutdown: Shutdown) -> Result<AppState> {
+    let config = config_watch.borrow().clone();
+    let errors = config.validate();
+    if !errors.is_empty() {
+        return Err(ConfigError::Invalid(errors).into());
+    }
+    let missing = |key: &str| ConfigError::Invalid(vec![format!("{} must be set", key)]);
+    let private_key = config
+        .wallet
+        .private_key
+        .as_ref()
+        .ok_or_else(|| missing("wallet.private_key"))?;
+    let rpc_url = config
+        .chain
+        .rpc_url
+        .clone()
+        .ok_or_else(|| missing("chain.rpc_url"))?;
 
-    let signer = PrivateKeySigner::from_slice(private_key.as_slice()).expect("invalid private key");
+    let signer = PrivateKeySigner::from_slice(private_key.expose_secret()).map_err(|_| {
+        ConfigError::Invalid(vec![
+            "wallet.private_key is not a valid secp256k1 private key".to_string(),
+        ])
+    })?;
     let wallet = EthereumWallet::from(signer);
 
+    let guests = Arc::new(GuestRegistry::load(&config)?);
+
     let fibonacci_ethereum_provider =
-        FibonacciEthereumProvider::new(wallet.clone(), contract_address.clone(), rpc_url);
+        FibonacciEthereumProvider::new(wallet, rpc_url, config_watch.clone(), shutdown.clone());
 
-    let fibonacci_risc_zero_provider = FibonacciRiscZeroProvider::new();
+    let fibonacci_risc_zero_provider: Pin<Box<dyn IFibonacciRiscZeroProvider + Sync + Send>> =
+        match config.prover.backend {
+            ProverBackend::Remote => Box::pin(FibonacciRemoteProvider::new(
+                config.prover.clone(),
+                guests.clone(),
+            )),
+            _ => Box::pin(FibonacciRiscZeroProvider::new(
+                config.prover.clone(),
+                guests.clone(),
+            )),
+        };
+
+    let job_store: Pin<Box<dyn IJobStore + Sync + Send>> = match config.storage.backend {
+        StorageBackend::Sqlite => Box::pin(SqliteJobStore::open(&config.storage.path)?),
+        StorageBackend::Memory => Box::pin(InMemoryJobStore::new()),
+    };
+
+    let archive = config.archive.dir.clone().map(|directory| {
+        Arc::new(ReceiptArchive::new(
+            directory,
+            config.chain.chain_id,
+            config.archive.retention(),
+        ))
+    });
 
     let fibonacci_number_generator = FibonacciGenerateNumberUseCase::new(
-        Arc::new(Pin::from(Box::new(fibonacci_risc_zero_provider))),
+        Arc::new(fibonacci_risc_zero_provider),
         Arc::new(Pin::from(Box::new(fibonacci_ethereum_provider))),
+        Arc::new(job_store),
+        ProverQueue::new(config.prover.workers, config.prover.max_queued),
+        guests,
+        archive,
+        config.retry,
+        shutdown,
     );
 
-    AppState {
+    Ok(AppState {
         fibonacci_number_generator,
-    }
+        config: config_watch,
+        rate_limiter: Arc::new(RateLimiter::new()),
+    })
 }
@@ -0,0 +1,484 @@
+use crate::domain::provider::ReceiptKind;
+use crate::infra::config::{ExecutionLimits, ProverBackend};
+use crate::infra::provider::fibonacci_risc_zero::prove;
+use crate::prelude::FibchainError;
+use axum::body::Bytes;
+use axum::extract::{Path, Request, State};
+use axum::http::StatusCode;
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post, put};
+use axum::{Json, Router};
+use risc0_zkvm::{get_prover_server, ProverOpts, Receipt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+use url::Url;
+use uuid::Uuid;
+
+const API_KEY_HEADER: &str = "x-api-key";
+
+#[derive(Debug, Serialize)]
+struct UploadResponse {
+    url: String,
+    uuid: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ImageUploadResponse {
+    url: String,
+}
+
+#[derive(Debug, Serialize)]
+struct CreatedResponse {
+    uuid: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SessionRequest {
+    img: String,
+    input: String,
+    #[serde(default)]
+    execute_only: bool,
+    /// Cycles the session may run, the zkVM default when missing.
+    #[serde(default)]
+    exec_cycle_limit: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SnarkRequest {
+    session_id: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct SessionStats {
+    segments: usize,
+    total_cycles: u64,
+    cycles: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct SessionStatusResponse {
+    status: &'static str,
+    receipt_url: Option<String>,
+    error_msg: Option<String>,
+    state: Option<String>,
+    elapsed_time: Option<f64>,
+    stats: Option<SessionStats>,
+}
+
+#[derive(Debug, Serialize)]
+struct SnarkStatusResponse {
+    status: &'static str,
+    output: Option<String>,
+    error_msg: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct ReceiptDownloadResponse {
+    url: String,
+}
+
+#[derive(Debug, Serialize)]
+struct VersionResponse {
+    risc0_zkvm: Vec<String>,
+}
+
+/// Outcome of a session or SNARK conversion, named as Bonsai reports it.
+#[derive(Clone)]
+enum Proving {
+    Running,
+    Succeeded { receipt: String, cycles: u64 },
+    Failed(String),
+    Aborted,
+}
+
+impl Proving {
+    fn status(&self) -> &'static str {
+        match self {
+            Self::Running => "RUNNING",
+            Self::Succeeded { .. } => "SUCCEEDED",
+            Self::Failed(_) => "FAILED",
+            Self::Aborted => "ABORTED",
+        }
+    }
+}
+
+struct Session {
+    proving: Proving,
+    started: Instant,
+    cancellation: CancellationToken,
+}
+
+#[derive(Default)]
+struct Storage {
+    images: HashMap<String, Arc<[u8]>>,
+    /// Inputs and assumption receipts, which share the upload flow.
+    uploads: HashMap<String, Vec<u8>>,
+    sessions: HashMap<String, Session>,
+    snarks: HashMap<String, Proving>,
+    /// Bincode encoded receipts of finished sessions and SNARK conversions.
+    receipts: HashMap<String, Vec<u8>>,
+}
+
+/// Serves the Bonsai REST API from this machine, proving with the `local` or `dev_mode`
+/// backend, so the `bonsai` backend can run in CI and offline. Any non-empty API key is accepted
+/// and everything is kept in memory.
+pub struct BonsaiMock {
+    /// URL the mock is reached at, used in the upload and download URLs it hands out.
+    url: String,
+    backend: ProverBackend,
+    storage: Mutex<Storage>,
+}
+
+impl BonsaiMock {
+    pub fn new(url: Url, backend: ProverBackend) -> Self {
+        Self {
+            url: url.as_str().trim_end_matches('/').to_string(),
+            backend,
+            storage: Mutex::new(Storage::default()),
+        }
+    }
+
+    pub fn routes(self: Arc<Self>) -> Router {
+        Router::new()
+            .route("/version", get(Self::version))
+            .route("/images/upload/{image_id}", get(Self::image_upload_url))
+            .route("/inputs/upload", get(Self::upload_url))
+            .route("/receipts/upload", get(Self::upload_url))
+            .route("/sessions/create", post(Self::create_session))
+            .route("/sessions/status/{id}", get(Self::session_status))
+            .route("/sessions/stop/{id}", get(Self::stop_session))
+            .route("/snark/create", post(Self::create_snark))
+            .route("/snark/status/{id}", get(Self::snark_status))
+            .route("/receipts/{id}", get(Self::receipt_url))
+            .layer(middleware::from_fn(require_api_key))
+            // Presigned URLs are not authenticated with the API key.
+            .route("/upload/images/{image_id}", put(Self::put_image))
+            .route("/upload/data/{id}", put(Self::put_data))
+            .route("/download/{id}", get(Self::download))
+            .with_state(self)
+    }
+
+    fn storage(&self) -> std::sync::MutexGuard<'_, Storage> {
+        self.storage.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn download_url(&self, id: &str) -> String {
+        format!("{}/download/{}", self.url, id)
+    }
+
+    async fn version() -> Json<VersionResponse> {
+        Json(VersionResponse {
+            risc0_zkvm: vec![risc0_zkvm::VERSION.to_string()],
+        })
+    }
+
+    /// `204 No Content` tells the client the image is already uploaded.
+    async fn image_upload_url(
+        State(mock): State<Arc<Self>>,
+        Path(image_id): Path<String>,
+    ) -> Response {
+        if mock.storage().images.contains_key(&image_id) {
+            return StatusCode::NO_CONTENT.into_response();
+        }
+
+        Json(ImageUploadResponse {
+            url: format!("{}/upload/images/{}", mock.url, image_id),
+        })
+        .into_response()
+    }
+
+    async fn upload_url(State(mock): State<Arc<Self>>) -> Json<UploadResponse> {
+        let uuid = Uuid::new_v4().to_string();
+
+        Json(UploadResponse {
+            url: format!("{}/upload/data/{}", mock.url, uuid),
+            uuid,
+        })
+    }
+
+    /// Only ELFs are accepted, and only under their own image id.
+    async fn put_image(
+        State(mock): State<Arc<Self>>,
+        Path(image_id): Path<String>,
+        elf: Bytes,
+    ) -> Response {
+        match risc0_zkvm::compute_image_id(&elf) {
+            Ok(computed) if computed.to_string() == image_id => {
+                mock.storage().images.insert(image_id, Arc::from(&elf[..]));
+                StatusCode::OK.into_response()
+            }
+            Ok(computed) => (
+                StatusCode::BAD_REQUEST,
+                format!("the ELF has image id {}, not {}", computed, image_id),
+            )
+                .into_response(),
+            Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+        }
+    }
+
+    async fn put_data(
+        State(mock): State<Arc<Self>>,
+        Path(id): Path<String>,
+        data: Bytes,
+    ) -> StatusCode {
+        mock.storage().uploads.insert(id, data.to_vec());
+        StatusCode::OK
+    }
+
+    async fn download(State(mock): State<Arc<Self>>, Path(id): Path<String>) -> Response {
+        match mock.storage().receipts.get(&id) {
+            Some(receipt) => receipt.clone().into_response(),
+            None => StatusCode::NOT_FOUND.into_response(),
+        }
+    }
+
+    /// Proves a succinct receipt in the background, like Bonsai sessions do. Assumptions are
+    /// ignored as the guest does not compose other proofs.
+    async fn create_session(
+        State(mock): State<Arc<Self>>,
+        Json(request): Json<SessionRequest>,
+    ) -> Response {
+        if request.execute_only {
+            return (
+                StatusCode::BAD_REQUEST,
+                "execute only sessions are not supported",
+            )
+                .into_response();
+        }
+
+        let id = Uuid::new_v4().to_string();
+        let cancellation = CancellationToken::new();
+        let (elf, input) = {
+            let mut storage = mock.storage();
+            let Some(elf) = storage.images.get(&request.img).cloned() else {
+                return (StatusCode::NOT_FOUND, "unknown image").into_response();
+            };
+            let Some(input) = storage.uploads.get(&request.input).cloned() else {
+                return (StatusCode::NOT_FOUND, "unknown input").into_response();
+            };
+            storage.sessions.insert(
+                id.clone(),
+                Session {
+                    proving: Proving::Running,
+                    started: Instant::now(),
+                    cancellation: cancellation.clone(),
+                },
+            );
+            (elf, input)
+        };
+
+        info!(
+            session = id,
+            image_id = request.img,
+            "Bonsai mock session created"
+        );
+        let backend = mock.backend;
+        let limits = ExecutionLimits {
+            session_cycle_limit: request
+                .exec_cycle_limit
+                .unwrap_or(ExecutionLimits::default().session_cycle_limit),
+            ..ExecutionLimits::default()
+        };
+        tokio::spawn({
+            let mock = mock.clone();
+            let id = id.clone();
+            async move {
+                let proved = tokio::task::spawn_blocking(move || {
+                    prove(
+                        backend,
+                        ReceiptKind::Succinct,
+                        limits,
+                        &elf,
+                        &input,
+                        &cancellation,
+                    )
+                })
+                .await;
+                let proving = match proved {
+                    Ok(Ok((receipt, cycles))) => mock.store_receipt(&receipt, cycles),
+                    // Reported with the message of the zkVM, like Bonsai does.
+                    Ok(Err(FibchainError::CycleLimitExceeded { .. })) => {
+                        Proving::Failed("Session limit exceeded".to_string())
+                    }
+                    Ok(Err(e)) => Proving::Failed(e.to_string()),
+                    Err(e) => Proving::Failed(e.to_string()),
+                };
+
+                if let Some(session) = mock.storage().sessions.get_mut(&id) {
+                    if matches!(session.proving, Proving::Running) {
+                        session.proving = proving;
+                    }
+                }
+            }
+        });
+
+        Json(CreatedResponse { uuid: id }).into_response()
+    }
+
+    fn store_receipt(&self, receipt: &Receipt, cycles: u64) -> Proving {
+        match bincode::serialize(receipt) {
+            Ok(encoded) => {
+                let id = Uuid::new_v4().to_string();
+                self.storage().receipts.insert(id.clone(), encoded);
+                Proving::Succeeded {
+                    receipt: id,
+                    cycles,
+                }
+            }
+            Err(e) => Proving::Failed(e.to_string()),
+        }
+    }
+
+    async fn session_status(State(mock): State<Arc<Self>>, Path(id): Path<String>) -> Response {
+        let storage = mock.storage();
+        let Some(session) = storage.sessions.get(&id) else {
+            return StatusCode::NOT_FOUND.into_response();
+        };
+
+        let mut response = SessionStatusResponse {
+            status: session.proving.status(),
+            receipt_url: None,
+            error_msg: None,
+            state: None,
+            elapsed_time: Some(session.started.elapsed().as_secs_f64()),
+            stats: None,
+        };
+        match &session.proving {
+            Proving::Running => response.state = Some("InProgress".to_string()),
+            Proving::Succeeded { receipt, cycles } => {
+                response.receipt_url = Some(mock.download_url(receipt));
+                // The prover only reports the total cycles.
+                response.stats = Some(SessionStats {
+                    segments: 0,
+                    total_cycles: *cycles,
+                    cycles: *cycles,
+                });
+            }
+            Proving::Failed(message) => response.error_msg = Some(message.clone()),
+            Proving::Aborted => response.error_msg = Some("the session was stopped".to_string()),
+        }
+
+        Json(response).into_response()
+    }
+
+    async fn stop_session(State(mock): State<Arc<Self>>, Path(id): Path<String>) -> StatusCode {
+        match mock.storage().sessions.get_mut(&id) {
+            Some(session) => {
+                if matches!(session.proving, Proving::Running) {
+                    warn!(session = id, "Bonsai mock session stopped");
+                    session.cancellation.cancel();
+                    session.proving = Proving::Aborted;
+                }
+                StatusCode::OK
+            }
+            None => StatusCode::NOT_FOUND,
+        }
+    }
+
+    async fn receipt_url(State(mock): State<Arc<Self>>, Path(id): Path<String>) -> Response {
+        match mock
+            .storage()
+            .sessions
+            .get(&id)
+            .map(|session| &session.proving)
+        {
+            Some(Proving::Succeeded { receipt, .. }) => Json(ReceiptDownloadResponse {
+                url: mock.download_url(receipt),
+            })
+            .into_response(),
+            _ => StatusCode::NOT_FOUND.into_response(),
+        }
+    }
+
+    /// Wraps the succinct receipt of a finished session in a Groth16 one. Fake receipts have no
+    /// kind, `dev_mode` sessions are returned as they are.
+    async fn create_snark(
+        State(mock): State<Arc<Self>>,
+        Json(request): Json<SnarkRequest>,
+    ) -> Response {
+        let receipt = {
+            let storage = mock.storage();
+            match storage
+                .sessions
+                .get(&request.session_id)
+                .map(|session| &session.proving)
+            {
+                Some(Proving::Succeeded { receipt, .. }) => storage.receipts.get(receipt).cloned(),
+                _ => None,
+            }
+        };
+        let Some(receipt) = receipt else {
+            return (StatusCode::NOT_FOUND, "no succeeded session with this id").into_response();
+        };
+
+        let id = Uuid::new_v4().to_string();
+        mock.storage().snarks.insert(id.clone(), Proving::Running);
+        info!(
+            snark = id,
+            session = request.session_id,
+            "Bonsai mock SNARK conversion created"
+        );
+        let backend = mock.backend;
+        tokio::spawn({
+            let mock = mock.clone();
+            let id = id.clone();
+            async move {
+                let converted = tokio::task::spawn_blocking(move || {
+                    let receipt: Receipt = bincode::deserialize(&receipt)?;
+                    if backend == ProverBackend::DevMode {
+                        return Ok(receipt);
+                    }
+
+                    let opts = ProverOpts::groth16();
+                    get_prover_server(&opts)?.compress(&opts, &receipt)
+                })
+                .await;
+                let proving = match converted {
+                    Ok(Ok(receipt)) => mock.store_receipt(&receipt, 0),
+                    Ok(Err(e)) => Proving::Failed(e.to_string()),
+                    Err(e) => Proving::Failed(e.to_string()),
+                };
+
+                mock.storage().snarks.insert(id, proving);
+            }
+        });
+
+        Json(CreatedResponse { uuid: id }).into_response()
+    }
+
+    async fn snark_status(State(mock): State<Arc<Self>>, Path(id): Path<String>) -> Response {
+        let Some(proving) = mock.storage().snarks.get(&id).cloned() else {
+            return StatusCode::NOT_FOUND.into_response();
+        };
+
+        Json(SnarkStatusResponse {
+            status: proving.status(),
+            output: match &proving {
+                Proving::Succeeded { receipt, .. } => Some(mock.download_url(receipt)),
+                _ => None,
+            },
+            error_msg: match proving {
+                Proving::Failed(message) => Some(message),
+                _ => None,
+            },
+        })
+        .into_response()
+    }
+}
+
+async fn require_api_key(request: Request, next: Next) -> Response {
+    let has_key = request
+        .headers()
+        .get(API_KEY_HEADER)
+        .is_some_and(|key| !key.is_empty());
+    if !has_key {
+        return (StatusCode::UNAUTHORIZED, "missing API key").into_response();
+    }
+
+    next.run(request).await
+}
@@ -0,0 +1,96 @@
+use crate::infra::config::{self, Cli, Config};
+use crate::infra::error::ConfigError;
+use crate::infra::secret::redact_url;
+use alloy::providers::{Provider, ProviderBuilder};
+use color_eyre::eyre::eyre;
+use std::time::Duration;
+use url::Url;
+
+const RPC_PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Prints the effective configuration with secrets redacted, then fails if it is invalid.
+pub fn print_config(cli: &Cli) -> color_eyre::Result<()> {
+    let (config, errors) = Config::merge(cli)?;
+    print!("{}", config.to_redacted_toml()?);
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(ConfigError::Invalid(errors).into())
+    }
+}
+
+/// Validates the configuration and checks the RPC endpoint serves the expected chain.
+pub async fn check_config(cli: &Cli) -> color_eyre::Result<()> {
+    let config = Config::load(cli)?;
+    println!("configuration is valid");
+
+    let rpc_url = config
+        .chain
+        .rpc_url
+        .clone()
+        .ok_or_else(|| eyre!("chain.rpc_url must be set"))?;
+    let chain_id = probe_chain_id(&rpc_url).await?;
+
+    match config.chain.chain_id {
+        Some(expected) if expected != chain_id => Err(eyre!(
+            "{} serves chain id {} but chain.chain_id expects {}",
+            redact_url(&rpc_url),
+            chain_id,
+            expected
+        )),
+        Some(_) => {
+            println!("rpc chain id {} matches chain.chain_id", chain_id);
+            Ok(())
+        }
+        None => {
+            println!(
+                "rpc chain id is {}, set chain.chain_id to enforce it",
+                chain_id
+            );
+            Ok(())
+        }
+    }
+}
+
+/// Refuses to fake proofs for a chain that is not a local devnet, whatever chain.chain_id says.
+pub async fn ensure_local_devnet(config: &Config) -> color_eyre::Result<()> {
+    let rpc_url = config
+        .chain
+        .rpc_url
+        .clone()
+        .ok_or_else(|| eyre!("chain.rpc_url must be set"))?;
+    let chain_id = probe_chain_id(&rpc_url).await?;
+
+    if config::is_local_devnet(chain_id) {
+        Ok(())
+    } else {
+        Err(eyre!(
+            "prover.backend `dev_mode` fakes proofs but {} serves chain id {}, which is not a \
+             local devnet",
+            redact_url(&rpc_url),
+            chain_id
+        ))
+    }
+}
+
+async fn probe_chain_id(rpc_url: &Url) -> color_eyre::Result<u64> {
+    let provider = ProviderBuilder::new().on_http(rpc_url.clone());
+
+    tokio::time::timeout(RPC_PROBE_TIMEOUT, provider.get_chain_id())
+        .await
+        .map_err(|_| {
+            eyre!(
+                "timed out after {}s reading the chain id from {}",
+                RPC_PROBE_TIMEOUT.as_secs(),
+                redact_url(rpc_url)
+            )
+        })?
+        .map_err(|e| {
+            eyre!(
+                "failed to read the chain id from {}: {}",
+                redact_url(rpc_url),
+                e
+            )
+        })
+}
@@ -0,0 +1,1440 @@
+use crate::infra::error::ConfigError;
+use crate::infra::guest_registry::{EMBEDDED_VERSION, FIBONACCI_METHOD};
+use crate::infra::redaction::{self, RedactionPolicy};
+use crate::infra::retry::{RetryPolicy, RetryStage};
+use crate::infra::secret::{self, PrivateKey};
+use alloy::signers::local::PrivateKeySigner;
+use alloy_primitives::Address;
+use clap::Parser;
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+use url::Url;
+use zeroize::Zeroizing;
+
+const DEFAULT_CONFIG_PATH: &str = "fibchain.toml";
+
+/// Chain ids of local development networks, anvil and hardhat use `31337`, geth and ganache `1337`.
+const LOCAL_DEVNET_CHAIN_IDS: [u64; 2] = [31337, 1337];
+
+pub fn is_local_devnet(chain_id: u64) -> bool {
+    LOCAL_DEVNET_CHAIN_IDS.contains(&chain_id)
+}
+
+/// Command line flags. They take precedence over both the config file and environment variables.
+#[derive(Clone, Default, Parser)]
+#[command(
+    version,
+    about = "Proves fibonacci numbers with RISC Zero and publishes them on-chain"
+)]
+pub struct Cli {
+    /// Path to the TOML config file. Defaults to `fibchain.toml` when it exists.
+    #[arg(long, env = "FIBCHAIN_CONFIG")]
+    pub config: Option<PathBuf>,
+
+    /// Address the HTTP server binds to.
+    #[arg(long)]
+    pub listen_addr: Option<SocketAddr>,
+
+    /// Ethereum JSON-RPC endpoint.
+    #[arg(long)]
+    pub rpc_url: Option<Url>,
+
+    /// Address of the deployed `Fibonacci` contract.
+    #[arg(long)]
+    pub contract: Option<Address>,
+
+    /// Chain id the RPC endpoint is expected to serve.
+    #[arg(long)]
+    pub chain_id: Option<u64>,
+
+    /// Confirmations to wait for before a transaction is considered final.
+    #[arg(long)]
+    pub confirmations: Option<u64>,
+
+    /// Seconds to wait for the transaction confirmations.
+    #[arg(long)]
+    pub tx_timeout_secs: Option<u64>,
+
+    /// Largest `iterations` value the prover accepts.
+    #[arg(long)]
+    pub max_iterations: Option<u16>,
+
+    /// gRPC endpoint of the OpenTelemetry collector.
+    #[arg(long)]
+    pub otlp_endpoint: Option<String>,
+
+    /// Log filter directives, using the `RUST_LOG` syntax.
+    #[arg(long)]
+    pub log_filter: Option<String>,
+
+    /// Validate the configuration, probe the RPC chain id and exit without starting the server.
+    #[arg(long, conflicts_with = "print_config")]
+    pub check_config: bool,
+
+    /// Print the effective configuration with secrets redacted and exit without starting the server.
+    #[arg(long)]
+    pub print_config: bool,
+}
+
+/// RPC providers commonly embed API keys in their URL, it is printed redacted.
+impl fmt::Debug for Cli {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self {
+            config,
+            listen_addr,
+            rpc_url,
+            contract,
+            chain_id,
+            confirmations,
+            tx_timeout_secs,
+            max_iterations,
+            otlp_endpoint,
+            log_filter,
+            check_config,
+            print_config,
+        } = self;
+
+        f.debug_struct("Cli")
+            .field("config", config)
+            .field("listen_addr", listen_addr)
+            .field("rpc_url", &rpc_url.as_ref().map(secret::redact_url))
+            .field("contract", contract)
+            .field("chain_id", chain_id)
+            .field("confirmations", confirmations)
+            .field("tx_timeout_secs", tx_timeout_secs)
+            .field("max_iterations", max_iterations)
+            .field("otlp_endpoint", otlp_endpoint)
+            .field("log_filter", log_filter)
+            .field("check_config", check_config)
+            .field("print_config", print_config)
+            .finish()
+    }
+}
+
+/// Effective service configuration.
+///
+/// Values are layered from the TOML config file, then environment variables, then command line
+/// flags, with each layer overriding the previous one.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub chain: ChainConfig,
+    pub wallet: WalletConfig,
+    pub prover: ProverConfig,
+    pub guests: GuestsConfig,
+    pub telemetry: TelemetryConfig,
+    pub limits: LimitsConfig,
+    pub auth: AuthConfig,
+    pub storage: StorageConfig,
+    pub archive: ArchiveConfig,
+    pub retry: RetryConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub listen_addr: SocketAddr,
+    /// Seconds running jobs get to finish once a shutdown signal is received.
+    pub shutdown_timeout_secs: u64,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            listen_addr: SocketAddr::from(([127, 0, 0, 1], 8080)),
+            shutdown_timeout_secs: 300,
+        }
+    }
+}
+
+impl ServerConfig {
+    pub fn shutdown_timeout(&self) -> Duration {
+        Duration::from_secs(self.shutdown_timeout_secs)
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChainConfig {
+    #[serde(serialize_with = "secret::serialize_redacted_url")]
+    pub rpc_url: Option<Url>,
+    pub contract: Option<Address>,
+    pub chain_id: Option<u64>,
+    pub confirmations: u64,
+    pub tx_timeout_secs: u64,
+    /// Seconds between checks for the chain to come back when submissions are deferred.
+    pub resubmit_interval_secs: u64,
+    /// Cancels jobs whose transaction is sent by replacing it with an empty one at the same nonce.
+    pub cancel_with_replacement: bool,
+}
+
+impl Default for ChainConfig {
+    fn default() -> Self {
+        Self {
+            rpc_url: None,
+            contract: None,
+            chain_id: None,
+            confirmations: 10,
+            tx_timeout_secs: 60,
+            resubmit_interval_secs: 30,
+            cancel_with_replacement: false,
+        }
+    }
+}
+
+/// The RPC URL is printed redacted, like in the rendered configuration.
+impl fmt::Debug for ChainConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self {
+            rpc_url,
+            contract,
+            chain_id,
+            confirmations,
+            tx_timeout_secs,
+            resubmit_interval_secs,
+            cancel_with_replacement,
+        } = self;
+
+        f.debug_struct("ChainConfig")
+            .field("rpc_url", &rpc_url.as_ref().map(secret::redact_url))
+            .field("contract", contract)
+            .field("chain_id", chain_id)
+            .field("confirmations", confirmations)
+            .field("tx_timeout_secs", tx_timeout_secs)
+            .field("resubmit_interval_secs", resubmit_interval_secs)
+            .field("cancel_with_replacement", cancel_with_replacement)
+            .finish()
+    }
+}
+
+impl ChainConfig {
+    pub fn tx_timeout(&self) -> Duration {
+        Duration::from_secs(self.tx_timeout_secs)
+    }
+
+    pub fn resubmit_interval(&self) -> Duration {
+        Duration::from_secs(self.resubmit_interval_secs)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WalletConfig {
+    pub private_key: Option<PrivateKey>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProverConfig {
+    pub backend: ProverBackend,
+    pub max_iterations: u16,
+    /// Proofs generated at the same time.
+    pub workers: usize,
+    /// Jobs that may wait for a free worker before new ones are rejected.
+    pub max_queued: usize,
+    /// Proofs kept in memory for reuse, `0` disables the memory cache.
+    pub cache_capacity: usize,
+    /// Directory where proofs are also kept across restarts, unset disables the disk cache.
+    pub cache_dir: Option<PathBuf>,
+    /// Prover workers the `remote` backend dispatches proofs to.
+    pub remote_workers: Vec<Url>,
+    /// Milliseconds between status polls of a proof running on a remote worker.
+    pub remote_poll_interval_ms: u64,
+    /// Seconds between health checks of the remote workers.
+    pub remote_health_interval_secs: u64,
+    /// Cycles a guest may run before its execution is stopped.
+    pub session_cycle_limit: u64,
+    /// Segments run at most `2^segment_limit_po2` cycles, smaller segments need less memory to prove.
+    pub segment_limit_po2: u32,
+    /// Seconds a proof may take before it is abandoned, `0` disables the timeout.
+    pub proving_timeout_secs: u64,
+}
+
+impl Default for ProverConfig {
+    fn default() -> Self {
+        Self {
+            backend: ProverBackend::default(),
+            max_iterations: u16::MAX,
+            workers: 1,
+            max_queued: 16,
+            cache_capacity: 64,
+            cache_dir: None,
+            remote_workers: Vec::new(),
+            remote_poll_interval_ms: 1000,
+            remote_health_interval_secs: 10,
+            session_cycle_limit: ExecutionLimits::default().session_cycle_limit,
+            segment_limit_po2: ExecutionLimits::default().segment_limit_po2,
+            proving_timeout_secs: 0,
+        }
+    }
+}
+
+impl ProverConfig {
+    pub fn remote_poll_interval(&self) -> Duration {
+        Duration::from_millis(self.remote_poll_interval_ms)
+    }
+
+    pub fn remote_health_interval(&self) -> Duration {
+        Duration::from_secs(self.remote_health_interval_secs)
+    }
+
+    pub fn proving_timeout(&self) -> Option<Duration> {
+        (self.proving_timeout_secs > 0).then(|| Duration::from_secs(self.proving_timeout_secs))
+    }
+
+    pub fn execution_limits(&self) -> ExecutionLimits {
+        ExecutionLimits {
+            session_cycle_limit: self.session_cycle_limit,
+            segment_limit_po2: self.segment_limit_po2,
+        }
+    }
+
+    fn validate_execution_limits(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+
+        if self.session_cycle_limit == 0 {
+            errors.push("prover.session_cycle_limit must be at least 1".to_string());
+        }
+
+        if !SEGMENT_LIMIT_PO2_RANGE.contains(&self.segment_limit_po2) {
+            errors.push(format!(
+                "prover.segment_limit_po2 must be between {} and {}",
+                SEGMENT_LIMIT_PO2_RANGE.start(),
+                SEGMENT_LIMIT_PO2_RANGE.end()
+            ));
+        }
+
+        errors
+    }
+}
+
+/// Segment sizes the zkVM circuit supports.
+const SEGMENT_LIMIT_PO2_RANGE: std::ops::RangeInclusive<u32> = 13..=24;
+
+/// Bounds on a guest execution, so a pathological input cannot run or allocate without limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExecutionLimits {
+    pub session_cycle_limit: u64,
+    pub segment_limit_po2: u32,
+}
+
+/// The defaults of the zkVM.
+impl Default for ExecutionLimits {
+    fn default() -> Self {
+        Self {
+            session_cycle_limit: 64 * 1024 * 1024,
+            segment_limit_po2: 20,
+        }
+    }
+}
+
+/// Guest programs served besides the one built into the binary.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GuestsConfig {
+    /// Version proven when a request does not pin one, the built-in guest when unset. Its
+    /// proofs are submitted to `chain.contract`.
+    pub default_version: Option<String>,
+    pub versions: Vec<GuestVersionConfig>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GuestVersionConfig {
+    #[serde(default = "default_guest_method")]
+    pub method: String,
+    pub version: String,
+    /// ELF binary of the guest, its image id is computed when the service starts.
+    pub elf: PathBuf,
+    /// Contract accepting the proofs of this version, unset keeps them off-chain. The default
+    /// version is always submitted to `chain.contract`.
+    pub contract: Option<Address>,
+}
+
+fn default_guest_method() -> String {
+    FIBONACCI_METHOD.to_string()
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProverBackend {
+    /// Proofs are generated on this machine.
+    #[default]
+    Local,
+    /// Proofs are generated by Bonsai, using the `BONSAI_API_URL` and `BONSAI_API_KEY` variables.
+    Bonsai,
+    /// The guest is only executed and proofs are faked, which only a mock verifier accepts.
+    DevMode,
+    /// Proofs are generated by the `prover-worker` processes listed in `remote_workers`.
+    Remote,
+}
+
+impl ProverBackend {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Local => "local",
+            Self::Bonsai => "bonsai",
+            Self::DevMode => "dev_mode",
+            Self::Remote => "remote",
+        }
+    }
+}
+
+impl FromStr for ProverBackend {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "local" => Ok(Self::Local),
+            "bonsai" => Ok(Self::Bonsai),
+            "dev_mode" => Ok(Self::DevMode),
+            "remote" => Ok(Self::Remote),
+            other => Err(format!(
+                "unknown prover backend `{}`, expected `local`, `bonsai`, `dev_mode` or `remote`",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TelemetryConfig {
+    pub otlp_endpoint: String,
+    pub log_filter: String,
+    pub environment: String,
+    pub max_payload_len: usize,
+    #[serde(serialize_with = "secret::serialize_redacted")]
+    pub client_id_salt: String,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        let redaction = RedactionPolicy::default();
+
+        Self {
+            otlp_endpoint: "http://localhost:4317".to_string(),
+            log_filter: "info".to_string(),
+            environment: "development".to_string(),
+            max_payload_len: redaction.max_payload_len,
+            // Unset, a random salt is generated on startup.
+            client_id_salt: String::new(),
+        }
+    }
+}
+
+impl fmt::Debug for TelemetryConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self {
+            otlp_endpoint,
+            log_filter,
+            environment,
+            max_payload_len,
+            client_id_salt,
+        } = self;
+
+        f.debug_struct("TelemetryConfig")
+            .field("otlp_endpoint", otlp_endpoint)
+            .field("log_filter", log_filter)
+            .field("environment", environment)
+            .field("max_payload_len", max_payload_len)
+            .field("client_id_salt", &secret::redact(client_id_salt))
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsConfig {
+    /// Requests each client may make per minute, `0` disables rate limiting.
+    pub requests_per_minute: u32,
+}
+
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    /// Keys accepted in the `x-api-key` header, an empty list disables authentication.
+    #[serde(serialize_with = "secret::serialize_redacted_list")]
+    pub api_keys: Vec<String>,
+}
+
+/// API keys are masked like in logs.
+impl fmt::Debug for AuthConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let api_keys: Vec<String> = self
+            .api_keys
+            .iter()
+            .map(|key| redaction::key(key))
+            .collect();
+
+        f.debug_struct("AuthConfig")
+            .field("api_keys", &api_keys)
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageBackend {
+    /// Jobs are kept in a SQLite database and survive restarts.
+    #[default]
+    Sqlite,
+    /// Jobs are kept in memory and lost on restart.
+    Memory,
+}
+
+impl FromStr for StorageBackend {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "sqlite" => Ok(Self::Sqlite),
+            "memory" => Ok(Self::Memory),
+            other => Err(format!(
+                "unknown storage backend `{}`, expected `sqlite` or `memory`",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageConfig {
+    pub backend: StorageBackend,
+    /// SQLite database file, only used by the `sqlite` backend.
+    pub path: PathBuf,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            backend: StorageBackend::default(),
+            path: PathBuf::from("fibchain.db"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ArchiveConfig {
+    /// Directory where the receipt of every submitted proof is kept, unset disables the archive.
+    pub dir: Option<PathBuf>,
+    /// Days an entry is kept after its last submission, `0` keeps entries forever.
+    pub retention_days: u64,
+    /// Seconds between removals of expired entries.
+    pub prune_interval_secs: u64,
+}
+
+impl Default for ArchiveConfig {
+    fn default() -> Self {
+        Self {
+            dir: None,
+            retention_days: 0,
+            prune_interval_secs: 3_600,
+        }
+    }
+}
+
+/// Longest retention whose milliseconds fit the archive's timestamps.
+const MAX_RETENTION_DAYS: u64 = u64::MAX / 86_400_000;
+
+impl ArchiveConfig {
+    pub fn retention(&self) -> Option<Duration> {
+        (self.retention_days > 0)
+            .then(|| Duration::from_secs(self.retention_days.saturating_mul(86_400)))
+    }
+
+    pub fn prune_interval(&self) -> Duration {
+        Duration::from_secs(self.prune_interval_secs)
+    }
+}
+
+/// Retry policies for the job stages that talk to the chain or a remote prover.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetryConfig {
+    pub proving: RetryPolicy,
+    pub send: RetryPolicy,
+    pub confirmation: RetryPolicy,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            proving: RetryPolicy::new(3, 5_000, 60_000, 1_800),
+            send: RetryPolicy::new(5, 500, 30_000, 120),
+            confirmation: RetryPolicy::new(5, 1_000, 30_000, 600),
+        }
+    }
+}
+
+impl RetryConfig {
+    pub fn policy(&self, stage: RetryStage) -> RetryPolicy {
+        match stage {
+            RetryStage::Proving => self.proving,
+            RetryStage::Send => self.send,
+            RetryStage::Confirmation => self.confirmation,
+        }
+    }
+}
+
+impl From<&TelemetryConfig> for RedactionPolicy {
+    fn from(config: &TelemetryConfig) -> Self {
+        Self::new(config.max_payload_len, &config.client_id_salt)
+    }
+}
+
+impl Config {
+    /// Builds the effective configuration and validates it, reporting every problem found.
+    pub fn load(cli: &Cli) -> Result<Self, ConfigError> {
+        let (config, errors) = Self::merge(cli)?;
+
+        if errors.is_empty() {
+            Ok(config)
+        } else {
+            Err(ConfigError::Invalid(errors))
+        }
+    }
+
+    /// Layers the config file, environment variables and command line flags without rejecting
+    /// the result, returning the merged configuration along with every problem found in it.
+    pub fn merge(cli: &Cli) -> Result<(Self, Vec<String>), ConfigError> {
+        let (config, mut errors) = Self::layer(cli)?;
+        errors.extend(config.validate());
+        errors.extend(config.check_prover_env(|key| std::env::var(key).ok()));
+
+        Ok((config, errors))
+    }
+
+    /// Builds the configuration of a `prover-worker`, which only needs the prover, guest and
+    /// telemetry sections of the same file and variables.
+    pub fn load_worker(cli: &Cli) -> Result<Self, ConfigError> {
+        let (config, mut errors) = Self::layer(cli)?;
+        errors.extend(config.validate_worker());
+        errors.extend(config.check_prover_env(|key| std::env::var(key).ok()));
+
+        if errors.is_empty() {
+            Ok(config)
+        } else {
+            Err(ConfigError::Invalid(errors))
+        }
+    }
+
+    fn layer(cli: &Cli) -> Result<(Self, Vec<String>), ConfigError> {
+        let mut config = match Self::file_path(cli) {
+            Some(path) => Self::from_file(&path)?,
+            None => Self::default(),
+        };
+
+        let errors = config.apply_env(|key| std::env::var(key).ok());
+        config.apply_cli(cli);
+
+        Ok((config, errors))
+    }
+
+    /// Renders the configuration as TOML with every secret redacted.
+    pub fn to_redacted_toml(&self) -> Result<String, ConfigError> {
+        toml::to_string_pretty(self).map_err(ConfigError::Render)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let contents = std::fs::read_to_string(path).map_err(|source| ConfigError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+        toml::from_str(&contents).map_err(|source| ConfigError::Parse {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    fn file_path(cli: &Cli) -> Option<PathBuf> {
+        cli.config.clone().or_else(|| {
+            let path = PathBuf::from(DEFAULT_CONFIG_PATH);
+            path.exists().then_some(path)
+        })
+    }
+
+    fn apply_env(&mut self, var: impl Fn(&str) -> Option<String>) -> Vec<String> {
+        let mut errors = Vec::new();
+
+        if let Some(value) = env_value(&var, "LISTEN_ADDR", &mut errors) {
+            self.server.listen_addr = value;
+        }
+        if let Some(value) = env_value(&var, "SHUTDOWN_TIMEOUT_SECS", &mut errors) {
+            self.server.shutdown_timeout_secs = value;
+        }
+        if let Some(value) = env_value(&var, "RPC_URL", &mut errors) {
+            self.chain.rpc_url = Some(value);
+        }
+        if let Some(value) = env_value(&var, "ETH_CONTRACT", &mut errors) {
+            self.chain.contract = Some(value);
+        }
+        if let Some(value) = env_value(&var, "ETH_CHAIN_ID", &mut errors) {
+            self.chain.chain_id = Some(value);
+        }
+        if let Some(value) = env_value(&var, "ETH_TX_CONFIRMATIONS", &mut errors) {
+            self.chain.confirmations = value;
+        }
+        if let Some(value) = env_value(&var, "ETH_TX_TIMEOUT_SECS", &mut errors) {
+            self.chain.tx_timeout_secs = value;
+        }
+        if let Some(value) = env_value(&var, "ETH_RESUBMIT_INTERVAL_SECS", &mut errors) {
+            self.chain.resubmit_interval_secs = value;
+        }
+        if let Some(value) = env_value(&var, "ETH_CANCEL_WITH_REPLACEMENT", &mut errors) {
+            self.chain.cancel_with_replacement = value;
+        }
+        if let Some(value) = var("ETH_WALLET_PRIVATE_KEY").map(Zeroizing::new) {
+            match PrivateKey::from_hex(&value) {
+                Ok(private_key) => self.wallet.private_key = Some(private_key),
+                Err(e) => errors.push(format!("ETH_WALLET_PRIVATE_KEY: {}", e)),
+            }
+        }
+        if let Some(value) = env_value(&var, "PROVER_BACKEND", &mut errors) {
+            self.prover.backend = value;
+        }
+        if let Some(value) = env_value(&var, "PROVER_MAX_ITERATIONS", &mut errors) {
+            self.prover.max_iterations = value;
+        }
+        if let Some(value) = env_value(&var, "PROVER_WORKERS", &mut errors) {
+            self.prover.workers = value;
+        }
+        if let Some(value) = env_value(&var, "PROVER_MAX_QUEUED", &mut errors) {
+            self.prover.max_queued = value;
+        }
+        if let Some(value) = env_value(&var, "PROVER_CACHE_CAPACITY", &mut errors) {
+            self.prover.cache_capacity = value;
+        }
+        if let Some(value) = var("PROVER_CACHE_DIR") {
+            self.prover.cache_dir = Some(PathBuf::from(value));
+        }
+        if let Some(value) = env_value(&var, "PROVER_SESSION_CYCLE_LIMIT", &mut errors) {
+            self.prover.session_cycle_limit = value;
+        }
+        if let Some(value) = env_value(&var, "PROVER_SEGMENT_LIMIT_PO2", &mut errors) {
+            self.prover.segment_limit_po2 = value;
+        }
+        if let Some(value) = env_value(&var, "PROVER_TIMEOUT_SECS", &mut errors) {
+            self.prover.proving_timeout_secs = value;
+        }
+        if let Some(value) = var("PROVER_REMOTE_WORKERS") {
+            self.prover.remote_workers = value
+                .split(',')
+                .map(str::trim)
+                .filter(|worker| !worker.is_empty())
+                .filter_map(|worker| {
+                    Url::parse(worker)
+                        .map_err(|e| errors.push(format!("PROVER_REMOTE_WORKERS: {}", e)))
+                        .ok()
+                })
+                .collect();
+        }
+        if let Some(value) = var("GUESTS_DEFAULT_VERSION") {
+            self.guests.default_version = Some(value);
+        }
+        if let Some(value) = env_value(&var, "OTEL_EXPORTER_URL", &mut errors) {
+            self.telemetry.otlp_endpoint = value;
+        }
+        if let Some(value) = env_value(&var, "OTEL_DEPLOYMENT_ENVIRONMENT", &mut errors) {
+            self.telemetry.environment = value;
+        }
+        if let Some(value) = env_value(&var, "RUST_LOG", &mut errors) {
+            self.telemetry.log_filter = value;
+        }
+        if let Some(value) = env_value(&var, "LOG_MAX_PAYLOAD_LEN", &mut errors) {
+            self.telemetry.max_payload_len = value;
+        }
+        if let Some(value) = env_value(&var, "LOG_CLIENT_ID_SALT", &mut errors) {
+            self.telemetry.client_id_salt = value;
+        }
+        if let Some(value) = env_value(&var, "RATE_LIMIT_REQUESTS_PER_MINUTE", &mut errors) {
+            self.limits.requests_per_minute = value;
+        }
+        if let Some(value) = var("API_KEYS").map(Zeroizing::new) {
+            self.auth.api_keys = value
+                .split(',')
+                .map(|key| key.trim().to_string())
+                .filter(|key| !key.is_empty())
+                .collect();
+        }
+        if let Some(value) = env_value(&var, "STORAGE_BACKEND", &mut errors) {
+            self.storage.backend = value;
+        }
+        if let Some(value) = var("STORAGE_PATH") {
+            self.storage.path = PathBuf::from(value);
+        }
+        if let Some(value) = var("ARCHIVE_DIR") {
+            self.archive.dir = Some(PathBuf::from(value));
+        }
+        if let Some(value) = env_value(&var, "ARCHIVE_RETENTION_DAYS", &mut errors) {
+            self.archive.retention_days = value;
+        }
+
+        errors
+    }
+
+    fn apply_cli(&mut self, cli: &Cli) {
+        if let Some(listen_addr) = cli.listen_addr {
+            self.server.listen_addr = listen_addr;
+        }
+        if let Some(rpc_url) = &cli.rpc_url {
+            self.chain.rpc_url = Some(rpc_url.clone());
+        }
+        if let Some(contract) = cli.contract {
+            self.chain.contract = Some(contract);
+        }
+        if let Some(chain_id) = cli.chain_id {
+            self.chain.chain_id = Some(chain_id);
+        }
+        if let Some(confirmations) = cli.confirmations {
+            self.chain.confirmations = confirmations;
+        }
+        if let Some(tx_timeout_secs) = cli.tx_timeout_secs {
+            self.chain.tx_timeout_secs = tx_timeout_secs;
+        }
+        if let Some(max_iterations) = cli.max_iterations {
+            self.prover.max_iterations = max_iterations;
+        }
+        if let Some(otlp_endpoint) = &cli.otlp_endpoint {
+            self.telemetry.otlp_endpoint = otlp_endpoint.clone();
+        }
+        if let Some(log_filter) = &cli.log_filter {
+            self.telemetry.log_filter = log_filter.clone();
+        }
+    }
+
+    /// Lists the settings that differ from `other` but can only change with a restart.
+    ///
+    /// Rate limits, API keys, transaction confirmations, timeouts and replacement and the log
+    /// filter are reloadable, everything else is fixed at startup.
+    pub fn non_reloadable_changes(&self, other: &Config) -> Vec<&'static str> {
+        let mut changes = Vec::new();
+
+        if self.server != other.server {
+            changes.push("server");
+        }
+        if self.chain.rpc_url != other.chain.rpc_url {
+            changes.push("chain.rpc_url");
+        }
+        if self.chain.contract != other.chain.contract {
+            changes.push("chain.contract");
+        }
+        if self.chain.chain_id != other.chain.chain_id {
+            changes.push("chain.chain_id");
+        }
+        if self.chain.resubmit_interval_secs != other.chain.resubmit_interval_secs {
+            changes.push("chain.resubmit_interval_secs");
+        }
+        if self.wallet != other.wallet {
+            changes.push("wallet");
+        }
+        if self.prover != other.prover {
+            changes.push("prover");
+        }
+        if self.guests != other.guests {
+            changes.push("guests");
+        }
+        if self.storage != other.storage {
+            changes.push("storage");
+        }
+        if self.archive != other.archive {
+            changes.push("archive");
+        }
+        if self.retry != other.retry {
+            changes.push("retry");
+        }
+
+        let telemetry = TelemetryConfig {
+            log_filter: other.telemetry.log_filter.clone(),
+            ..self.telemetry.clone()
+        };
+        if telemetry != other.telemetry {
+            changes.push("telemetry");
+        }
+
+        changes
+    }
+
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+
+        match &self.chain.rpc_url {
+            None => errors.push("chain.rpc_url must be set (RPC_URL)".to_string()),
+            Some(rpc_url) if !matches!(rpc_url.scheme(), "http" | "https") => errors.push(format!(
+                "chain.rpc_url must be an http or https URL, got `{}`",
+                rpc_url.scheme()
+            )),
+            Some(_) => {}
+        }
+
+        match &self.chain.contract {
+            None => errors.push("chain.contract must be set (ETH_CONTRACT)".to_string()),
+            Some(contract) if contract.is_zero() => {
+                errors.push("chain.contract must not be the zero address".to_string())
+            }
+            Some(_) => {}
+        }
+
+        if self.chain.confirmations == 0 {
+            errors.push("chain.confirmations must be at least 1".to_string());
+        }
+
+        if self.chain.tx_timeout_secs == 0 {
+            errors.push("chain.tx_timeout_secs must be at least 1".to_string());
+        }
+
+        if self.chain.resubmit_interval_secs == 0 {
+            errors.push("chain.resubmit_interval_secs must be at least 1".to_string());
+        }
+
+        match &self.wallet.private_key {
+            None => {
+                errors.push("wallet.private_key must be set (ETH_WALLET_PRIVATE_KEY)".to_string())
+            }
+            Some(private_key)
+                if PrivateKeySigner::from_slice(private_key.expose_secret()).is_err() =>
+            {
+                errors.push("wallet.private_key is not a valid secp256k1 private key".to_string())
+            }
+            Some(_) => {}
+        }
+
+        if self.prover.max_iterations == 0 {
+            errors.push("prover.max_iterations must be at least 1".to_string());
+        }
+
+        if self.prover.workers == 0 {
+            errors.push("prover.workers must be at least 1".to_string());
+        }
+
+        errors.extend(self.prover.validate_execution_limits());
+
+        if self.prover.backend == ProverBackend::DevMode
+            && !self.chain.chain_id.is_some_and(is_local_devnet)
+        {
+            errors.push(
+                "prover.backend `dev_mode` fakes proofs and requires chain.chain_id to be a local \
+                 devnet (31337 or 1337)"
+                    .to_string(),
+            );
+        }
+
+        if self.prover.backend == ProverBackend::Remote {
+            if self.prover.remote_workers.is_empty() {
+                errors.push(
+                    "prover.remote_workers must list at least one worker for prover.backend \
+                     `remote` (PROVER_REMOTE_WORKERS)"
+                        .to_string(),
+                );
+            }
+            for worker in &self.prover.remote_workers {
+                if !matches!(worker.scheme(), "http" | "https") {
+                    errors.push(format!(
+                        "prover.remote_workers must be http or https URLs, got `{}`",
+                        worker
+                    ));
+                }
+            }
+            if self.prover.remote_poll_interval_ms == 0 {
+                errors.push("prover.remote_poll_interval_ms must be at least 1".to_string());
+            }
+            if self.prover.remote_health_interval_secs == 0 {
+                errors.push("prover.remote_health_interval_secs must be at least 1".to_string());
+            }
+        }
+
+        errors.extend(self.validate_guests());
+
+        if self.archive.prune_interval_secs == 0 {
+            errors.push("archive.prune_interval_secs must be at least 1".to_string());
+        }
+
+        if self.archive.retention_days > MAX_RETENTION_DAYS {
+            errors.push(format!(
+                "archive.retention_days must be at most {}",
+                MAX_RETENTION_DAYS
+            ));
+        }
+
+        for stage in [
+            RetryStage::Proving,
+            RetryStage::Send,
+            RetryStage::Confirmation,
+        ] {
+            let policy = self.retry.policy(stage);
+            if policy.max_attempts == 0 {
+                errors.push(format!(
+                    "retry.{}.max_attempts must be at least 1",
+                    stage.as_str()
+                ));
+            }
+            if policy.initial_backoff_ms > policy.max_backoff_ms {
+                errors.push(format!(
+                    "retry.{}.initial_backoff_ms must not exceed max_backoff_ms",
+                    stage.as_str()
+                ));
+            }
+        }
+
+        if let Err(e) = Url::parse(&self.telemetry.otlp_endpoint) {
+            errors.push(format!("telemetry.otlp_endpoint is not a valid URL: {}", e));
+        }
+
+        if let Err(e) = tracing_subscriber::EnvFilter::try_new(&self.telemetry.log_filter) {
+            errors.push(format!("telemetry.log_filter is invalid: {}", e));
+        }
+
+        if self.auth.api_keys.iter().any(|key| key.trim().is_empty()) {
+            errors.push("auth.api_keys must not contain empty keys".to_string());
+        }
+
+        errors
+    }
+
+    /// Checks what a `prover-worker` uses, it neither reaches the chain nor serves the API.
+    fn validate_worker(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+
+        if self.prover.max_iterations == 0 {
+            errors.push("prover.max_iterations must be at least 1".to_string());
+        }
+
+        if self.prover.workers == 0 {
+            errors.push("prover.workers must be at least 1".to_string());
+        }
+
+        errors.extend(self.prover.validate_execution_limits());
+
+        if self.prover.backend == ProverBackend::Remote {
+            errors.push(
+                "prover.backend `remote` cannot be used by a prover worker, pick the backend it \
+                 proves with"
+                    .to_string(),
+            );
+        }
+
+        errors.extend(self.validate_guests());
+
+        if let Err(e) = Url::parse(&self.telemetry.otlp_endpoint) {
+            errors.push(format!("telemetry.otlp_endpoint is not a valid URL: {}", e));
+        }
+
+        if let Err(e) = tracing_subscriber::EnvFilter::try_new(&self.telemetry.log_filter) {
+            errors.push(format!("telemetry.log_filter is invalid: {}", e));
+        }
+
+        errors
+    }
+
+    /// Versions must be unique per method and a contract can only accept one of them, as it
+    /// verifies a single image id.
+    fn validate_guests(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        let default_version = self.guests.default_version.as_deref();
+        let mut versions = vec![(FIBONACCI_METHOD, EMBEDDED_VERSION)];
+        let mut contracts: Vec<Address> = self.chain.contract.into_iter().collect();
+
+        for guest in &self.guests.versions {
+            let name = format!("guests.versions `{}@{}`", guest.method, guest.version);
+            if guest.version.trim().is_empty() {
+                errors.push("guests.versions must not contain empty versions".to_string());
+            } else if versions.contains(&(guest.method.as_str(), guest.version.as_str())) {
+                errors.push(format!("{} is listed more than once", name));
+            }
+            versions.push((&guest.method, &guest.version));
+
+            let is_default =
+                guest.method == FIBONACCI_METHOD && default_version == Some(guest.version.as_str());
+            match guest.contract {
+                Some(contract) if is_default && Some(contract) != self.chain.contract => errors
+                    .push(format!(
+                        "{} is the default version, its contract must be chain.contract",
+                        name
+                    )),
+                Some(contract) if contract.is_zero() => {
+                    errors.push(format!("{} contract must not be the zero address", name))
+                }
+                Some(contract) if !is_default && contracts.contains(&contract) => {
+                    errors.push(format!(
+                        "{} contract {} already accepts another version",
+                        name, contract
+                    ))
+                }
+                Some(contract) => contracts.push(contract),
+                None => {}
+            }
+        }
+
+        if let Some(version) = default_version {
+            if !versions.contains(&(FIBONACCI_METHOD, version)) {
+                errors.push(format!(
+                    "guests.default_version `{}` is not a `{}` version",
+                    version, FIBONACCI_METHOD
+                ));
+            }
+        }
+
+        errors
+    }
+
+    /// Checks the variables the RISC Zero prover reads on its own agree with the prover backend.
+    fn check_prover_env(&self, var: impl Fn(&str) -> Option<String>) -> Vec<String> {
+        let mut errors = Vec::new();
+
+        let dev_mode = var("RISC0_DEV_MODE")
+            .is_some_and(|value| matches!(value.to_lowercase().as_str(), "1" | "true" | "yes"));
+        if dev_mode && self.prover.backend != ProverBackend::DevMode {
+            errors.push(format!(
+                "RISC0_DEV_MODE fakes proofs and is only allowed with prover.backend `dev_mode`, \
+                 got `{}`",
+                self.prover.backend.as_str()
+            ));
+        }
+
+        if self.prover.backend == ProverBackend::Bonsai {
+            for key in ["BONSAI_API_URL", "BONSAI_API_KEY"] {
+                if var(key).filter(|value| !value.is_empty()).is_none() {
+                    errors.push(format!("{} must be set for prover.backend `bonsai`", key));
+                }
+            }
+        }
+
+        errors
+    }
+}
+
+fn env_value<T>(
+    var: &impl Fn(&str) -> Option<String>,
+    key: &str,
+    errors: &mut Vec<String>,
+) -> Option<T>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    let value = var(key)?;
+    value
+        .parse()
+        .map_err(|e| errors.push(format!("{}: {}", key, e)))
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    const PRIVATE_KEY: &str = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+    const CONTRACT: &str = "0xe7f1725E7734CE288F8367e1Bb143E90bb3F0512";
+
+    fn env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+
+        move |key| vars.get(key).cloned()
+    }
+
+    #[test]
+    fn test_validate_reports_all_errors() {
+        let mut config = Config::default();
+        config.chain.confirmations = 0;
+
+        let errors = config.validate();
+
+        assert_eq!(errors.len(), 4, "Unexpected errors: {:?}", errors);
+        assert!(errors.iter().any(|e| e.contains("chain.rpc_url")));
+        assert!(errors.iter().any(|e| e.contains("chain.contract")));
+        assert!(errors.iter().any(|e| e.contains("chain.confirmations")));
+        assert!(errors.iter().any(|e| e.contains("wallet.private_key")));
+    }
+
+    #[test]
+    fn test_guest_versions_are_unique_and_accept_one_contract_each() {
+        let contract = Address::repeat_byte(0x11);
+        let version = |version: &str, contract: Option<Address>| GuestVersionConfig {
+            method: FIBONACCI_METHOD.to_string(),
+            version: version.to_string(),
+            elf: PathBuf::from("fibonacci.elf"),
+            contract,
+        };
+        let mut config = Config::default();
+        config.chain.contract = Some(contract);
+        config.guests.default_version = Some("v3".to_string());
+        config.guests.versions = vec![
+            version(EMBEDDED_VERSION, None),
+            version("v2", Some(contract)),
+            version("v2", None),
+        ];
+
+        let errors = config.validate_guests();
+
+        assert_eq!(errors.len(), 4, "Unexpected errors: {:?}", errors);
+        assert!(errors[0].contains("fibonacci@embedded` is listed more than once"));
+        assert!(errors[1].contains("already accepts another version"));
+        assert!(errors[2].contains("fibonacci@v2` is listed more than once"));
+        assert!(errors[3].contains("guests.default_version `v3`"));
+    }
+
+    #[test]
+    fn test_layers_override_in_order() {
+        let mut config: Config = toml::from_str(&format!(
+            r#"
+            [server]
+            listen_addr = "0.0.0.0:9000"
+
+            [chain]
+            rpc_url = "http://localhost:8545"
+            contract = "{CONTRACT}"
+            confirmations = 3
+
+            [wallet]
+            private_key = "{PRIVATE_KEY}"
+            "#
+        ))
+        .unwrap();
+
+        let errors = config.apply_env(env(&[
+            ("ETH_TX_CONFIRMATIONS", "5"),
+            ("RPC_URL", "http://anvil:8545"),
+        ]));
+        config.apply_cli(&Cli {
+            rpc_url: Some(Url::parse("https://rpc.example.com").unwrap()),
+            ..Default::default()
+        });
+
+        assert!(errors.is_empty(), "Unexpected errors: {:?}", errors);
+        assert!(config.validate().is_empty());
+        assert_eq!(config.server.listen_addr.port(), 9000);
+        assert_eq!(config.chain.confirmations, 5);
+        assert_eq!(
+            config.chain.rpc_url.unwrap().as_str(),
+            "https://rpc.example.com/"
+        );
+    }
+
+    #[test]
+    fn test_env_collects_every_invalid_value() {
+        let mut config = Config::default();
+
+        let errors = config.apply_env(env(&[
+            ("ETH_CONTRACT", "not-an-address"),
+            ("ETH_TX_TIMEOUT_SECS", "-1"),
+            ("ETH_WALLET_PRIVATE_KEY", "0xzz"),
+            ("STORAGE_BACKEND", "postgres"),
+        ]));
+
+        assert_eq!(errors.len(), 4, "Unexpected errors: {:?}", errors);
+        assert!(errors[0].starts_with("ETH_CONTRACT"));
+        assert!(errors[1].starts_with("ETH_TX_TIMEOUT_SECS"));
+        assert!(errors[2].starts_with("ETH_WALLET_PRIVATE_KEY"));
+        assert!(errors[3].starts_with("STORAGE_BACKEND"));
+    }
+
+    #[test]
+    fn test_debug_hides_secrets() {
+        let mut config = Config::default();
+        config.telemetry.client_id_salt = "pepper".to_string();
+        config.auth.api_keys = vec!["my-very-secret-api-key".to_string()];
+        config.apply_env(env(&[
+            ("ETH_WALLET_PRIVATE_KEY", PRIVATE_KEY),
+            (
+                "RPC_URL",
+                "https://eth-sepolia.g.alchemy.com/v2/secret-api-key",
+            ),
+        ]));
+        let cli = Cli {
+            rpc_url: config.chain.rpc_url.clone(),
+            ..Cli::default()
+        };
+
+        for printed in [format!("{:?}", config), format!("{:?}", cli)] {
+            assert!(printed.contains("eth-sepolia.g.alchemy.com"));
+            assert!(!printed.contains("secret-api-key"));
+            assert!(!printed.contains(PRIVATE_KEY));
+            assert!(!printed.contains("pepper"));
+            assert!(!printed.contains("my-very-secret-api-key"));
+        }
+    }
+
+    #[test]
+    fn test_redacted_toml_hides_secrets() {
+        let mut config = Config::default();
+        config.telemetry.client_id_salt = "pepper".to_string();
+        config.auth.api_keys = vec!["my-very-secret-api-key".to_string()];
+        config.apply_env(env(&[
+            ("ETH_WALLET_PRIVATE_KEY", PRIVATE_KEY),
+            (
+                "RPC_URL",
+                "https://eth-sepolia.g.alchemy.com/v2/secret-api-key",
+            ),
+        ]));
+
+        let rendered = config.to_redacted_toml().unwrap();
+
+        assert!(rendered.contains("private_key = \"[REDACTED]\""));
+        assert!(rendered.contains("rpc_url = \"https://eth-sepolia.g.alchemy.com/[REDACTED]\""));
+        assert!(!rendered.contains(PRIVATE_KEY));
+        assert!(!rendered.contains("secret-api-key"));
+        assert!(!rendered.contains("pepper"));
+        assert!(!rendered.contains("my-very-secret-api-key"));
+    }
+
+    #[test]
+    fn test_non_reloadable_changes_are_listed() {
+        let current = Config::default();
+        let mut reloaded = Config::default();
+        reloaded.chain.confirmations = 3;
+        reloaded.limits.requests_per_minute = 60;
+        reloaded.auth.api_keys = vec!["0123456789abcdef".to_string()];
+        reloaded.telemetry.log_filter = "debug".to_string();
+
+        assert!(current.non_reloadable_changes(&reloaded).is_empty());
+
+        reloaded.server.listen_addr = SocketAddr::from(([0, 0, 0, 0], 8080));
+        reloaded.telemetry.environment = "production".to_string();
+
+        assert_eq!(
+            current.non_reloadable_changes(&reloaded),
+            vec!["server", "telemetry"]
+        );
+    }
+
+    #[test]
+    fn test_dev_mode_requires_a_local_devnet() {
+        let mut config = Config::default();
+        config.apply_env(env(&[
+            ("PROVER_BACKEND", "dev_mode"),
+            ("ETH_CHAIN_ID", "1"),
+        ]));
+
+        let errors = config.validate();
+        assert!(errors
+            .iter()
+            .any(|e| e.contains("prover.backend `dev_mode`")));
+
+        config.chain.chain_id = Some(31337);
+        let errors = config.validate();
+        assert!(!errors.iter().any(|e| e.contains("prover.backend")));
+    }
+
+    #[test]
+    fn test_prover_env_must_match_the_backend() {
+        let mut config = Config::default();
+
+        let errors = config.check_prover_env(env(&[("RISC0_DEV_MODE", "true")]));
+        assert_eq!(errors.len(), 1, "Unexpected errors: {:?}", errors);
+        assert!(errors[0].starts_with("RISC0_DEV_MODE"));
+
+        config.prover.backend = ProverBackend::Bonsai;
+        let errors = config.check_prover_env(env(&[("BONSAI_API_URL", "https://api.bonsai.xyz")]));
+        assert_eq!(errors.len(), 1, "Unexpected errors: {:?}", errors);
+        assert!(errors[0].starts_with("BONSAI_API_KEY"));
+    }
+
+    #[test]
+    fn test_remote_backend_requires_workers() {
+        let mut config = Config::default();
+
+        let errors = config.apply_env(env(&[
+            ("PROVER_BACKEND", "remote"),
+            (
+                "PROVER_REMOTE_WORKERS",
+                "http://127.0.0.1:8090, ftp://prover:2121,",
+            ),
+        ]));
+        assert!(errors.is_empty(), "Unexpected errors: {:?}", errors);
+        assert_eq!(config.prover.remote_workers.len(), 2);
+
+        let errors = config.validate();
+        assert!(errors
+            .iter()
+            .any(|e| e.contains("must be http or https URLs, got `ftp://prover:2121/`")));
+
+        config.prover.remote_workers.clear();
+        let errors = config.validate();
+        assert!(errors
+            .iter()
+            .any(|e| e.contains("prover.remote_workers must list at least one worker")));
+    }
+
+    #[test]
+    fn test_execution_limits_are_validated() {
+        let mut config = Config::default();
+
+        let errors = config.apply_env(env(&[
+            ("PROVER_SESSION_CYCLE_LIMIT", "0"),
+            ("PROVER_SEGMENT_LIMIT_PO2", "25"),
+            ("PROVER_TIMEOUT_SECS", "600"),
+        ]));
+        assert!(errors.is_empty(), "Unexpected errors: {:?}", errors);
+        assert_eq!(
+            config.prover.proving_timeout(),
+            Some(Duration::from_secs(600))
+        );
+
+        let errors = config.prover.validate_execution_limits();
+        assert_eq!(errors.len(), 2, "Unexpected errors: {:?}", errors);
+        assert!(errors[0].contains("prover.session_cycle_limit"));
+        assert!(errors[1].contains("prover.segment_limit_po2 must be between 13 and 24"));
+    }
+
+    #[test]
+    fn test_retention_days_must_fit_the_archive_timestamps() {
+        let mut config = Config::default();
+        config.archive.retention_days = u64::MAX;
+
+        assert_eq!(
+            config.archive.retention(),
+            Some(Duration::from_secs(u64::MAX))
+        );
+        assert!(config
+            .validate()
+            .iter()
+            .any(|e| e.contains("archive.retention_days must be at most")));
+
+        config.archive.retention_days = MAX_RETENTION_DAYS;
+        assert!(!config
+            .validate()
+            .iter()
+            .any(|e| e.contains("archive.retention_days")));
+    }
+
+    #[test]
+    fn test_worker_does_not_need_the_chain() {
+        let mut config = Config::default();
+
+        assert!(config.validate_worker().is_empty());
+
+        config.prover.backend = ProverBackend::Remote;
+        let errors = config.validate_worker();
+        assert_eq!(errors.len(), 1, "Unexpected errors: {:?}", errors);
+        assert!(errors[0].contains("cannot be used by a prover worker"));
+    }
+
+    #[test]
+    fn test_private_key_prefix_is_optional() {
+        let mut with_prefix = Config::default();
+        let mut without_prefix = Config::default();
+
+        with_prefix.apply_env(env(&[(
+            "ETH_WALLET_PRIVATE_KEY",
+            &format!("0x{PRIVATE_KEY}"),
+        )]));
+        without_prefix.apply_env(env(&[("ETH_WALLET_PRIVATE_KEY", PRIVATE_KEY)]));
+
+        assert_eq!(
+            with_prefix.wallet.private_key.unwrap().expose_secret(),
+            without_prefix.wallet.private_key.unwrap().expose_secret()
+        );
+    }
+}
@@ -3,12 +3,188 @@ pub enum FibchainError {
     #[error("zkVM Error: {0}")]
     ZkVM(String),
 
+    #[error("Remote prover Error: {0}")]
+    RemoteProver(String),
+
     #[error(transparent)]
     Alloy(#[from] alloy::contract::Error),
 
     #[error(transparent)]
     AlloyPendingTransaction(#[from] alloy::providers::PendingTransactionError),
 
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+
+    #[error("Storage Error: {0}")]
+    Storage(String),
+
+    #[error("Job cannot move from `{from}` to `{to}`")]
+    InvalidJobTransition {
+        from: &'static str,
+        to: &'static str,
+    },
+
+    #[error(
+        "The prover queue is full, retry in {} seconds",
+        .retry_after.as_secs().max(1)
+    )]
+    ProverQueueFull { retry_after: std::time::Duration },
+
+    #[error("The service is shutting down and does not accept new work")]
+    ShuttingDown,
+
+    #[error("Iterations must be between 1 and {max}, got {iterations}")]
+    InvalidIterations { iterations: u16, max: u16 },
+
+    #[error("The job was cancelled")]
+    Cancelled,
+
+    #[error(
+        "The chain is unavailable, job {job_id} keeps its proof and is submitted once it is back"
+    )]
+    SubmissionDeferred { job_id: crate::domain::job::JobId },
+
+    #[error("Job {job_id} was cancelled")]
+    JobCancelled { job_id: crate::domain::job::JobId },
+
+    #[error("Job {job_id} is `{status}` and can no longer be cancelled")]
+    NotCancellable {
+        job_id: crate::domain::job::JobId,
+        status: &'static str,
+    },
+
+    #[error("`{kind}` receipts cannot be verified on-chain, only `groth16` ones")]
+    UnverifiableReceiptKind { kind: &'static str },
+
+    #[error("The receipt archive is disabled, set archive.dir to enable it")]
+    ArchiveDisabled,
+
+    #[error(
+        "Contract {contract} accepts proofs of image id {deployed} but the guest has image id \
+         {expected}, redeploy the contract for this guest or run the guest it was deployed with"
+    )]
+    ImageIdMismatch {
+        contract: alloy_primitives::Address,
+        expected: alloy_primitives::B256,
+        deployed: alloy_primitives::B256,
+    },
+
+    #[error("Unknown `{method}` guest version `{version}`")]
+    UnknownGuestVersion { method: String, version: String },
+
+    #[error(
+        "No configured contract accepts proofs of guest image id {image_id}, set the `contract` \
+         of its version to submit them"
+    )]
+    GuestNotDeployed { image_id: alloy_primitives::B256 },
+
+    #[error(
+        "The guest ran past the limit of {limit} cycles, request fewer iterations or raise \
+         prover.session_cycle_limit"
+    )]
+    CycleLimitExceeded { limit: u64 },
+
+    #[error(
+        "The proof took longer than {} seconds and was abandoned",
+        .timeout.as_secs()
+    )]
+    ProvingTimeout { timeout: std::time::Duration },
+
     #[error(transparent)]
     Generic(#[from] color_eyre::Report),
 }
+
+impl FibchainError {
+    /// A stable name for the kind of error, reported in job events.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::ZkVM(_) => "zkvm",
+            Self::RemoteProver(_) => "remote_prover",
+            Self::Alloy(_) => "contract",
+            Self::AlloyPendingTransaction(_) => "pending_transaction",
+            Self::Config(_) => "config",
+            Self::Storage(_) => "storage",
+            Self::InvalidJobTransition { .. } => "invalid_job_transition",
+            Self::ProverQueueFull { .. } => "prover_queue_full",
+            Self::ShuttingDown => "shutting_down",
+            Self::InvalidIterations { .. } => "invalid_iterations",
+            Self::Cancelled => "cancelled",
+            Self::SubmissionDeferred { .. } => "submission_deferred",
+            Self::JobCancelled { .. } => "job_cancelled",
+            Self::NotCancellable { .. } => "not_cancellable",
+            Self::UnverifiableReceiptKind { .. } => "unverifiable_receipt_kind",
+            Self::ArchiveDisabled => "archive_disabled",
+            Self::ImageIdMismatch { .. } => "image_id_mismatch",
+            Self::UnknownGuestVersion { .. } => "unknown_guest_version",
+            Self::GuestNotDeployed { .. } => "guest_not_deployed",
+            Self::CycleLimitExceeded { .. } => "cycle_limit_exceeded",
+            Self::ProvingTimeout { .. } => "proving_timeout",
+            Self::Generic(_) => "generic",
+        }
+    }
+
+    /// Whether trying the same operation again may succeed, such as after a dropped connection,
+    /// a rate limited or unavailable RPC node or prover service, or a receipt that was not seen in
+    /// time. Reverts, invalid input and local prover failures are permanent.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::RemoteProver(_) => true,
+            Self::Alloy(alloy::contract::Error::TransportError(e)) => is_retryable_rpc_error(e),
+            Self::Alloy(alloy::contract::Error::PendingTransactionError(e))
+            | Self::AlloyPendingTransaction(e) => is_retryable_pending_transaction_error(e),
+            _ => false,
+        }
+    }
+}
+
+fn is_retryable_pending_transaction_error(
+    error: &alloy::providers::PendingTransactionError,
+) -> bool {
+    use alloy::providers::PendingTransactionError;
+
+    match error {
+        PendingTransactionError::TransportError(e) => is_retryable_rpc_error(e),
+        PendingTransactionError::FailedToRegister | PendingTransactionError::TxWatcher(_) => true,
+        _ => false,
+    }
+}
+
+fn is_retryable_rpc_error(error: &alloy::transports::TransportError) -> bool {
+    use alloy::transports::{RpcError, TransportErrorKind};
+
+    match error {
+        RpcError::Transport(TransportErrorKind::HttpError(e)) => e.status == 429 || e.status >= 500,
+        RpcError::Transport(_) | RpcError::NullResp => true,
+        // -32005 is the limit exceeded code used by most node providers.
+        RpcError::ErrorResp(payload) => {
+            payload.code == 429
+                || payload.code == -32005
+                || payload.message.to_lowercase().contains("rate limit")
+        }
+        _ => false,
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("failed to read config file {path}: {source}")]
+    Read {
+        path: std::path::PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("failed to parse config file {path}: {source}")]
+    Parse {
+        path: std::path::PathBuf,
+        source: toml::de::Error,
+    },
+
+    #[error("failed to render configuration: {0}")]
+    Render(#[from] toml::ser::Error),
+
+    #[error("{} can only be changed with a restart", .0.join(", "))]
+    NotReloadable(Vec<&'static str>),
+
+    #[error("invalid configuration:\n  - {}", .0.join("\n  - "))]
+    Invalid(Vec<String>),
+}
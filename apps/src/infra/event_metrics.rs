@@ -0,0 +1,51 @@
+use crate::domain::event::JobEvent;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::{debug, info, warn};
+
+/// Records metrics from job events until the use case is dropped.
+pub async fn record(mut events: broadcast::Receiver<JobEvent>) {
+    loop {
+        match events.recv().await {
+            Ok(event) => record_event(&event),
+            Err(RecvError::Lagged(missed)) => {
+                warn!(
+                    missed = missed,
+                    "Job event metrics fell behind and missed events"
+                )
+            }
+            Err(RecvError::Closed) => return,
+        }
+    }
+}
+
+fn record_event(event: &JobEvent) {
+    debug!(job_id = %event.job_id(), event = event.name(), "Job event");
+
+    match event {
+        JobEvent::ProofRequested { iterations, .. } => info!(
+            monotonic_counter.proofs_requested = 1_u64,
+            iterations = *iterations,
+            "Proof requested"
+        ),
+        JobEvent::ProofGenerated {
+            cycles, duration, ..
+        } => info!(
+            histogram.proof_cycles = *cycles,
+            histogram.proof_duration_ms = duration.as_millis() as u64,
+            "Proof generated"
+        ),
+        JobEvent::TxSent { .. } => info!(
+            monotonic_counter.transactions_sent = 1_u64,
+            "Transaction sent"
+        ),
+        JobEvent::TxConfirmed { gas, .. } => info!(
+            histogram.transaction_gas_used = *gas,
+            "Transaction confirmed"
+        ),
+        JobEvent::JobFailed { code, .. } => info!(
+            monotonic_counter.job_failures = 1_u64,
+            code = *code,
+            "Job failed"
+        ),
+    }
+}
@@ -0,0 +1,231 @@
+use crate::infra::config::Config;
+use crate::infra::error::ConfigError;
+use crate::prelude::{FibchainError, Result};
+use alloy_primitives::{Address, B256};
+use methods::{FIBONACCI_ELF, FIBONACCI_ID};
+use risc0_zkvm::sha::Digest;
+use std::fmt;
+use std::sync::Arc;
+use tracing::info;
+
+/// The only method the host serves.
+pub const FIBONACCI_METHOD: &str = "fibonacci";
+
+/// Version of the guest built into the binary by the `methods` crate.
+pub const EMBEDDED_VERSION: &str = "embedded";
+
+/// A version of a guest program, with the contract that accepts its proofs.
+#[derive(Clone)]
+pub struct GuestProgram {
+    pub method: String,
+    pub version: String,
+    pub elf: Arc<[u8]>,
+    pub image_id: B256,
+    /// Contract the proofs are submitted to, `None` when they are only verified off-chain.
+    pub contract: Option<Address>,
+}
+
+/// The ELF is left out, it is megabytes long.
+impl fmt::Debug for GuestProgram {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GuestProgram")
+            .field("method", &self.method)
+            .field("version", &self.version)
+            .field("image_id", &self.image_id)
+            .field("contract", &self.contract)
+            .finish()
+    }
+}
+
+/// Guest versions served side by side, so proofs for the old and new contract can both be
+/// generated while a contract is migrated to a new guest.
+#[derive(Debug, Clone)]
+pub struct GuestRegistry {
+    programs: Vec<GuestProgram>,
+    default_version: String,
+}
+
+impl GuestRegistry {
+    /// Only the guest built into the binary, with no contract.
+    pub fn embedded() -> Self {
+        Self {
+            programs: vec![GuestProgram {
+                method: FIBONACCI_METHOD.to_string(),
+                version: EMBEDDED_VERSION.to_string(),
+                elf: Arc::from(FIBONACCI_ELF),
+                image_id: B256::from_slice(Digest::from(FIBONACCI_ID).as_bytes()),
+                contract: None,
+            }],
+            default_version: EMBEDDED_VERSION.to_string(),
+        }
+    }
+
+    /// The built-in guest and every configured version, whose ELF files are read to compute
+    /// their image ids. `chain.contract` accepts the default version.
+    pub fn load(config: &Config) -> std::result::Result<Self, ConfigError> {
+        let mut registry = Self::embedded();
+        let mut errors = Vec::new();
+
+        for guest in &config.guests.versions {
+            let image_id = std::fs::read(&guest.elf)
+                .map_err(|e| e.to_string())
+                .and_then(|elf| {
+                    risc0_zkvm::compute_image_id(&elf)
+                        .map(|image_id| (elf, image_id))
+                        .map_err(|e| e.to_string())
+                });
+            match image_id {
+                Ok((elf, image_id)) => registry.programs.push(GuestProgram {
+                    method: guest.method.clone(),
+                    version: guest.version.clone(),
+                    elf: Arc::from(elf),
+                    image_id: B256::from_slice(image_id.as_bytes()),
+                    contract: guest.contract,
+                }),
+                Err(e) => errors.push(format!(
+                    "guests.versions `{}@{}` ELF {} cannot be loaded: {}",
+                    guest.method,
+                    guest.version,
+                    guest.elf.display(),
+                    e
+                )),
+            }
+        }
+        if !errors.is_empty() {
+            return Err(ConfigError::Invalid(errors));
+        }
+
+        if let Some(version) = &config.guests.default_version {
+            registry.default_version = version.clone();
+        }
+        let default_version = registry.default_version.clone();
+        if let Some(program) = registry.programs.iter_mut().find(|program| {
+            program.method == FIBONACCI_METHOD && program.version == default_version
+        }) {
+            program.contract = config.chain.contract;
+        }
+
+        for program in &registry.programs {
+            info!(
+                method = program.method,
+                version = program.version,
+                image_id = %program.image_id,
+                contract = program.contract.map(|contract| contract.to_string()),
+                "Guest program registered"
+            );
+        }
+        Ok(registry)
+    }
+
+    /// The pinned version of a method, or its default version when none is pinned.
+    pub fn resolve(&self, method: &str, version: Option<&str>) -> Result<&GuestProgram> {
+        let version = version.unwrap_or(&self.default_version);
+
+        self.programs
+            .iter()
+            .find(|program| program.method == method && program.version == version)
+            .ok_or_else(|| FibchainError::UnknownGuestVersion {
+                method: method.to_string(),
+                version: version.to_string(),
+            })
+    }
+
+    pub fn by_image_id(&self, image_id: B256) -> Option<&GuestProgram> {
+        self.programs
+            .iter()
+            .find(|program| program.image_id == image_id)
+    }
+
+    /// The `fibonacci` version with this image id, or the default version. Versions are resolved
+    /// by the caller, so an unknown image id belongs to a version removed from the configuration.
+    pub fn guest(&self, image_id: Option<B256>) -> Result<&GuestProgram> {
+        match image_id {
+            None => self.resolve(FIBONACCI_METHOD, None),
+            Some(image_id) => {
+                self.by_image_id(image_id)
+                    .ok_or_else(|| FibchainError::UnknownGuestVersion {
+                        method: FIBONACCI_METHOD.to_string(),
+                        version: image_id.to_string(),
+                    })
+            }
+        }
+    }
+
+    /// Versions whose proofs are submitted to a contract.
+    pub fn deployed(&self) -> impl Iterator<Item = &GuestProgram> {
+        self.programs
+            .iter()
+            .filter(|program| program.contract.is_some())
+    }
+}
+
+#[cfg(test)]
+impl GuestRegistry {
+    /// The default version, with the image id of [`crate::domain::provider::FibonacciProof::fake`]
+    /// proofs and a contract, and an off-chain `v2` version, both without an ELF.
+    pub fn fake() -> Self {
+        let program = |version: &str, image_id: u8, contract: Option<Address>| GuestProgram {
+            method: FIBONACCI_METHOD.to_string(),
+            version: version.to_string(),
+            elf: Arc::from(Vec::new()),
+            image_id: B256::repeat_byte(image_id),
+            contract,
+        };
+
+        Self {
+            programs: vec![
+                program(EMBEDDED_VERSION, 0x01, Some(Address::repeat_byte(0x11))),
+                program("v2", 0x03, None),
+            ],
+            default_version: EMBEDDED_VERSION.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infra::config::GuestVersionConfig;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_default_version_is_submitted_to_the_chain_contract() {
+        let contract = Address::repeat_byte(0x11);
+        let mut config = Config::default();
+        config.chain.contract = Some(contract);
+
+        let registry = GuestRegistry::load(&config).unwrap();
+        let embedded = registry.resolve(FIBONACCI_METHOD, None).unwrap();
+
+        assert_eq!(embedded.version, EMBEDDED_VERSION);
+        assert_eq!(embedded.contract, Some(contract));
+        assert_eq!(
+            registry.by_image_id(embedded.image_id).unwrap().version,
+            EMBEDDED_VERSION
+        );
+        assert_eq!(registry.deployed().count(), 1);
+        assert!(matches!(
+            registry.resolve(FIBONACCI_METHOD, Some("v2")),
+            Err(FibchainError::UnknownGuestVersion { .. })
+        ));
+    }
+
+    #[test]
+    fn test_unreadable_elf_is_a_config_error() {
+        let mut config = Config::default();
+        config.guests.versions.push(GuestVersionConfig {
+            method: FIBONACCI_METHOD.to_string(),
+            version: "v2".to_string(),
+            elf: PathBuf::from("does/not/exist.elf"),
+            contract: None,
+        });
+
+        let loaded = GuestRegistry::load(&config);
+
+        assert!(
+            matches!(&loaded, Err(ConfigError::Invalid(errors)) if errors[0].contains("fibonacci@v2")),
+            "Expected the ELF to be reported, got: {:?}",
+            loaded
+        );
+    }
+}
@@ -0,0 +1,158 @@
+use crate::domain::job::JobId;
+use alloy_primitives::TxHash;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio_util::sync::CancellationToken;
+
+/// Cancellations requested for running jobs.
+///
+/// A job can be cancelled until it starts submitting its proof. Once its transaction is sent, it
+/// can only be cancelled by replacing that transaction.
+#[derive(Clone, Default)]
+pub struct JobCancellations {
+    jobs: Arc<Mutex<HashMap<JobId, Entry>>>,
+}
+
+#[derive(Default)]
+struct Entry {
+    /// Missing while a cancellation is requested for a job that did not start running yet.
+    token: Option<CancellationToken>,
+    requested: bool,
+    submitting: bool,
+    replacement: Option<TxHash>,
+}
+
+impl Entry {
+    fn cancel(&self) {
+        if let Some(token) = &self.token {
+            token.cancel();
+        }
+    }
+}
+
+impl JobCancellations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a running job whose work stops when `token` is cancelled, which happens right
+    /// away when its cancellation was requested before it started.
+    pub fn register(&self, job: JobId, token: CancellationToken) -> Registration {
+        let mut jobs = lock(&self.jobs);
+        let entry = jobs.entry(job).or_default();
+        entry.token = Some(token);
+        if entry.requested {
+            entry.cancel();
+        }
+
+        Registration {
+            jobs: self.jobs.clone(),
+            job,
+        }
+    }
+
+    /// Requests the job to stop, returning `false` when it already started submitting.
+    pub fn cancel(&self, job: JobId) -> bool {
+        let mut jobs = lock(&self.jobs);
+        let entry = jobs.entry(job).or_default();
+        if entry.submitting {
+            return false;
+        }
+
+        entry.requested = true;
+        entry.cancel();
+        true
+    }
+
+    /// Tells the running job its sent transaction was replaced by `replacement`.
+    pub fn replace(&self, job: JobId, replacement: TxHash) {
+        if let Some(entry) = lock(&self.jobs).get_mut(&job) {
+            entry.replacement = Some(replacement);
+            entry.cancel();
+        }
+    }
+}
+
+/// A running job's view of its cancellation, forgotten once dropped.
+pub struct Registration {
+    jobs: Arc<Mutex<HashMap<JobId, Entry>>>,
+    job: JobId,
+}
+
+impl Registration {
+    pub fn is_requested(&self) -> bool {
+        self.read(|entry| entry.requested)
+    }
+
+    /// Marks the job as submitting so it can no longer be cancelled, returning `false` when its
+    /// cancellation was requested first.
+    pub fn start_submitting(&self) -> bool {
+        let mut jobs = lock(&self.jobs);
+        let Some(entry) = jobs.get_mut(&self.job) else {
+            return true;
+        };
+        entry.submitting = !entry.requested;
+
+        entry.submitting
+    }
+
+    /// The transaction that replaced the job's sent transaction, if any.
+    pub fn replacement(&self) -> Option<TxHash> {
+        self.read(|entry| entry.replacement)
+    }
+
+    fn read<T: Default>(&self, read: impl FnOnce(&Entry) -> T) -> T {
+        lock(&self.jobs)
+            .get(&self.job)
+            .map(read)
+            .unwrap_or_default()
+    }
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        lock(&self.jobs).remove(&self.job);
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancellation_requested_before_the_job_runs_applies_once_registered() {
+        let cancellations = JobCancellations::new();
+        let job = JobId::random();
+        let token = CancellationToken::new();
+
+        assert!(cancellations.cancel(job));
+        let registration = cancellations.register(job, token.clone());
+
+        assert!(token.is_cancelled());
+        assert!(registration.is_requested());
+        assert!(!registration.start_submitting());
+    }
+
+    #[test]
+    fn test_submitting_job_refuses_cancellation() {
+        let cancellations = JobCancellations::new();
+        let job = JobId::random();
+        let token = CancellationToken::new();
+        let registration = cancellations.register(job, token.clone());
+
+        assert!(registration.start_submitting());
+        assert!(!cancellations.cancel(job));
+        assert!(!token.is_cancelled());
+
+        cancellations.replace(job, TxHash::repeat_byte(0x01));
+        assert!(token.is_cancelled());
+        assert_eq!(registration.replacement(), Some(TxHash::repeat_byte(0x01)));
+
+        drop(registration);
+        assert!(lock(&cancellations.jobs).is_empty());
+    }
+}
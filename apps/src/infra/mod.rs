@@ -1,5 +1,24 @@
 pub mod app_state;
+pub mod bonsai_mock;
+pub mod command;
+pub mod config;
 pub mod error;
+pub mod event_metrics;
+pub mod guest_registry;
+pub mod job_cancellation;
 pub mod observability;
+pub mod proof_cache;
+pub mod prover_pool;
+pub mod prover_queue;
+pub mod prover_worker;
 pub mod provider;
+pub mod rate_limit;
+pub mod receipt_archive;
+pub mod redaction;
+pub mod reload;
+pub mod retry;
+pub mod secret;
+pub mod shutdown;
+pub mod single_flight;
 pub mod sol;
+pub mod store;
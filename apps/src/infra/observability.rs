@@ -1,5 +1,7 @@
-use opentelemetry::{global, KeyValue};
+use crate::infra::config::TelemetryConfig;
+use crate::infra::redaction::{self, RedactionPolicy};
 use opentelemetry::trace::TracerProvider;
+use opentelemetry::{global, KeyValue};
 use opentelemetry_appender_tracing::layer::OpenTelemetryTracingBridge;
 use opentelemetry_otlp::WithExportConfig;
 use opentelemetry_sdk::logs::SdkLoggerProvider;
@@ -10,31 +12,31 @@ use opentelemetry_semantic_conventions::resource::{
     DEPLOYMENT_ENVIRONMENT_NAME, SERVICE_NAME, SERVICE_VERSION,
 };
 use opentelemetry_semantic_conventions::SCHEMA_URL;
-use tracing::Level;
 use tracing_opentelemetry::{MetricsLayer, OpenTelemetryLayer};
+use tracing_subscriber::filter::{LevelFilter, Targets};
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
-use tracing_subscriber::Layer;
+use tracing_subscriber::{reload, EnvFilter, Layer, Registry};
 
-pub fn setup_tracing() -> OtelGuard {
-    let otel_exporter_url =
-        std::env::var("OTEL_EXPORTER_URL").unwrap_or_else(|_| "http://localhost:4317".to_string());
+pub fn setup_tracing(config: &TelemetryConfig) -> OtelGuard {
+    redaction::init(RedactionPolicy::from(config));
 
-    let otel_meter_provider = init_meter_provider(otel_exporter_url.as_str());
-    let otel_tracer_provider = init_tracer_provider(otel_exporter_url.as_str());
-    let otel_logger_provider = init_logger_provider(otel_exporter_url.as_str());
+    let otel_exporter_url = config.otlp_endpoint.as_str();
+    let resource = get_span_resource(&config.environment);
 
-    let otel_layer = OpenTelemetryTracingBridge::new(&otel_logger_provider);
-    let otel_env_filter_layer = tracing_subscriber::EnvFilter::from_default_env()
-        .add_directive("hyper=off".parse().unwrap())
-        .add_directive("opentelemetry=off".parse().unwrap())
-        .add_directive("tonic=off".parse().unwrap())
-        .add_directive("h2=off".parse().unwrap())
-        .add_directive("reqwest=off".parse().unwrap());
-    let otel_logger_layer = otel_layer.with_filter(otel_env_filter_layer);
+    let otel_meter_provider = init_meter_provider(otel_exporter_url, resource.clone());
+    let otel_tracer_provider = init_tracer_provider(otel_exporter_url, resource.clone());
+    let otel_logger_provider = init_logger_provider(otel_exporter_url, resource);
 
-    let fmt_env_filter_layer = tracing_subscriber::EnvFilter::from_default_env()
-        .add_directive("opentelemetry=debug".parse().unwrap());
+    let otel_layer = OpenTelemetryTracingBridge::new(&otel_logger_provider);
+    let otel_targets_filter_layer = Targets::new()
+        .with_default(LevelFilter::TRACE)
+        .with_target("hyper", LevelFilter::OFF)
+        .with_target("opentelemetry", LevelFilter::OFF)
+        .with_target("tonic", LevelFilter::OFF)
+        .with_target("h2", LevelFilter::OFF)
+        .with_target("reqwest", LevelFilter::OFF);
+    let otel_logger_layer = otel_layer.with_filter(otel_targets_filter_layer);
 
     let fmt_logger_layer = tracing_subscriber::fmt::layer()
         .with_ansi(true)
@@ -43,40 +45,39 @@ pub fn setup_tracing() -> OtelGuard {
         .with_target(true)
         .with_thread_ids(true)
         .with_thread_names(true)
-        .pretty()
-        .with_filter(fmt_env_filter_layer);
+        .pretty();
 
     let tracer = otel_tracer_provider.tracer("fibchain-otel-subscriber");
     let otel_tracer_layer = OpenTelemetryLayer::new(tracer);
 
     let otel_meter_layer = MetricsLayer::new(otel_meter_provider.clone());
 
-    let level_filter_layer = tracing_subscriber::filter::LevelFilter::from_level(
-        Level::INFO,
-    );
+    let (log_filter_layer, log_filter_handle) =
+        reload::Layer::new(EnvFilter::new(&config.log_filter));
 
     tracing_subscriber::Registry::default()
-        .with(level_filter_layer)
+        .with(log_filter_layer)
         .with(otel_logger_layer)
         .with(fmt_logger_layer)
         .with(otel_meter_layer)
         .with(otel_tracer_layer)
         .init();
-    
+
     OtelGuard {
         logger_provider: otel_logger_provider,
         tracer_provider: otel_tracer_provider,
         meter_provider: otel_meter_provider,
+        log_filter: LogFilterHandle(log_filter_handle),
     }
 }
 
-fn get_span_resource() -> Resource {
+fn get_span_resource(environment: &str) -> Resource {
     Resource::builder()
         .with_schema_url(
             [
                 KeyValue::new(SERVICE_NAME, env!("CARGO_PKG_NAME")),
                 KeyValue::new(SERVICE_VERSION, env!("CARGO_PKG_VERSION")),
-                KeyValue::new(DEPLOYMENT_ENVIRONMENT_NAME, "development"),
+                KeyValue::new(DEPLOYMENT_ENVIRONMENT_NAME, environment.to_string()),
             ],
             SCHEMA_URL,
         )
@@ -84,7 +85,7 @@ fn get_span_resource() -> Resource {
         .build()
 }
 
-fn init_meter_provider(otel_collector_url: &str) -> SdkMeterProvider {
+fn init_meter_provider(otel_collector_url: &str, resource: Resource) -> SdkMeterProvider {
     let exporter = opentelemetry_otlp::MetricExporter::builder()
         .with_tonic()
         .with_endpoint(otel_collector_url)
@@ -97,7 +98,7 @@ fn init_meter_provider(otel_collector_url: &str) -> SdkMeterProvider {
         .build();
 
     let meter_provider = MeterProviderBuilder::default()
-        .with_resource(get_span_resource())
+        .with_resource(resource)
         .with_reader(reader)
         .build();
 
@@ -106,7 +107,7 @@ fn init_meter_provider(otel_collector_url: &str) -> SdkMeterProvider {
     meter_provider
 }
 
-fn init_tracer_provider(otel_collector_url: &str) -> SdkTracerProvider {
+fn init_tracer_provider(otel_collector_url: &str, resource: Resource) -> SdkTracerProvider {
     let exporter = opentelemetry_otlp::SpanExporter::builder()
         .with_tonic()
         .with_endpoint(otel_collector_url)
@@ -116,7 +117,7 @@ fn init_tracer_provider(otel_collector_url: &str) -> SdkTracerProvider {
     let sampler = Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(1.0)));
 
     let tracer_provider = SdkTracerProvider::builder()
-        .with_resource(get_span_resource())
+        .with_resource(resource)
         .with_batch_exporter(exporter)
         .with_sampler(sampler)
         .with_id_generator(RandomIdGenerator::default())
@@ -127,7 +128,7 @@ fn init_tracer_provider(otel_collector_url: &str) -> SdkTracerProvider {
     tracer_provider
 }
 
-fn init_logger_provider(otel_collector_url: &str) -> SdkLoggerProvider {
+fn init_logger_provider(otel_collector_url: &str, resource: Resource) -> SdkLoggerProvider {
     let exporter = opentelemetry_otlp::LogExporter::builder()
         .with_tonic()
         .with_endpoint(otel_collector_url)
@@ -135,7 +136,7 @@ fn init_logger_provider(otel_collector_url: &str) -> SdkLoggerProvider {
         .unwrap();
 
     SdkLoggerProvider::builder()
-        .with_resource(get_span_resource())
+        .with_resource(resource)
         .with_batch_exporter(exporter)
         .build()
 }
@@ -144,20 +145,40 @@ pub struct OtelGuard {
     logger_provider: SdkLoggerProvider,
     tracer_provider: SdkTracerProvider,
     meter_provider: SdkMeterProvider,
+    log_filter: LogFilterHandle,
+}
+
+impl OtelGuard {
+    pub fn log_filter(&self) -> LogFilterHandle {
+        self.log_filter.clone()
+    }
+}
+
+/// Swaps the global log filter of a running subscriber.
+#[derive(Clone)]
+pub struct LogFilterHandle(reload::Handle<EnvFilter, Registry>);
+
+impl LogFilterHandle {
+    pub fn reload(&self, log_filter: &str) -> Result<(), String> {
+        let filter = EnvFilter::try_new(log_filter).map_err(|e| e.to_string())?;
+        self.0.reload(filter).map_err(|e| e.to_string())
+    }
 }
 
 impl Drop for OtelGuard {
+    /// Flushes spans first, then metrics, then logs, so that anything reported while the
+    /// other providers shut down still reaches the collector.
     fn drop(&mut self) {
-        if let Err(err) = self.logger_provider.shutdown() {
-            eprintln!("Error shutting down logger provider: {}", err);
-        }
-        
         if let Err(err) = self.tracer_provider.shutdown() {
             eprintln!("Error shutting down tracer provider: {}", err);
         }
-        
+
         if let Err(err) = self.meter_provider.shutdown() {
             eprintln!("Error shutting down meter provider: {}", err);
         }
+
+        if let Err(err) = self.logger_provider.shutdown() {
+            eprintln!("Error shutting down logger provider: {}", err);
+        }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,235 @@
+use alloy_primitives::{keccak256, B256};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
+use tracing::{debug, info, warn};
+
+/// Identifies a proof by the guest image that produced it, its input and the kind of receipt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ProofCacheKey(B256);
+
+impl ProofCacheKey {
+    pub fn new(image_id: &[u8], input: &[u8], receipt_kind: &str) -> Self {
+        let mut preimage =
+            Vec::with_capacity(image_id.len() + receipt_kind.len() + input.len() + 2);
+        preimage.push(image_id.len() as u8);
+        preimage.extend_from_slice(image_id);
+        preimage.push(receipt_kind.len() as u8);
+        preimage.extend_from_slice(receipt_kind.as_bytes());
+        preimage.extend_from_slice(input);
+
+        Self(keccak256(preimage))
+    }
+
+    /// Files of the earlier formats, `.proof` with the seal and journal and `.receipt` with the
+    /// bare receipt, are ignored.
+    fn file_name(&self) -> String {
+        format!("{}.bin", hex::encode(self.0))
+    }
+}
+
+/// A bincode encoded proof, decoded by the prover that reuses it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedProof {
+    pub proof: Vec<u8>,
+}
+
+impl CachedProof {
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        (!bytes.is_empty()).then(|| Self {
+            proof: bytes.to_vec(),
+        })
+    }
+}
+
+/// Proofs already generated, kept in a least recently used memory cache of `capacity` entries
+/// and, when a directory is set, on disk where they survive restarts. Proofs are deterministic
+/// for a given key, so a hit can be published instead of proving again.
+pub struct ProofCache {
+    capacity: usize,
+    directory: Option<PathBuf>,
+    state: Mutex<CacheState>,
+}
+
+#[derive(Default)]
+struct CacheState {
+    entries: HashMap<ProofCacheKey, (CachedProof, u64)>,
+    clock: u64,
+}
+
+impl ProofCache {
+    pub fn new(capacity: usize, directory: Option<PathBuf>) -> Self {
+        Self {
+            capacity,
+            directory,
+            state: Mutex::new(CacheState::default()),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.capacity > 0 || self.directory.is_some()
+    }
+
+    /// Looks the proof up in memory, then on disk. Reads the disk, so call it off the runtime.
+    pub fn get(&self, key: &ProofCacheKey) -> Option<CachedProof> {
+        if !self.is_enabled() {
+            return None;
+        }
+
+        if let Some(proof) = self.state().touch(key) {
+            info!(
+                monotonic_counter.proof_cache_hits = 1_u64,
+                tier = "memory",
+                "Proof cache hit"
+            );
+            return Some(proof);
+        }
+
+        if let Some(proof) = self.read(key) {
+            info!(
+                monotonic_counter.proof_cache_hits = 1_u64,
+                tier = "disk",
+                "Proof cache hit"
+            );
+            self.remember(*key, proof.clone());
+            return Some(proof);
+        }
+
+        info!(
+            monotonic_counter.proof_cache_misses = 1_u64,
+            "Proof cache miss"
+        );
+        None
+    }
+
+    /// Stores a freshly generated proof. Writes the disk, so call it off the runtime.
+    pub fn insert(&self, key: ProofCacheKey, proof: CachedProof) {
+        if let Err(e) = self.write(&key, &proof) {
+            warn!("Failed to write proof to the cache directory: {}", e);
+        }
+        self.remember(key, proof);
+    }
+
+    fn remember(&self, key: ProofCacheKey, proof: CachedProof) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut state = self.state();
+        state.clock += 1;
+        let clock = state.clock;
+        state.entries.insert(key, (proof, clock));
+
+        if state.entries.len() > self.capacity {
+            if let Some(oldest) = state
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(key, _)| *key)
+            {
+                state.entries.remove(&oldest);
+                debug!("Evicted least recently used proof from the cache");
+            }
+        }
+    }
+
+    fn read(&self, key: &ProofCacheKey) -> Option<CachedProof> {
+        let path = self.directory.as_deref()?.join(key.file_name());
+        let bytes = match fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return None,
+            Err(e) => {
+                warn!(path = %path.display(), "Failed to read cached proof: {}", e);
+                return None;
+            }
+        };
+
+        let proof = CachedProof::from_bytes(&bytes);
+        if proof.is_none() {
+            warn!(path = %path.display(), "Ignoring empty cached proof");
+        }
+
+        proof
+    }
+
+    fn write(&self, key: &ProofCacheKey, proof: &CachedProof) -> io::Result<()> {
+        let Some(directory) = self.directory.as_deref() else {
+            return Ok(());
+        };
+
+        fs::create_dir_all(directory)?;
+        let path = directory.join(key.file_name());
+        let partial = path.with_extension("partial");
+        fs::write(&partial, &proof.proof)?;
+        fs::rename(&partial, &path)
+    }
+
+    fn state(&self) -> MutexGuard<'_, CacheState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl CacheState {
+    fn touch(&mut self, key: &ProofCacheKey) -> Option<CachedProof> {
+        self.clock += 1;
+        let clock = self.clock;
+        let (proof, used) = self.entries.get_mut(key)?;
+        *used = clock;
+
+        Some(proof.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(input: u8) -> ProofCacheKey {
+        ProofCacheKey::new(&[1; 32], &[input], "groth16")
+    }
+
+    fn proof(journal: u128) -> CachedProof {
+        CachedProof {
+            proof: vec![0xaa, journal as u8],
+        }
+    }
+
+    #[test]
+    fn test_key_depends_on_image_input_and_receipt_kind() {
+        let base = ProofCacheKey::new(&[1; 32], &[5], "groth16");
+
+        assert_eq!(base, ProofCacheKey::new(&[1; 32], &[5], "groth16"));
+        assert_ne!(base, ProofCacheKey::new(&[2; 32], &[5], "groth16"));
+        assert_ne!(base, ProofCacheKey::new(&[1; 32], &[6], "groth16"));
+        assert_ne!(base, ProofCacheKey::new(&[1; 32], &[5], "succinct"));
+    }
+
+    #[test]
+    fn test_least_recently_used_proof_is_evicted() {
+        let cache = ProofCache::new(2, None);
+        cache.insert(key(1), proof(1));
+        cache.insert(key(2), proof(2));
+
+        assert_eq!(cache.get(&key(1)), Some(proof(1)));
+        cache.insert(key(3), proof(3));
+
+        assert_eq!(cache.get(&key(1)), Some(proof(1)));
+        assert_eq!(cache.get(&key(2)), None);
+        assert_eq!(cache.get(&key(3)), Some(proof(3)));
+    }
+
+    #[test]
+    fn test_disk_cache_survives_a_new_instance() {
+        let directory = std::env::temp_dir().join(format!("fibchain-cache-{}", std::process::id()));
+        ProofCache::new(1, Some(directory.clone())).insert(key(1), proof(1));
+
+        let cache = ProofCache::new(0, Some(directory.clone()));
+        let hit = cache.get(&key(1));
+        fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(hit, Some(proof(1)));
+        assert_eq!(cache.get(&key(2)), None);
+    }
+}
@@ -0,0 +1,127 @@
+use crate::prelude::*;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tokio::sync::oneshot;
+use tracing::{error, Span};
+
+type Task = Box<dyn FnOnce() + Send>;
+
+/// A fixed set of dedicated threads for CPU bound proving, so a running proof never occupies a
+/// tokio worker and the HTTP layer stays responsive. Threads exit once the pool is dropped.
+pub struct ProverPool {
+    sender: Mutex<mpsc::Sender<Task>>,
+    threads: usize,
+}
+
+impl ProverPool {
+    pub fn new(threads: usize) -> Self {
+        let threads = threads.max(1);
+        let (sender, receiver) = mpsc::channel::<Task>();
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        for index in 0..threads {
+            let receiver = receiver.clone();
+            thread::Builder::new()
+                .name(format!("prover-{}", index))
+                .spawn(move || loop {
+                    let task = match receiver.lock().unwrap_or_else(|e| e.into_inner()).recv() {
+                        Ok(task) => task,
+                        Err(_) => break,
+                    };
+                    task();
+                })
+                .expect("failed to spawn prover thread");
+        }
+
+        Self {
+            sender: Mutex::new(sender),
+            threads,
+        }
+    }
+
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    /// Runs the task on the next free prover thread and waits for its result without blocking
+    /// the runtime. Dropping the returned future does not stop the task, it has to watch for
+    /// cancellation itself.
+    pub async fn run<T, F>(&self, task: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let (result_sender, result_receiver) = oneshot::channel();
+        let span = Span::current();
+        let task: Task = Box::new(move || {
+            let _entered = span.enter();
+            let result = panic::catch_unwind(AssertUnwindSafe(task));
+            if result.is_err() {
+                error!("Prover thread panicked while running a task");
+            }
+            let _ = result_sender.send(result);
+        });
+
+        self.sender
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .send(task)
+            .map_err(|_| FibchainError::ZkVM("the prover pool has shut down".to_string()))?;
+
+        match result_receiver.await {
+            Ok(Ok(result)) => Ok(result),
+            Ok(Err(_)) => Err(FibchainError::ZkVM(
+                "the prover thread panicked".to_string(),
+            )),
+            Err(_) => Err(FibchainError::ZkVM(
+                "the prover thread stopped before finishing".to_string(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_runs_tasks_on_prover_threads() {
+        let pool = ProverPool::new(2);
+
+        let name = pool
+            .run(|| thread::current().name().map(str::to_string))
+            .await
+            .unwrap();
+
+        assert_eq!(pool.threads(), 2);
+        assert!(name.unwrap().starts_with("prover-"));
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_blocking_task_does_not_stall_the_runtime() {
+        let pool = ProverPool::new(1);
+        let proving = pool.run(|| thread::sleep(Duration::from_millis(200)));
+        tokio::pin!(proving);
+
+        let responsive = tokio::select! {
+            _ = &mut proving => false,
+            _ = tokio::time::sleep(Duration::from_millis(10)) => true,
+        };
+
+        assert!(responsive);
+        proving.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_panicking_task_keeps_the_thread_alive() {
+        let pool = ProverPool::new(1);
+
+        let result = pool.run(|| -> u8 { panic!("boom") }).await;
+
+        assert!(matches!(result, Err(FibchainError::ZkVM(_))));
+        assert_eq!(pool.run(|| 5).await.unwrap(), 5);
+    }
+}
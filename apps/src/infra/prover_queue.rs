@@ -0,0 +1,251 @@
+use crate::domain::job::JobId;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tracing::info;
+
+/// Assumed proof duration until the first proof finishes, used to estimate `Retry-After`.
+const DEFAULT_PROOF_DURATION: Duration = Duration::from_secs(60);
+
+/// Bounds how many proofs run at once and how many jobs may wait for a free prover.
+#[derive(Clone)]
+pub struct ProverQueue {
+    inner: Arc<ProverQueueInner>,
+}
+
+struct ProverQueueInner {
+    workers: Arc<Semaphore>,
+    worker_count: usize,
+    max_queued: usize,
+    state: Mutex<QueueState>,
+}
+
+struct QueueState {
+    waiting: VecDeque<(JobId, Instant)>,
+    average_proof_duration: Option<Duration>,
+}
+
+/// A free prover, held for as long as the proof runs.
+pub struct ProverPermit {
+    inner: Arc<ProverQueueInner>,
+    waited: Duration,
+    started_at: Instant,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl ProverQueue {
+    pub fn new(workers: usize, max_queued: usize) -> Self {
+        Self {
+            inner: Arc::new(ProverQueueInner {
+                workers: Arc::new(Semaphore::new(workers)),
+                worker_count: workers,
+                max_queued,
+                state: Mutex::new(QueueState {
+                    waiting: VecDeque::new(),
+                    average_proof_duration: None,
+                }),
+            }),
+        }
+    }
+
+    /// Queues a new job, returning how long to wait before retrying when the queue is full.
+    pub fn try_enqueue(&self, job: JobId) -> Result<(), Duration> {
+        let mut state = self.inner.state();
+        if state.waiting.len() >= self.inner.max_queued {
+            info!(
+                monotonic_counter.prover_queue_rejections = 1_u64,
+                queue_length = state.waiting.len(),
+                "Rejected job because the prover queue is full"
+            );
+            return Err(self.inner.retry_after(&state));
+        }
+
+        self.inner.push(&mut state, job);
+        Ok(())
+    }
+
+    /// Waits until a prover is free. Jobs that were not queued yet, such as resumed ones, join
+    /// the back of the queue without being subject to its limit.
+    pub async fn wait_turn(&self, job: JobId) -> ProverPermit {
+        let enqueued_at = {
+            let mut state = self.inner.state();
+            match state.waiting.iter().find(|(id, _)| *id == job) {
+                Some((_, enqueued_at)) => *enqueued_at,
+                None => self.inner.push(&mut state, job),
+            }
+        };
+        let waiting = Waiting {
+            inner: &self.inner,
+            job,
+        };
+
+        let permit = self
+            .inner
+            .workers
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("the prover semaphore is never closed");
+        drop(waiting);
+
+        let waited = enqueued_at.elapsed();
+        info!(
+            histogram.prover_queue_wait_ms = waited.as_millis() as u64,
+            job_id = %job,
+            "Prover picked up job"
+        );
+
+        ProverPermit {
+            inner: self.inner.clone(),
+            waited,
+            started_at: Instant::now(),
+            _permit: permit,
+        }
+    }
+
+    /// Zero based position of a job in the queue, `None` when it is not waiting.
+    pub fn position(&self, job: JobId) -> Option<usize> {
+        self.inner
+            .state()
+            .waiting
+            .iter()
+            .position(|(id, _)| *id == job)
+    }
+
+    /// Number of jobs waiting for a prover.
+    pub fn waiting(&self) -> usize {
+        self.inner.state().waiting.len()
+    }
+
+    /// Takes a job out of the queue, for jobs that stop before reaching a prover.
+    pub fn remove(&self, job: JobId) {
+        self.inner.remove(job);
+    }
+}
+
+impl ProverQueueInner {
+    fn state(&self) -> MutexGuard<'_, QueueState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn push(&self, state: &mut QueueState, job: JobId) -> Instant {
+        let now = Instant::now();
+        state.waiting.push_back((job, now));
+        info!(
+            counter.prover_queue_length = 1_i64,
+            "Job queued for a prover"
+        );
+
+        now
+    }
+
+    fn remove(&self, job: JobId) {
+        let mut state = self.state();
+        if let Some(index) = state.waiting.iter().position(|(id, _)| *id == job) {
+            state.waiting.remove(index);
+            info!(
+                counter.prover_queue_length = -1_i64,
+                "Job left the prover queue"
+            );
+        }
+    }
+
+    /// Estimates when the job at the back of the queue would reach a prover.
+    fn retry_after(&self, state: &QueueState) -> Duration {
+        let proof_duration = state
+            .average_proof_duration
+            .unwrap_or(DEFAULT_PROOF_DURATION);
+        let rounds = state
+            .waiting
+            .len()
+            .div_ceil(self.worker_count.max(1))
+            .max(1);
+
+        (proof_duration * rounds as u32).max(Duration::from_secs(1))
+    }
+}
+
+impl ProverPermit {
+    /// How long the job waited in the queue for this prover.
+    pub fn waited(&self) -> Duration {
+        self.waited
+    }
+}
+
+impl Drop for ProverPermit {
+    fn drop(&mut self) {
+        let elapsed = self.started_at.elapsed();
+        let mut state = self.inner.state();
+
+        state.average_proof_duration = Some(match state.average_proof_duration {
+            Some(average) => (average * 4 + elapsed) / 5,
+            None => elapsed,
+        });
+    }
+}
+
+/// Takes the job out of the queue once it stops waiting, even when the wait is cancelled.
+struct Waiting<'a> {
+    inner: &'a ProverQueueInner,
+    job: JobId,
+}
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.inner.remove(self.job);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_full_queue_rejects_new_jobs() {
+        let queue = ProverQueue::new(1, 2);
+
+        assert!(queue.try_enqueue(JobId::random()).is_ok());
+        assert!(queue.try_enqueue(JobId::random()).is_ok());
+        let retry_after = queue.try_enqueue(JobId::random()).unwrap_err();
+
+        assert_eq!(queue.waiting(), 2);
+        assert_eq!(retry_after, DEFAULT_PROOF_DURATION * 2);
+    }
+
+    #[tokio::test]
+    async fn test_jobs_wait_for_a_free_prover_in_order() {
+        let queue = ProverQueue::new(1, 8);
+        let (first, second) = (JobId::random(), JobId::random());
+        queue.try_enqueue(first).unwrap();
+        queue.try_enqueue(second).unwrap();
+
+        let permit = queue.wait_turn(first).await;
+        assert_eq!(queue.position(first), None);
+        assert_eq!(queue.position(second), Some(0));
+
+        let waiting = tokio::spawn({
+            let queue = queue.clone();
+            async move { queue.wait_turn(second).await.waited() }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiting.is_finished());
+
+        drop(permit);
+        assert!(waiting.await.unwrap() >= Duration::from_millis(20));
+        assert_eq!(queue.waiting(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_wait_leaves_the_queue() {
+        let queue = ProverQueue::new(1, 8);
+        let _permit = queue.wait_turn(JobId::random()).await;
+        let job = JobId::random();
+        queue.try_enqueue(job).unwrap();
+
+        let wait = tokio::time::timeout(Duration::from_millis(10), queue.wait_turn(job)).await;
+
+        assert!(wait.is_err());
+        assert_eq!(queue.position(job), None);
+    }
+}
@@ -0,0 +1,302 @@
+use crate::domain::provider::ReceiptKind;
+use crate::infra::config::{ExecutionLimits, ProverBackend};
+use crate::infra::guest_registry::GuestRegistry;
+use crate::infra::prover_pool::ProverPool;
+use crate::infra::provider::fibonacci_risc_zero::prove;
+use crate::infra::redaction;
+use crate::prelude::FibchainError;
+use alloy_primitives::B256;
+use axum::body::Bytes;
+use axum::extract::{Path, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::Router;
+use risc0_zkvm::Receipt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, instrument, warn};
+
+/// Finished proofs nobody fetched are dropped after this long.
+const FINISHED_PROOF_TTL: Duration = Duration::from_secs(600);
+
+/// Bodies of the worker protocol are bincode encoded, like the proofs kept in the cache.
+const CONTENT_TYPE: &str = "application/octet-stream";
+
+/// Body of `POST /proofs`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProveRequest {
+    pub image_id: B256,
+    /// ABI encoded guest input.
+    pub input: Vec<u8>,
+    pub receipt_kind: ReceiptKind,
+    /// Limits of the host, applied to the guest execution.
+    pub limits: ExecutionLimits,
+}
+
+/// Accepted proof, polled with `GET /proofs/{id}`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ProveTicket {
+    pub id: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProveStatus {
+    Proving,
+    /// The receipt can be fetched from `GET /proofs/{id}/receipt`.
+    Proved,
+    /// Retryable failures, such as an unavailable Bonsai, may succeed on another attempt.
+    Failed {
+        message: String,
+        retryable: bool,
+    },
+    /// The guest ran past the session cycle limit of the request.
+    CycleLimitExceeded {
+        limit: u64,
+    },
+}
+
+impl ProveStatus {
+    fn failure(error: &FibchainError) -> Self {
+        match error {
+            FibchainError::CycleLimitExceeded { limit } => {
+                Self::CycleLimitExceeded { limit: *limit }
+            }
+            error => Self::Failed {
+                message: error.to_string(),
+                retryable: error.is_retryable(),
+            },
+        }
+    }
+}
+
+/// Body of `GET /proofs/{id}/receipt`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProvedReceipt {
+    pub receipt: Receipt,
+    pub cycles: u64,
+    /// Backend the worker proved with.
+    pub backend: ProverBackend,
+    pub zkvm_version: String,
+    pub duration_ms: u64,
+}
+
+/// Body of `GET /health`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkerHealth {
+    pub proving: usize,
+    pub capacity: usize,
+    pub backend: ProverBackend,
+    pub zkvm_version: String,
+}
+
+enum WorkerProofState {
+    Proving,
+    Proved(ProvedReceipt),
+    Failed(ProveStatus),
+}
+
+struct WorkerProof {
+    state: WorkerProofState,
+    cancellation: CancellationToken,
+    finished_at: Option<Instant>,
+}
+
+/// Runs only the prover, for a host using the `remote` backend: proofs are submitted, polled
+/// and fetched over HTTP. Proofs are kept in memory until they are deleted or expire.
+pub struct ProverWorker {
+    backend: ProverBackend,
+    guests: Arc<GuestRegistry>,
+    pool: ProverPool,
+    /// Proofs running or waiting for a prover thread before new ones are refused.
+    capacity: usize,
+    next_id: AtomicU64,
+    proofs: Mutex<HashMap<u64, WorkerProof>>,
+}
+
+impl ProverWorker {
+    pub fn new(
+        backend: ProverBackend,
+        guests: Arc<GuestRegistry>,
+        threads: usize,
+        capacity: usize,
+    ) -> Self {
+        Self {
+            backend,
+            guests,
+            pool: ProverPool::new(threads),
+            capacity: capacity.max(1),
+            next_id: AtomicU64::new(1),
+            proofs: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn routes(self: Arc<Self>) -> Router {
+        Router::new()
+            .route("/proofs", post(Self::submit))
+            .route("/proofs/{id}", get(Self::status).delete(Self::delete))
+            .route("/proofs/{id}/receipt", get(Self::receipt))
+            .route("/health", get(Self::health))
+            .with_state(self)
+    }
+
+    fn proofs(&self) -> std::sync::MutexGuard<'_, HashMap<u64, WorkerProof>> {
+        self.proofs.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn proving(proofs: &HashMap<u64, WorkerProof>) -> usize {
+        proofs
+            .values()
+            .filter(|proof| matches!(proof.state, WorkerProofState::Proving))
+            .count()
+    }
+
+    #[instrument(skip_all)]
+    async fn submit(State(worker): State<Arc<Self>>, body: Bytes) -> Response {
+        let request = match bincode::deserialize::<ProveRequest>(&body) {
+            Ok(request) => request,
+            Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+        };
+        let Some(guest) = worker.guests.by_image_id(request.image_id).cloned() else {
+            warn!(image_id = %request.image_id, "Refusing a proof of an unknown guest");
+            return (
+                StatusCode::NOT_FOUND,
+                format!("unknown guest image id {}", request.image_id),
+            )
+                .into_response();
+        };
+
+        let id = worker.next_id.fetch_add(1, Ordering::Relaxed);
+        let cancellation = CancellationToken::new();
+        {
+            let mut proofs = worker.proofs();
+            proofs.retain(|_, proof| match proof.finished_at {
+                Some(finished_at) => finished_at.elapsed() < FINISHED_PROOF_TTL,
+                None => true,
+            });
+            if Self::proving(&proofs) >= worker.capacity {
+                return (StatusCode::SERVICE_UNAVAILABLE, "the worker is at capacity")
+                    .into_response();
+            }
+            proofs.insert(
+                id,
+                WorkerProof {
+                    state: WorkerProofState::Proving,
+                    cancellation: cancellation.clone(),
+                    finished_at: None,
+                },
+            );
+        }
+
+        info!(
+            id = id,
+            input = redaction::payload(&request.input),
+            receipt_kind = request.receipt_kind.as_str(),
+            version = guest.version,
+            "Proof accepted"
+        );
+        let backend = worker.backend;
+        let started = Instant::now();
+        tokio::spawn({
+            let worker = worker.clone();
+            async move {
+                let proved = worker
+                    .pool
+                    .run(move || {
+                        prove(
+                            backend,
+                            request.receipt_kind,
+                            request.limits,
+                            &guest.elf,
+                            &request.input,
+                            &cancellation,
+                        )
+                    })
+                    .await
+                    .and_then(|proved| proved);
+
+                let state = match proved {
+                    Ok((receipt, cycles)) => {
+                        info!(id = id, cycles = cycles, "Proof generated");
+                        WorkerProofState::Proved(ProvedReceipt {
+                            receipt,
+                            cycles,
+                            backend,
+                            zkvm_version: risc0_zkvm::VERSION.to_string(),
+                            duration_ms: started.elapsed().as_millis() as u64,
+                        })
+                    }
+                    Err(e) => {
+                        warn!(id = id, "Proof generation failed: {}", e);
+                        WorkerProofState::Failed(ProveStatus::failure(&e))
+                    }
+                };
+                // A deleted proof was cancelled, its outcome is not kept.
+                if let Some(proof) = worker.proofs().get_mut(&id) {
+                    proof.state = state;
+                    proof.finished_at = Some(Instant::now());
+                }
+            }
+        });
+
+        encoded(StatusCode::ACCEPTED, &ProveTicket { id })
+    }
+
+    async fn status(State(worker): State<Arc<Self>>, Path(id): Path<u64>) -> Response {
+        let status = match worker.proofs().get(&id).map(|proof| &proof.state) {
+            None => return StatusCode::NOT_FOUND.into_response(),
+            Some(WorkerProofState::Proving) => ProveStatus::Proving,
+            Some(WorkerProofState::Proved(_)) => ProveStatus::Proved,
+            Some(WorkerProofState::Failed(status)) => status.clone(),
+        };
+
+        encoded(StatusCode::OK, &status)
+    }
+
+    /// The receipt stays available until the proof is deleted or expires, so a failed download
+    /// can be tried again.
+    async fn receipt(State(worker): State<Arc<Self>>, Path(id): Path<u64>) -> Response {
+        match worker.proofs().get(&id).map(|proof| &proof.state) {
+            None => StatusCode::NOT_FOUND.into_response(),
+            Some(WorkerProofState::Proved(receipt)) => encoded(StatusCode::OK, receipt),
+            Some(_) => (StatusCode::CONFLICT, "the proof has no receipt").into_response(),
+        }
+    }
+
+    /// Forgets the proof, stopping it at the next segment when it is still running.
+    #[instrument(skip(worker))]
+    async fn delete(State(worker): State<Arc<Self>>, Path(id): Path<u64>) -> StatusCode {
+        match worker.proofs().remove(&id) {
+            Some(proof) => {
+                if matches!(proof.state, WorkerProofState::Proving) {
+                    info!(id = id, "Proof cancelled");
+                    proof.cancellation.cancel();
+                }
+                StatusCode::NO_CONTENT
+            }
+            None => StatusCode::NOT_FOUND,
+        }
+    }
+
+    async fn health(State(worker): State<Arc<Self>>) -> Response {
+        let health = WorkerHealth {
+            proving: Self::proving(&worker.proofs()),
+            capacity: worker.capacity,
+            backend: worker.backend,
+            zkvm_version: risc0_zkvm::VERSION.to_string(),
+        };
+
+        encoded(StatusCode::OK, &health)
+    }
+}
+
+fn encoded(status: StatusCode, body: &impl Serialize) -> Response {
+    match bincode::serialize(body) {
+        Ok(body) => (status, [(header::CONTENT_TYPE, CONTENT_TYPE)], body).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
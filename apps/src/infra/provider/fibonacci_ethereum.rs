@@ -1,106 +1,347 @@
-use crate::domain::provider::IFibonacciEthereumProvider;
+use crate::domain::provider::{
+    FibonacciProof, IFibonacciEthereumProvider, SentTransaction, SignedTransaction,
+    TransactionConfirmation,
+};
+use crate::infra::config::Config;
+use crate::infra::redaction;
+use crate::infra::shutdown::Shutdown;
 use crate::prelude::{FibchainError, IFibonacci};
-use alloy::network::EthereumWallet;
-use alloy::providers::ProviderBuilder;
-use alloy_primitives::{Address, TxHash};
-use alloy_sol_types::SolValue;
-use std::time::Duration;
-use tracing::{error, info, instrument};
+use alloy::consensus::Transaction;
+use alloy::eips::eip2718::Encodable2718;
+use alloy::network::{Ethereum, EthereumWallet, NetworkWallet, TransactionBuilder};
+use alloy::providers::{PendingTransactionBuilder, Provider, ProviderBuilder};
+use alloy::rpc::types::TransactionRequest;
+use alloy_primitives::{Address, TxHash, B256, U256};
+use alloy_sol_types::SolCall;
+use tokio::sync::watch;
+use tracing::{error, info, instrument, warn};
 
-static BLOCKCHAIN_TX_CONFIRMATIONS: u8 = 10;
-static BLOCKCHAIN_TX_TIMEOUT_SECS: u16 = 60;
+/// Length of a Groth16 seal as encoded for the verifier router: a 4 bytes selector followed by
+/// the 8 words of the proof.
+const GROTH16_SEAL_LEN: usize = 4 + 8 * 32;
+
+/// Gas of `increaseCounter` besides its calldata: the base transaction cost, the Groth16 pairing
+/// check behind the verifier router and the counter update. It does not depend on the input.
+const INCREASE_COUNTER_EXECUTION_GAS: u64 = 21_000 + 250_000;
+
+/// Calldata costs 4 gas per zero byte and 16 per other byte.
+fn calldata_gas(calldata: &[u8]) -> u64 {
+    calldata
+        .iter()
+        .map(|byte| if *byte == 0 { 4 } else { 16 })
+        .sum()
+}
 
 #[derive(Clone)]
 pub struct FibonacciEthereumProvider {
     wallet: EthereumWallet,
-    contract: Address,
     rpc_url: url::Url,
+    config: watch::Receiver<Config>,
+    shutdown: Shutdown,
 }
 
 impl FibonacciEthereumProvider {
-    pub fn new(wallet: EthereumWallet, contract: Address, rpc_url: url::Url) -> Self {
+    /// Confirmations and timeouts are read from `config` on every transaction, so they follow
+    /// configuration reloads. Proofs are sent to the contract accepting their guest version.
+    pub fn new(
+        wallet: EthereumWallet,
+        rpc_url: url::Url,
+        config: watch::Receiver<Config>,
+        shutdown: Shutdown,
+    ) -> Self {
         Self {
             wallet,
-            contract,
             rpc_url,
+            config,
+            shutdown,
         }
     }
 }
 
 #[async_trait::async_trait]
 impl IFibonacciEthereumProvider for FibonacciEthereumProvider {
-    #[instrument(skip(self, seal))]
-    async fn increase_counter(
+    #[instrument(skip(self))]
+    async fn image_id(&self, contract: Address) -> crate::prelude::Result<B256> {
+        let provider = ProviderBuilder::new().on_http(self.rpc_url.clone());
+        let contract = IFibonacci::new(contract, provider);
+
+        contract
+            .imageId()
+            .call()
+            .await
+            .map(|image_id| image_id._0)
+            .map_err(|e| {
+                warn!(
+                    contract = hex::encode(&contract.address().0),
+                    "Failed to read the image id of the contract: {}", e
+                );
+
+                FibchainError::Alloy(e)
+            })
+    }
+
+    #[instrument(skip(self, proof))]
+    async fn estimate_increase_counter_gas(
         &self,
-        fibonacci_number: u128,
-        seal: Vec<u8>,
-    ) -> crate::prelude::Result<TxHash> {
-        info!("Sending cryptographic proof to the contract");
-        let fill_provider = ProviderBuilder::new()
-            .with_recommended_fillers()
-            .wallet(self.wallet.clone())
-            .on_http(self.rpc_url.clone());
-        let contract = IFibonacci::new(self.contract.clone(), fill_provider);
-        let call_builder = contract.increaseCounter(fibonacci_number, seal.clone().into());
-        let pending_transaction = call_builder
-            .send()
+        contract: Address,
+        proof: &FibonacciProof,
+    ) -> crate::prelude::Result<u64> {
+        let seal = proof.on_chain_seal()?;
+        let provider = ProviderBuilder::new().on_http(self.rpc_url.clone());
+        let contract = IFibonacci::new(contract, provider);
+
+        contract
+            .increaseCounter(proof.journal, seal.to_vec().into())
+            .from(NetworkWallet::<Ethereum>::default_signer_address(
+                &self.wallet,
+            ))
+            .estimate_gas()
             .await
             .map_err(|e| {
-                error!(
+                warn!(
                     contract = hex::encode(&contract.address().0),
-                    seal = hex::encode(&seal.abi_encode()),
-                    journal = hex::encode(&fibonacci_number.to_ne_bytes()),
-                    "Failed to send the proof to the contract: {}",
+                    seal = redaction::payload(seal),
+                    "Failed to estimate the gas of the proof submission: {}",
                     e
                 );
 
                 FibchainError::Alloy(e)
-            })?
-            .with_timeout(Some(Duration::from_secs(BLOCKCHAIN_TX_TIMEOUT_SECS as u64)))
-            .with_required_confirmations(BLOCKCHAIN_TX_CONFIRMATIONS as u64);
+            })
+    }
 
-        let transaction_hash = pending_transaction.tx_hash().clone();
+    /// Seal bytes are counted as non-zero, so the approximation leans high.
+    fn approximate_increase_counter_gas(&self, journal: u128) -> u64 {
+        let calldata = IFibonacci::increaseCounterCall {
+            fibonacciNum: journal,
+            seal: vec![0xff; GROTH16_SEAL_LEN].into(),
+        }
+        .abi_encode();
 
-        info!(
-            contract = hex::encode(&contract.address().0),
-            timeout = BLOCKCHAIN_TX_TIMEOUT_SECS as u64,
-            confirmations = BLOCKCHAIN_TX_CONFIRMATIONS as u64,
-            transaction_hash = hex::encode(&pending_transaction.tx_hash().0),
-            "Sent! Waiting for confirmation..."
-        );
-        let transaction = pending_transaction.get_receipt().await.map_err(|e| {
+        INCREASE_COUNTER_EXECUTION_GAS + calldata_gas(&calldata)
+    }
+
+    #[instrument(skip(self, proof))]
+    async fn sign_increase_counter(
+        &self,
+        contract: Address,
+        proof: &FibonacciProof,
+    ) -> crate::prelude::Result<SignedTransaction> {
+        let seal = proof.on_chain_seal()?;
+        let fill_provider = ProviderBuilder::new()
+            .with_recommended_fillers()
+            .wallet(self.wallet.clone())
+            .on_http(self.rpc_url.clone());
+        // Picked here rather than by the filler so it can be reported along with the hash.
+        let sender = NetworkWallet::<Ethereum>::default_signer_address(&self.wallet);
+        let nonce = fill_provider
+            .get_transaction_count(sender)
+            .pending()
+            .await
+            .map_err(|e| {
+                error!("Failed to get the wallet nonce: {}", e);
+
+                FibchainError::Alloy(alloy::contract::Error::TransportError(e))
+            })?;
+        let contract = IFibonacci::new(contract, fill_provider.clone());
+        let request = contract
+            .increaseCounter(proof.journal, seal.to_vec().into())
+            .into_transaction_request()
+            .with_nonce(nonce);
+        let filled = fill_provider.fill(request).await.map_err(|e| {
             error!(
                 contract = hex::encode(&contract.address().0),
-                transaction_hash = hex::encode(transaction_hash.0),
-                "Error while waiting for confirmations: {}",
+                seal = redaction::payload(seal),
+                journal = redaction::payload(&proof.journal_bytes),
+                "Failed to sign the proof submission: {}",
                 e
             );
-            FibchainError::AlloyPendingTransaction(e)
+
+            FibchainError::Alloy(alloy::contract::Error::TransportError(e))
         })?;
+        let Some(envelope) = filled.as_envelope() else {
+            return Err(FibchainError::Generic(color_eyre::eyre::eyre!(
+                "the wallet did not sign the proof submission"
+            )));
+        };
 
+        let transaction_hash = *envelope.tx_hash();
         info!(
             contract = hex::encode(&contract.address().0),
+            transaction_hash = hex::encode(&transaction_hash.0),
+            nonce = nonce,
+            "Signed the proof submission"
+        );
+        Ok(SignedTransaction {
+            transaction_hash,
+            nonce,
+            raw: envelope.encoded_2718(),
+        })
+    }
+
+    #[instrument(skip(self, transaction), fields(nonce = transaction.nonce))]
+    async fn broadcast(
+        &self,
+        transaction: &SignedTransaction,
+    ) -> crate::prelude::Result<SentTransaction> {
+        info!("Sending cryptographic proof to the contract");
+        let provider = ProviderBuilder::new().on_http(self.rpc_url.clone());
+        if let Err(e) = provider.send_raw_transaction(&transaction.raw).await {
+            // An earlier attempt may have reached the node even though its answer was lost.
+            let known = provider
+                .get_transaction_by_hash(transaction.transaction_hash)
+                .await
+                .is_ok_and(|known| known.is_some());
+            if !known {
+                error!("Failed to send the proof to the contract: {}", e);
+
+                return Err(FibchainError::Alloy(
+                    alloy::contract::Error::TransportError(e),
+                ));
+            }
+            info!("The node already has the transaction");
+        }
+
+        self.shutdown.transaction_sent(transaction.transaction_hash);
+
+        info!(
             transaction_hash = hex::encode(&transaction.transaction_hash.0),
+            "Sent!"
+        );
+        Ok(SentTransaction {
+            transaction_hash: transaction.transaction_hash,
+            nonce: transaction.nonce,
+        })
+    }
+
+    #[instrument(skip(self), fields(transaction_hash = hex::encode(transaction_hash.0)))]
+    async fn wait_for_confirmation(
+        &self,
+        transaction_hash: TxHash,
+    ) -> crate::prelude::Result<TransactionConfirmation> {
+        let (confirmations, timeout) = {
+            let config = self.config.borrow();
+            (config.chain.confirmations, config.chain.tx_timeout())
+        };
+        self.shutdown.transaction_sent(transaction_hash);
+
+        info!(
+            timeout = timeout.as_secs(),
+            confirmations = confirmations,
+            "Waiting for confirmation..."
+        );
+        let provider = ProviderBuilder::new().on_http(self.rpc_url.clone());
+        let transaction = PendingTransactionBuilder::new(provider.root().clone(), transaction_hash)
+            .with_timeout(Some(timeout))
+            .with_required_confirmations(confirmations)
+            .get_receipt()
+            .await;
+        self.shutdown.transaction_settled(&transaction_hash);
+
+        let transaction = transaction.map_err(|e| {
+            error!("Error while waiting for confirmations: {}", e);
+            FibchainError::AlloyPendingTransaction(e)
+        })?;
+
+        info!(
             block_hash = hex::encode(&transaction.block_hash.unwrap_or_default().0),
             "Transaction Confirmed. Success!"
         );
-        Ok(transaction.transaction_hash)
+        Ok(TransactionConfirmation {
+            transaction_hash: transaction.transaction_hash,
+            block_number: transaction.block_number,
+            gas_used: transaction.gas_used,
+        })
+    }
+
+    #[instrument(skip(self))]
+    async fn is_available(&self) -> bool {
+        let provider = ProviderBuilder::new().on_http(self.rpc_url.clone());
+        match provider.get_block_number().await {
+            Ok(_) => true,
+            Err(e) => {
+                warn!("The chain is still unavailable: {}", e);
+                false
+            }
+        }
+    }
+
+    #[instrument(skip(self), fields(transaction_hash = hex::encode(transaction_hash.0)))]
+    async fn cancel_transaction(
+        &self,
+        transaction_hash: TxHash,
+    ) -> crate::prelude::Result<Option<TxHash>> {
+        if !self.config.borrow().chain.cancel_with_replacement {
+            return Ok(None);
+        }
+
+        let provider = ProviderBuilder::new()
+            .with_recommended_fillers()
+            .wallet(self.wallet.clone())
+            .on_http(self.rpc_url.clone());
+        let transaction = provider
+            .get_transaction_by_hash(transaction_hash)
+            .await
+            .map_err(|e| FibchainError::Alloy(alloy::contract::Error::TransportError(e)))?;
+        let Some(transaction) =
+            transaction.filter(|transaction| transaction.block_number.is_none())
+        else {
+            info!("The transaction is already mined or unknown, it cannot be replaced");
+            return Ok(None);
+        };
+
+        // Nodes only accept a replacement paying at least 10% more than the pending transaction.
+        let bump = |fee: u128| fee + fee / 10 + 1;
+        let replacement = TransactionRequest::default()
+            .with_from(transaction.from)
+            .with_to(transaction.from)
+            .with_value(U256::ZERO)
+            .with_nonce(transaction.nonce())
+            .with_gas_limit(21_000);
+        let replacement = match transaction.max_priority_fee_per_gas() {
+            Some(priority_fee) => replacement
+                .with_max_fee_per_gas(bump(transaction.max_fee_per_gas()))
+                .with_max_priority_fee_per_gas(bump(priority_fee)),
+            None => replacement.with_gas_price(bump(transaction.max_fee_per_gas())),
+        };
+
+        let pending_transaction = provider.send_transaction(replacement).await.map_err(|e| {
+            error!(
+                nonce = transaction.nonce(),
+                "Failed to send the replacement transaction: {}", e
+            );
+
+            FibchainError::Alloy(alloy::contract::Error::TransportError(e))
+        })?;
+
+        let replacement_hash = *pending_transaction.tx_hash();
+        info!(
+            nonce = transaction.nonce(),
+            replacement_hash = hex::encode(replacement_hash.0),
+            "Sent a replacement transaction to cancel the job"
+        );
+        Ok(Some(replacement_hash))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::domain::provider::{IFibonacciEthereumProvider, IFibonacciRiscZeroProvider};
+    use crate::domain::provider::{
+        IFibonacciEthereumProvider, IFibonacciRiscZeroProvider, ProofOptions, ReceiptKind,
+    };
+    use crate::infra::config::ProverConfig;
+    use crate::infra::guest_registry::GuestRegistry;
+    use crate::infra::provider::fibonacci_risc_zero::FibonacciRiscZeroProvider;
     use alloy::network::EthereumWallet;
     use alloy::signers::local::PrivateKeySigner;
     use alloy_primitives::Address;
     use std::str::FromStr;
+    use std::sync::Arc;
+    use tokio_util::sync::CancellationToken;
     use url::Url;
-    use crate::infra::provider::fibonacci_risc_zero::FibonacciRiscZeroProvider;
 
     #[tokio::test]
-    async fn test_increase_counter_success() {
+    async fn test_proof_submission_success() {
         let wallet = EthereumWallet::new(
             PrivateKeySigner::from_str(
                 "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
@@ -110,21 +351,47 @@ mod tests {
         let contract = Address::from_str("0xe7f1725E7734CE288F8367e1Bb143E90bb3F0512").unwrap();
         let rpc_url = Url::parse("http://localhost:8545").unwrap();
 
-        let risc_zero_provider = FibonacciRiscZeroProvider::new();
-        let provider = FibonacciEthereumProvider::new(wallet, contract, rpc_url);
+        let risc_zero_provider = FibonacciRiscZeroProvider::new(
+            ProverConfig::default(),
+            Arc::new(GuestRegistry::embedded()),
+        );
+        let provider = FibonacciEthereumProvider::new(
+            wallet,
+            rpc_url,
+            watch::channel(Config::default()).1,
+            Shutdown::new(),
+        );
 
         // Generate a proof
-        let (seal, num) = risc_zero_provider.generate_proof(5).await.unwrap();
-        
-        // Call increase_counter
-        let result = provider.increase_counter(num, seal).await;
+        let proof = risc_zero_provider
+            .generate_proof(5, ProofOptions::default(), CancellationToken::new())
+            .await
+            .unwrap();
+
+        // Sign and send the proof
+        let signed = provider
+            .sign_increase_counter(contract, &proof)
+            .await
+            .unwrap();
+        let result = provider.broadcast(&signed).await;
 
         // Assert that result is Ok
         assert!(result.is_ok(), "Expected Ok result, got: {:?}", result);
+
+        // Wait for the sent transaction
+        let transaction_hash = result.unwrap().transaction_hash;
+        let confirmation = provider.wait_for_confirmation(transaction_hash).await;
+
+        assert!(
+            confirmation.is_ok(),
+            "Expected Ok result, got: {:?}",
+            confirmation
+        );
+        assert_eq!(confirmation.unwrap().transaction_hash, transaction_hash);
     }
 
     #[tokio::test]
-    async fn test_increase_counter_error_handling() {
+    async fn test_proof_submission_error_handling() {
         let wallet = EthereumWallet::new(
             PrivateKeySigner::from_str(
                 "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
@@ -134,10 +401,19 @@ mod tests {
         let contract = Address::from_str("0xe7f1725E7734CE288F8367e1Bb143E90bb3F0512").unwrap();
         let rpc_url = Url::parse("http://localhost:8545").unwrap();
 
-        let provider = FibonacciEthereumProvider::new(wallet, contract, rpc_url);
+        let provider = FibonacciEthereumProvider::new(
+            wallet,
+            rpc_url,
+            watch::channel(Config::default()).1,
+            Shutdown::new(),
+        );
 
         // Simulate an invalid seal or connection issue
-        let result = provider.increase_counter(21, vec![]).await;
+        let proof = FibonacciProof {
+            seal: Some(vec![]),
+            ..FibonacciProof::fake(8, ReceiptKind::Groth16, 0)
+        };
+        let result = provider.sign_increase_counter(contract, &proof).await;
 
         // Assert that result is Err
         assert!(result.is_err(), "Expected Err result, got: {:?}", result);
@@ -0,0 +1,649 @@
+use crate::domain::job::now_millis;
+use crate::domain::provider::{
+    ExecutionReport, FibonacciProof, IFibonacciRiscZeroProvider, ProofOptions, ProverMetadata,
+    ReceiptKind,
+};
+use crate::infra::config::{ProverBackend, ProverConfig};
+use crate::infra::guest_registry::{GuestProgram, GuestRegistry};
+use crate::infra::proof_cache::{ProofCache, ProofCacheKey};
+use crate::infra::prover_worker::{ProveRequest, ProveStatus, ProveTicket, ProvedReceipt};
+use crate::infra::provider::fibonacci_risc_zero::{
+    cache_kind, cache_proof, cached, check_iterations, execute, fibonacci_proof, shared_error,
+    timed_out, SharedProof,
+};
+use crate::infra::redaction;
+use crate::infra::single_flight::SingleFlight;
+use crate::prelude::FibchainError;
+use alloy::transports::http::reqwest::{self, StatusCode};
+use alloy_primitives::B256;
+use alloy_sol_types::SolValue;
+use serde::de::DeserializeOwned;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, instrument, warn};
+use url::Url;
+
+/// Time a worker gets to answer a single request, proofs themselves are polled.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+struct RemoteWorker {
+    url: Url,
+    healthy: AtomicBool,
+    /// Proofs this host is waiting for on the worker.
+    in_flight: AtomicUsize,
+}
+
+impl RemoteWorker {
+    fn endpoint(&self, path: &str) -> String {
+        format!("{}/{}", self.url.as_str().trim_end_matches('/'), path)
+    }
+
+    fn set_healthy(&self, healthy: bool) {
+        if self.healthy.swap(healthy, Ordering::Relaxed) != healthy {
+            if healthy {
+                info!(worker = %self.url, "Prover worker is back");
+            } else {
+                warn!(worker = %self.url, "Prover worker is unavailable");
+            }
+        }
+    }
+}
+
+/// Counts a proof against its worker while it runs there.
+struct InFlight<'a>(&'a AtomicUsize);
+
+impl<'a> InFlight<'a> {
+    fn new(in_flight: &'a AtomicUsize) -> Self {
+        in_flight.fetch_add(1, Ordering::Relaxed);
+        Self(in_flight)
+    }
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Why a worker did not take a proof.
+enum Refusal {
+    Unreachable(String),
+    AtCapacity,
+    UnknownGuest,
+}
+
+struct WorkerPool {
+    client: reqwest::Client,
+    workers: Vec<RemoteWorker>,
+    poll_interval: Duration,
+}
+
+impl WorkerPool {
+    /// Healthy workers first, least loaded by this host first, then the unhealthy ones in case
+    /// they came back since the last health check.
+    fn candidates(&self) -> Vec<&RemoteWorker> {
+        let mut candidates: Vec<&RemoteWorker> = self.workers.iter().collect();
+        candidates.sort_by_key(|worker| {
+            (
+                !worker.healthy.load(Ordering::Relaxed),
+                worker.in_flight.load(Ordering::Relaxed),
+            )
+        });
+        candidates
+    }
+
+    async fn prove(
+        &self,
+        request: &ProveRequest,
+        cancellation: &CancellationToken,
+    ) -> crate::prelude::Result<(ProvedReceipt, Url)> {
+        let body = bincode::serialize(request).map_err(|e| FibchainError::ZkVM(e.to_string()))?;
+        let mut unknown_guest = false;
+        let mut refusals = Vec::new();
+
+        for worker in self.candidates() {
+            if cancellation.is_cancelled() {
+                return Err(FibchainError::Cancelled);
+            }
+
+            match self.submit(worker, body.clone()).await {
+                Ok(ticket) => {
+                    let _in_flight = InFlight::new(&worker.in_flight);
+                    return self
+                        .wait(worker, ticket, cancellation)
+                        .await
+                        .map(|receipt| (receipt, worker.url.clone()));
+                }
+                Err(Refusal::Unreachable(e)) => {
+                    worker.set_healthy(false);
+                    refusals.push(format!("{}: {}", worker.url, e));
+                }
+                Err(Refusal::AtCapacity) => refusals.push(format!("{}: at capacity", worker.url)),
+                Err(Refusal::UnknownGuest) => {
+                    unknown_guest = true;
+                    refusals.push(format!("{}: unknown guest", worker.url));
+                }
+            }
+        }
+
+        let message = format!("no prover worker took the proof ({})", refusals.join(", "));
+        if unknown_guest && refusals.len() == self.workers.len() {
+            // Every worker answered, none of them runs this guest version.
+            Err(FibchainError::ZkVM(message))
+        } else {
+            Err(FibchainError::RemoteProver(message))
+        }
+    }
+
+    async fn submit(
+        &self,
+        worker: &RemoteWorker,
+        body: Vec<u8>,
+    ) -> std::result::Result<ProveTicket, Refusal> {
+        let response = self
+            .client
+            .post(worker.endpoint("proofs"))
+            .timeout(REQUEST_TIMEOUT)
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| Refusal::Unreachable(e.to_string()))?;
+
+        match response.status() {
+            StatusCode::ACCEPTED => decode(response)
+                .await
+                .map_err(|e| Refusal::Unreachable(e.to_string())),
+            StatusCode::SERVICE_UNAVAILABLE => Err(Refusal::AtCapacity),
+            StatusCode::NOT_FOUND => Err(Refusal::UnknownGuest),
+            status => Err(Refusal::Unreachable(format!(
+                "unexpected status {}",
+                status
+            ))),
+        }
+    }
+
+    /// Polls the proof until it is done, deleting it from the worker once it is no longer
+    /// needed so a cancelled proof also stops there.
+    async fn wait(
+        &self,
+        worker: &RemoteWorker,
+        ticket: ProveTicket,
+        cancellation: &CancellationToken,
+    ) -> crate::prelude::Result<ProvedReceipt> {
+        let proof = worker.endpoint(&format!("proofs/{}", ticket.id));
+        let outcome = loop {
+            tokio::select! {
+                _ = tokio::time::sleep(self.poll_interval) => {}
+                _ = cancellation.cancelled() => {
+                    warn!(worker = %worker.url, id = ticket.id, "Proof cancelled on the worker");
+                    break Err(FibchainError::Cancelled);
+                }
+            }
+
+            match self.get::<ProveStatus>(&proof).await {
+                Ok(ProveStatus::Proving) => continue,
+                Ok(ProveStatus::Proved) => {
+                    break self
+                        .get::<ProvedReceipt>(&format!("{}/receipt", proof))
+                        .await
+                }
+                Ok(ProveStatus::Failed { message, retryable }) if retryable => {
+                    break Err(FibchainError::RemoteProver(message))
+                }
+                Ok(ProveStatus::Failed { message, .. }) => break Err(FibchainError::ZkVM(message)),
+                Ok(ProveStatus::CycleLimitExceeded { limit }) => {
+                    break Err(FibchainError::CycleLimitExceeded { limit })
+                }
+                Err(e) => {
+                    worker.set_healthy(false);
+                    break Err(e);
+                }
+            }
+        };
+
+        if let Err(e) = self
+            .client
+            .delete(&proof)
+            .timeout(REQUEST_TIMEOUT)
+            .send()
+            .await
+        {
+            warn!(worker = %worker.url, id = ticket.id, "Failed to delete the proof: {}", e);
+        }
+
+        outcome
+    }
+
+    async fn get<T: DeserializeOwned>(&self, url: &str) -> crate::prelude::Result<T> {
+        let response = self
+            .client
+            .get(url)
+            .timeout(REQUEST_TIMEOUT)
+            .send()
+            .await
+            .map_err(|e| FibchainError::RemoteProver(e.to_string()))?;
+
+        if !response.status().is_success() {
+            return Err(FibchainError::RemoteProver(format!(
+                "{} answered with status {}",
+                url,
+                response.status()
+            )));
+        }
+
+        decode(response).await
+    }
+
+    async fn check_health(&self) {
+        for worker in &self.workers {
+            let healthy = self
+                .client
+                .get(worker.endpoint("health"))
+                .timeout(REQUEST_TIMEOUT)
+                .send()
+                .await
+                .is_ok_and(|response| response.status().is_success());
+            worker.set_healthy(healthy);
+        }
+    }
+}
+
+async fn decode<T: DeserializeOwned>(response: reqwest::Response) -> crate::prelude::Result<T> {
+    let body = response
+        .bytes()
+        .await
+        .map_err(|e| FibchainError::RemoteProver(e.to_string()))?;
+
+    bincode::deserialize(&body).map_err(|e| FibchainError::RemoteProver(e.to_string()))
+}
+
+/// Checks the workers until the provider is dropped.
+async fn check_health(workers: Weak<WorkerPool>, interval: Duration) {
+    let mut ticks = tokio::time::interval(interval);
+    loop {
+        ticks.tick().await;
+        let Some(workers) = workers.upgrade() else {
+            return;
+        };
+        workers.check_health().await;
+    }
+}
+
+/// Dispatches proofs to a pool of `prover-worker` processes, balanced by the proofs this host
+/// has running on each of them. Unreachable workers are skipped until a health check sees them
+/// back. Proofs are cached and coalesced like with the local prover, guests are only executed
+/// locally for dry runs.
+pub struct FibonacciRemoteProvider {
+    config: ProverConfig,
+    guests: Arc<GuestRegistry>,
+    workers: Arc<WorkerPool>,
+    cache: Arc<ProofCache>,
+    in_flight: SingleFlight<ProofCacheKey, SharedProof>,
+}
+
+impl FibonacciRemoteProvider {
+    /// Starts checking the health of the workers, it has to be called within a tokio runtime.
+    pub fn new(config: ProverConfig, guests: Arc<GuestRegistry>) -> Self {
+        let workers = Arc::new(WorkerPool {
+            client: reqwest::Client::new(),
+            workers: config
+                .remote_workers
+                .iter()
+                .map(|url| RemoteWorker {
+                    url: url.clone(),
+                    healthy: AtomicBool::new(true),
+                    in_flight: AtomicUsize::new(0),
+                })
+                .collect(),
+            poll_interval: config.remote_poll_interval(),
+        });
+        info!(
+            backend = config.backend.as_str(),
+            workers = workers.workers.len(),
+            "Prover backend selected"
+        );
+        tokio::spawn(check_health(
+            Arc::downgrade(&workers),
+            config.remote_health_interval(),
+        ));
+        let cache = Arc::new(ProofCache::new(
+            config.cache_capacity,
+            config.cache_dir.clone(),
+        ));
+
+        Self {
+            config,
+            guests,
+            workers,
+            cache,
+            in_flight: SingleFlight::new(),
+        }
+    }
+}
+
+/// Proves the input on a worker and caches the proof under the backend the worker used. A proof
+/// running past the proving timeout is deleted from the worker.
+async fn prove_and_cache(
+    workers: Arc<WorkerPool>,
+    cache: Arc<ProofCache>,
+    config: ProverConfig,
+    guest: GuestProgram,
+    receipt_kind: ReceiptKind,
+    iterations: u16,
+    cancellation: CancellationToken,
+) -> crate::prelude::Result<FibonacciProof> {
+    let request = ProveRequest {
+        image_id: guest.image_id,
+        input: iterations.abi_encode(),
+        receipt_kind,
+        limits: config.execution_limits(),
+    };
+    info!(
+        input = redaction::payload(&request.input),
+        receipt_kind = receipt_kind.as_str(),
+        version = guest.version,
+        "Proof generation started"
+    );
+    let proving_cancellation = cancellation.child_token();
+    let proving = workers.prove(&request, &proving_cancellation);
+    tokio::pin!(proving);
+    let (proved, worker) = tokio::select! {
+        proved = &mut proving => proved?,
+        _ = timed_out(config.proving_timeout()) => {
+            proving_cancellation.cancel();
+            // Waits for the proof to be deleted, so the worker stops proving it.
+            let _ = proving.await;
+            warn!(
+                timeout_secs = config.proving_timeout_secs,
+                "Proof generation timed out on the worker"
+            );
+            return Err(FibchainError::ProvingTimeout {
+                timeout: Duration::from_secs(config.proving_timeout_secs),
+            });
+        }
+    };
+
+    info!(
+        input = redaction::payload(&request.input),
+        worker = %worker,
+        backend = proved.backend.as_str(),
+        "Proof generated remotely. Extracting seal and journal..."
+    );
+    let prover = ProverMetadata {
+        backend: proved.backend.as_str().to_string(),
+        zkvm_version: proved.zkvm_version,
+        generated_at: now_millis(),
+        duration_ms: proved.duration_ms,
+    };
+    let proof = fibonacci_proof(
+        iterations,
+        guest.image_id,
+        receipt_kind,
+        proved.receipt,
+        proved.cycles,
+        prover,
+    )?;
+
+    let key = ProofCacheKey::new(
+        guest.image_id.as_slice(),
+        &request.input,
+        &cache_kind(proved.backend, receipt_kind),
+    );
+    cache_proof(cache, key, &proof).await?;
+
+    Ok(proof)
+}
+
+#[async_trait::async_trait]
+impl IFibonacciRiscZeroProvider for FibonacciRemoteProvider {
+    #[instrument(skip(self, cancellation))]
+    async fn generate_proof(
+        &self,
+        iterations: u16,
+        options: ProofOptions,
+        cancellation: CancellationToken,
+    ) -> crate::prelude::Result<FibonacciProof> {
+        check_iterations(&self.config, iterations)?;
+
+        let guest = self.guests.guest(options.image_id)?.clone();
+        let input = iterations.abi_encode();
+        let receipt_kind = options.receipt_kind;
+        let cache_key = ProofCacheKey::new(
+            guest.image_id.as_slice(),
+            &input,
+            &cache_kind(ProverBackend::Remote, receipt_kind),
+        );
+        if !options.bypass_cache {
+            if let Some(proof) = cached(&self.cache, cache_key).await {
+                info!(
+                    iterations = iterations,
+                    input = redaction::payload(&input),
+                    receipt_kind = receipt_kind.as_str(),
+                    version = guest.version,
+                    "Reusing cached proof"
+                );
+                return Ok(proof);
+            }
+        }
+
+        let shared = self
+            .in_flight
+            .run(cache_key, &cancellation, |shared_cancellation| {
+                let workers = self.workers.clone();
+                let cache = self.cache.clone();
+                let config = self.config.clone();
+                async move {
+                    prove_and_cache(
+                        workers,
+                        cache,
+                        config,
+                        guest,
+                        receipt_kind,
+                        iterations,
+                        shared_cancellation,
+                    )
+                    .await
+                    .map_err(Arc::new)
+                }
+            })
+            .await;
+
+        match shared {
+            Some(Ok(proof)) => Ok(proof),
+            Some(Err(e)) => Err(shared_error(&e)),
+            None if cancellation.is_cancelled() => Err(FibchainError::Cancelled),
+            None => Err(FibchainError::RemoteProver(
+                "proof generation stopped before finishing".to_string(),
+            )),
+        }
+    }
+
+    #[instrument(skip(self))]
+    async fn dry_run(
+        &self,
+        iterations: u16,
+        image_id: Option<B256>,
+    ) -> crate::prelude::Result<ExecutionReport> {
+        check_iterations(&self.config, iterations)?;
+
+        let elf = self.guests.guest(image_id)?.elf.clone();
+        let input = iterations.abi_encode();
+        let limits = self.config.execution_limits();
+        tokio::task::spawn_blocking(move || execute(&elf, &input, limits))
+            .await
+            .map_err(|e| FibchainError::ZkVM(e.to_string()))?
+    }
+
+    async fn cached_proof(
+        &self,
+        iterations: u16,
+        receipt_kind: ReceiptKind,
+        image_id: Option<B256>,
+    ) -> Option<FibonacciProof> {
+        let image_id = self.guests.guest(image_id).ok()?.image_id;
+        let key = ProofCacheKey::new(
+            image_id.as_slice(),
+            &iterations.abi_encode(),
+            &cache_kind(ProverBackend::Remote, receipt_kind),
+        );
+
+        cached(&self.cache, key).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infra::prover_worker::ProverWorker;
+    use methods::FIBONACCI_ID;
+    use risc0_zkvm::sha::Digest;
+
+    /// Serves a `dev_mode` worker on a free localhost port.
+    async fn spawn_worker() -> Url {
+        let worker = Arc::new(ProverWorker::new(
+            ProverBackend::DevMode,
+            Arc::new(GuestRegistry::embedded()),
+            1,
+            4,
+        ));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, worker.routes()).await });
+
+        Url::parse(&format!("http://{}", address)).unwrap()
+    }
+
+    /// Serves a worker accepting proofs it never finishes, recording when one is deleted.
+    async fn spawn_stalled_worker(deleted: Arc<AtomicBool>) -> Url {
+        use axum::routing::{get, post};
+
+        let routes = axum::Router::new()
+            .route(
+                "/proofs",
+                post(|| async {
+                    (
+                        axum::http::StatusCode::ACCEPTED,
+                        bincode::serialize(&ProveTicket { id: 1 }).unwrap(),
+                    )
+                }),
+            )
+            .route(
+                "/proofs/{id}",
+                get(|| async { bincode::serialize(&ProveStatus::Proving).unwrap() }).delete(
+                    move || async move {
+                        deleted.store(true, Ordering::Relaxed);
+                        axum::http::StatusCode::NO_CONTENT
+                    },
+                ),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, routes).await });
+
+        Url::parse(&format!("http://{}", address)).unwrap()
+    }
+
+    fn config(workers: Vec<Url>) -> ProverConfig {
+        ProverConfig {
+            backend: ProverBackend::Remote,
+            remote_workers: workers,
+            remote_poll_interval_ms: 10,
+            cache_capacity: 0,
+            ..ProverConfig::default()
+        }
+    }
+
+    fn provider(workers: Vec<Url>) -> FibonacciRemoteProvider {
+        FibonacciRemoteProvider::new(config(workers), Arc::new(GuestRegistry::embedded()))
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_workers_are_skipped() {
+        let dead = Url::parse("http://127.0.0.1:1").unwrap();
+        let provider = provider(vec![dead, spawn_worker().await]);
+        let options = ProofOptions {
+            receipt_kind: ReceiptKind::Composite,
+            ..ProofOptions::default()
+        };
+
+        let proof = provider
+            .generate_proof(5, options, CancellationToken::new())
+            .await
+            .unwrap();
+
+        assert!(proof.journal > 0, "Journal should be a positive value");
+        assert_eq!(
+            proof.image_id.as_slice(),
+            Digest::from(FIBONACCI_ID).as_bytes()
+        );
+        assert_eq!(proof.prover.backend, "dev_mode");
+        assert!(proof.cycles > 0, "Cycles should be reported by the worker");
+        assert!(!provider.workers.workers[0].healthy.load(Ordering::Relaxed));
+        assert_eq!(
+            provider.workers.workers[1]
+                .in_flight
+                .load(Ordering::Relaxed),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_worker_applies_the_cycle_limit() {
+        let config = ProverConfig {
+            session_cycle_limit: 1,
+            ..config(vec![spawn_worker().await])
+        };
+        let provider = FibonacciRemoteProvider::new(config, Arc::new(GuestRegistry::embedded()));
+
+        let result = provider
+            .generate_proof(5, ProofOptions::default(), CancellationToken::new())
+            .await;
+
+        assert!(
+            matches!(result, Err(FibchainError::CycleLimitExceeded { limit: 1 })),
+            "Expected the cycle limit to be exceeded, got: {:?}",
+            result
+        );
+    }
+
+    #[tokio::test]
+    async fn test_timed_out_proof_is_deleted_from_the_worker() {
+        let deleted = Arc::new(AtomicBool::new(false));
+        let config = ProverConfig {
+            proving_timeout_secs: 1,
+            ..config(vec![spawn_stalled_worker(deleted.clone()).await])
+        };
+        let provider = FibonacciRemoteProvider::new(config, Arc::new(GuestRegistry::embedded()));
+
+        let result = provider
+            .generate_proof(5, ProofOptions::default(), CancellationToken::new())
+            .await;
+
+        assert!(
+            matches!(result, Err(FibchainError::ProvingTimeout { .. })),
+            "Expected the proof to time out, got: {:?}",
+            result
+        );
+        assert!(
+            deleted.load(Ordering::Relaxed),
+            "The proof should be deleted"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_no_reachable_worker_is_retryable() {
+        let provider = provider(vec![Url::parse("http://127.0.0.1:1").unwrap()]);
+
+        let result = provider
+            .generate_proof(5, ProofOptions::default(), CancellationToken::new())
+            .await;
+
+        assert!(
+            matches!(&result, Err(e @ FibchainError::RemoteProver(_)) if e.is_retryable()),
+            "Expected a retryable remote prover error, got: {:?}",
+            result
+        );
+    }
+}
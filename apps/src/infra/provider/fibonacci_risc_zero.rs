@@ -1,111 +1,591 @@
-use crate::domain::provider::IFibonacciRiscZeroProvider;
+use crate::domain::job::now_millis;
+use crate::domain::provider::{
+    ExecutionReport, FibonacciProof, IFibonacciRiscZeroProvider, ProofOptions, ProverMetadata,
+    ReceiptKind,
+};
+use crate::infra::config::{ExecutionLimits, ProverBackend, ProverConfig};
+use crate::infra::guest_registry::{GuestProgram, GuestRegistry};
+use crate::infra::proof_cache::{CachedProof, ProofCache, ProofCacheKey};
+use crate::infra::prover_pool::ProverPool;
+use crate::infra::redaction;
+use crate::infra::single_flight::SingleFlight;
 use crate::prelude::FibchainError;
+use alloy_primitives::B256;
 use alloy_sol_types::SolValue;
-use methods::FIBONACCI_ELF;
 use risc0_ethereum_contracts::encode_seal;
-use risc0_zkvm::{default_prover, ExecutorEnv, ProverOpts, VerifierContext};
-use tracing::{error, info, instrument};
+use risc0_zkvm::{
+    get_prover_server, BonsaiProver, ExecutorEnv, ExecutorImpl, FakeReceipt, InnerReceipt,
+    NullSegmentRef, Prover, ProverOpts, Receipt, Segment, SegmentRef, VerifierContext,
+};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, instrument, warn};
 
-pub struct FibonacciRiscZeroProvider;
+/// Receipt kind produced by the backend, part of the proof cache key so fake proofs are never
+/// reused by a real prover. Remote workers are expected to prove for real, the proofs they fake
+/// are cached under the backend they report.
+pub(crate) fn cache_kind(backend: ProverBackend, receipt_kind: ReceiptKind) -> String {
+    match backend {
+        ProverBackend::Local | ProverBackend::Bonsai | ProverBackend::Remote => {
+            receipt_kind.as_str().to_string()
+        }
+        ProverBackend::DevMode => format!("fake_{}", receipt_kind.as_str()),
+    }
+}
+
+fn prover_opts(receipt_kind: ReceiptKind) -> ProverOpts {
+    match receipt_kind {
+        ReceiptKind::Composite => ProverOpts::composite(),
+        ReceiptKind::Succinct => ProverOpts::succinct(),
+        ReceiptKind::Groth16 => ProverOpts::groth16(),
+    }
+}
+
+/// Outcome of a proof shared between every request that waited for it.
+pub(crate) type SharedProof = std::result::Result<FibonacciProof, Arc<FibchainError>>;
+
+pub(crate) fn check_iterations(
+    config: &ProverConfig,
+    iterations: u16,
+) -> crate::prelude::Result<()> {
+    if iterations == 0 || iterations > config.max_iterations {
+        error!(
+            iterations = iterations,
+            max_iterations = config.max_iterations,
+            "Iterations out of range!"
+        );
+        return Err(FibchainError::InvalidIterations {
+            iterations,
+            max: config.max_iterations,
+        });
+    }
+
+    Ok(())
+}
+
+/// Looks the proof up in the cache, treating an entry that no longer decodes as a miss.
+pub(crate) async fn cached(cache: &Arc<ProofCache>, key: ProofCacheKey) -> Option<FibonacciProof> {
+    let cache = cache.clone();
+    let cached = tokio::task::spawn_blocking(move || cache.get(&key))
+        .await
+        .ok()
+        .flatten()?;
+    let proof = bincode::deserialize::<FibonacciProof>(&cached.proof)
+        .map_err(|e| warn!("Ignoring cached proof that does not decode: {}", e))
+        .ok()?;
+
+    Some(FibonacciProof { cycles: 0, ..proof })
+}
+
+pub(crate) async fn cache_proof(
+    cache: Arc<ProofCache>,
+    key: ProofCacheKey,
+    proof: &FibonacciProof,
+) -> crate::prelude::Result<()> {
+    let encoded = bincode::serialize(proof).map_err(|e| FibchainError::ZkVM(e.to_string()))?;
+    let cached = CachedProof { proof: encoded };
+    if let Err(e) = tokio::task::spawn_blocking(move || cache.insert(key, cached)).await {
+        warn!("Failed to cache proof: {}", e);
+    }
+
+    Ok(())
+}
+
+pub struct FibonacciRiscZeroProvider {
+    config: ProverConfig,
+    guests: Arc<GuestRegistry>,
+    pool: Arc<ProverPool>,
+    cache: Arc<ProofCache>,
+    in_flight: SingleFlight<ProofCacheKey, SharedProof>,
+}
 
 impl FibonacciRiscZeroProvider {
-    pub fn new() -> Self {
-        Self
+    pub fn new(config: ProverConfig, guests: Arc<GuestRegistry>) -> Self {
+        info!(backend = config.backend.as_str(), "Prover backend selected");
+        let pool = Arc::new(ProverPool::new(config.workers));
+        info!(threads = pool.threads(), "Prover threads started");
+        let cache = Arc::new(ProofCache::new(
+            config.cache_capacity,
+            config.cache_dir.clone(),
+        ));
+
+        Self {
+            config,
+            guests,
+            pool,
+            cache,
+            in_flight: SingleFlight::new(),
+        }
+    }
+
+    fn cache_key(&self, image_id: B256, input: &[u8], receipt_kind: ReceiptKind) -> ProofCacheKey {
+        ProofCacheKey::new(
+            image_id.as_slice(),
+            input,
+            &cache_kind(self.config.backend, receipt_kind),
+        )
+    }
+}
+
+/// Keeps the error of a shared proof for every request that waited for it.
+pub(crate) fn shared_error(error: &FibchainError) -> FibchainError {
+    match error {
+        FibchainError::Cancelled => FibchainError::Cancelled,
+        FibchainError::ZkVM(message) => FibchainError::ZkVM(message.clone()),
+        FibchainError::RemoteProver(message) => FibchainError::RemoteProver(message.clone()),
+        FibchainError::CycleLimitExceeded { limit } => {
+            FibchainError::CycleLimitExceeded { limit: *limit }
+        }
+        FibchainError::ProvingTimeout { timeout } => {
+            FibchainError::ProvingTimeout { timeout: *timeout }
+        }
+        other => FibchainError::ZkVM(other.to_string()),
+    }
+}
+
+/// The executor stops a guest running past its session limit with this error, Bonsai reports it
+/// the same way.
+fn is_cycle_limit_error(error: &anyhow::Error) -> bool {
+    format!("{:#}", error)
+        .to_lowercase()
+        .contains("session limit exceeded")
+}
+
+/// Resolves once the proving timeout elapsed, never when it is disabled.
+pub(crate) async fn timed_out(timeout: Option<Duration>) {
+    match timeout {
+        Some(timeout) => tokio::time::sleep(timeout).await,
+        None => std::future::pending().await,
     }
 }
 
+/// Keeps a segment in memory and refuses to hand it to the prover once the job is cancelled, so
+/// proving stops at the next segment boundary.
+struct CancellableSegment {
+    segment: Segment,
+    cancellation: CancellationToken,
+}
+
+impl SegmentRef for CancellableSegment {
+    fn resolve(&self) -> anyhow::Result<Segment> {
+        if self.cancellation.is_cancelled() {
+            anyhow::bail!("proof generation was cancelled");
+        }
+
+        Ok(self.segment.clone())
+    }
+}
+
+/// Proves the guest with the given backend and returns the receipt with the cycles it took. When
+/// the guest runs on this machine, cancellation is checked between segments. Runs on a prover
+/// thread.
+pub(crate) fn prove(
+    backend: ProverBackend,
+    receipt_kind: ReceiptKind,
+    limits: ExecutionLimits,
+    elf: &[u8],
+    input: &[u8],
+    cancellation: &CancellationToken,
+) -> crate::prelude::Result<(Receipt, u64)> {
+    let to_error = |e: anyhow::Error| {
+        if cancellation.is_cancelled() {
+            FibchainError::Cancelled
+        } else if is_cycle_limit_error(&e) {
+            FibchainError::CycleLimitExceeded {
+                limit: limits.session_cycle_limit,
+            }
+        } else {
+            FibchainError::ZkVM(e.to_string())
+        }
+    };
+
+    let executor_environment = ExecutorEnv::builder()
+        .write_slice(input)
+        .session_limit(Some(limits.session_cycle_limit))
+        .segment_limit_po2(limits.segment_limit_po2)
+        .build()
+        .map_err(|e| {
+            error!(
+                input = redaction::payload(input),
+                "Failed to build executor environment: {}", e
+            );
+
+            to_error(e)
+        })?;
+
+    if backend == ProverBackend::Bonsai {
+        // Bonsai runs the whole session remotely, it can only be cancelled before it starts.
+        if cancellation.is_cancelled() {
+            return Err(FibchainError::Cancelled);
+        }
+
+        return BonsaiProver::new("bonsai")
+            .prove_with_opts(executor_environment, elf, &prover_opts(receipt_kind))
+            .map(|prove_info| (prove_info.receipt, prove_info.stats.total_cycles))
+            .map_err(|e| {
+                error!(
+                    input = redaction::payload(input),
+                    "Bonsai failed to build proof: {}", e
+                );
+
+                if is_cycle_limit_error(&e) {
+                    FibchainError::CycleLimitExceeded {
+                        limit: limits.session_cycle_limit,
+                    }
+                } else {
+                    FibchainError::RemoteProver(e.to_string())
+                }
+            });
+    }
+
+    let session = ExecutorImpl::from_elf(executor_environment, elf)
+        .and_then(|mut executor| {
+            executor.run_with_callback(|segment| {
+                if cancellation.is_cancelled() {
+                    anyhow::bail!("execution was cancelled");
+                }
+
+                Ok(Box::new(CancellableSegment {
+                    segment,
+                    cancellation: cancellation.clone(),
+                }))
+            })
+        })
+        .map_err(|e| {
+            error!(
+                input = redaction::payload(input),
+                "Failed to execute guest: {}", e
+            );
+
+            to_error(e)
+        })?;
+
+    if backend == ProverBackend::DevMode {
+        let journal = session
+            .journal
+            .as_ref()
+            .map(|journal| journal.bytes.clone())
+            .unwrap_or_default();
+
+        return session
+            .claim()
+            .map(|claim| {
+                let receipt = Receipt::new(InnerReceipt::Fake(FakeReceipt::new(claim)), journal);
+                (receipt, session.total_cycles)
+            })
+            .map_err(|e| {
+                error!(
+                    input = redaction::payload(input),
+                    "Failed to build fake proof: {}", e
+                );
+
+                to_error(e)
+            });
+    }
+
+    get_prover_server(&prover_opts(receipt_kind))
+        .and_then(|prover| prover.prove_session(&VerifierContext::default(), &session))
+        .map(|prove_info| (prove_info.receipt, prove_info.stats.total_cycles))
+        .map_err(|e| {
+            error!(
+                input = redaction::payload(input),
+                "Failed to build proof: {}", e
+            );
+
+            to_error(e)
+        })
+}
+
 #[async_trait::async_trait]
 impl IFibonacciRiscZeroProvider for FibonacciRiscZeroProvider {
-    #[instrument(skip(self))]
-    async fn generate_proof(&self, iterations: u16) -> crate::prelude::Result<(Vec<u8>, u128)> {
-        if iterations == 0 {
-            error!(iterations = iterations, "Iterations cannot be zero!");
-            return Err(FibchainError::Generic(color_eyre::eyre::eyre!(
-                "Iterations cannot be zero!"
-            )));
-        }
-        
+    #[instrument(skip(self, cancellation))]
+    async fn generate_proof(
+        &self,
+        iterations: u16,
+        options: ProofOptions,
+        cancellation: CancellationToken,
+    ) -> crate::prelude::Result<FibonacciProof> {
+        check_iterations(&self.config, iterations)?;
+
         info!(
             iterations = iterations,
             "Generating cryptographic proof of computation"
         );
 
+        let guest = self.guests.guest(options.image_id)?.clone();
         let input = iterations.abi_encode();
-        let executor_environment =
-            ExecutorEnv::builder()
-                .write_slice(&input)
-                .build()
-                .map_err(|e| {
-                    error!(
-                        iterations = iterations,
-                        input = hex::encode(&input),
-                        "Failed to build executor environment: {}",
-                        e
-                    );
-
-                    FibchainError::ZkVM(e.to_string())
-                })?;
+        let config = self.config.clone();
+        let receipt_kind = options.receipt_kind;
+        let cache_key = self.cache_key(guest.image_id, &input, receipt_kind);
+        if !options.bypass_cache {
+            if let Some(proof) = cached(&self.cache, cache_key).await {
+                info!(
+                    iterations = iterations,
+                    input = redaction::payload(&input),
+                    receipt_kind = receipt_kind.as_str(),
+                    version = guest.version,
+                    "Reusing cached proof"
+                );
+                return Ok(proof);
+            }
+        }
 
-        info!(
-            iterations = iterations,
-            input = hex::encode(&input),
-            "Proof generation started"
-        );
-        let receipt = default_prover()
-            .prove_with_ctx(
-                executor_environment,
-                &VerifierContext::default(),
-                FIBONACCI_ELF,
-                &ProverOpts::groth16(),
-            )
-            .map_err(|e| {
-                error!(
+        let shared = self
+            .in_flight
+            .run(cache_key, &cancellation, |shared_cancellation| {
+                let pool = self.pool.clone();
+                let cache = self.cache.clone();
+                async move {
+                    prove_and_cache(
+                        pool,
+                        cache,
+                        config,
+                        guest,
+                        receipt_kind,
+                        cache_key,
+                        iterations,
+                        shared_cancellation,
+                    )
+                    .await
+                    .map_err(Arc::new)
+                }
+            })
+            .await;
+
+        match shared {
+            Some(Ok(proof)) => Ok(proof),
+            Some(Err(e)) => Err(shared_error(&e)),
+            None if cancellation.is_cancelled() => {
+                warn!(
                     iterations = iterations,
-                    input = hex::encode(&input),
-                    "Failed to build proof: {}",
-                    e
+                    "Stopped waiting for the proof, the prover stops at the next segment unless \
+                     other requests still wait for it"
                 );
+                Err(FibchainError::Cancelled)
+            }
+            None => Err(FibchainError::ZkVM(
+                "proof generation stopped before finishing".to_string(),
+            )),
+        }
+    }
+
+    #[instrument(skip(self))]
+    async fn dry_run(
+        &self,
+        iterations: u16,
+        image_id: Option<B256>,
+    ) -> crate::prelude::Result<ExecutionReport> {
+        check_iterations(&self.config, iterations)?;
+
+        let elf = self.guests.guest(image_id)?.elf.clone();
+        let input = iterations.abi_encode();
+        let limits = self.config.execution_limits();
+        tokio::task::spawn_blocking(move || execute(&elf, &input, limits))
+            .await
+            .map_err(|e| FibchainError::ZkVM(e.to_string()))?
+    }
+
+    async fn cached_proof(
+        &self,
+        iterations: u16,
+        receipt_kind: ReceiptKind,
+        image_id: Option<B256>,
+    ) -> Option<FibonacciProof> {
+        let image_id = self.guests.guest(image_id).ok()?.image_id;
+        let input = iterations.abi_encode();
 
+        cached(&self.cache, self.cache_key(image_id, &input, receipt_kind)).await
+    }
+}
+
+/// Executes the guest without keeping its segments, whatever the prover backend is.
+pub(crate) fn execute(
+    elf: &[u8],
+    input: &[u8],
+    limits: ExecutionLimits,
+) -> crate::prelude::Result<ExecutionReport> {
+    let started = Instant::now();
+    let executor_environment = ExecutorEnv::builder()
+        .write_slice(input)
+        .session_limit(Some(limits.session_cycle_limit))
+        .segment_limit_po2(limits.segment_limit_po2)
+        .build()
+        .map_err(|e| FibchainError::ZkVM(e.to_string()))?;
+    let session = ExecutorImpl::from_elf(executor_environment, elf)
+        .and_then(|mut executor| executor.run_with_callback(|_| Ok(Box::new(NullSegmentRef))))
+        .map_err(|e| {
+            error!(
+                input = redaction::payload(input),
+                "Failed to execute guest: {}", e
+            );
+
+            if is_cycle_limit_error(&e) {
+                FibchainError::CycleLimitExceeded {
+                    limit: limits.session_cycle_limit,
+                }
+            } else {
                 FibchainError::ZkVM(e.to_string())
-            })?
-            .receipt;
+            }
+        })?;
 
-        info!(
-            iterations = iterations,
-            input = hex::encode(&input),
-            "Proof generation finished. Extracting seal and journal..."
+    let journal = session
+        .journal
+        .as_ref()
+        .map(|journal| journal.bytes.as_slice())
+        .unwrap_or_default();
+    let journal = <u128>::abi_decode(journal, true).map_err(|e| {
+        error!(
+            input = redaction::payload(input),
+            journal = redaction::payload(journal),
+            "Failed to decode journal: {}",
+            e
         );
+
+        FibchainError::ZkVM(e.to_string())
+    })?;
+
+    let stats = session.stats();
+    info!(
+        input = redaction::payload(input),
+        segments = stats.segments,
+        total_cycles = stats.total_cycles,
+        user_cycles = stats.user_cycles,
+        "Guest executed"
+    );
+    Ok(ExecutionReport {
+        journal,
+        segments: stats.segments,
+        total_cycles: stats.total_cycles,
+        user_cycles: stats.user_cycles,
+        paging_cycles: stats.paging_cycles,
+        reserved_cycles: stats.reserved_cycles,
+        duration: started.elapsed(),
+    })
+}
+
+/// Decodes the journal and, for receipts the contract verifies, encodes the seal.
+pub(crate) fn fibonacci_proof(
+    iterations: u16,
+    image_id: B256,
+    receipt_kind: ReceiptKind,
+    receipt: Receipt,
+    cycles: u64,
+    prover: ProverMetadata,
+) -> crate::prelude::Result<FibonacciProof> {
+    let journal = <u128>::abi_decode(&receipt.journal.bytes, true).map_err(|e| {
+        error!(
+            journal = redaction::payload(&receipt.journal.bytes),
+            "Failed to decode journal: {}", e
+        );
+
+        FibchainError::ZkVM(e.to_string())
+    })?;
+    let seal = if receipt_kind.is_verifiable_on_chain() {
         let seal = encode_seal(&receipt).map_err(|e| {
             error!(
-                iterations = iterations,
-                input = hex::encode(&input),
-                journal = hex::encode(&receipt.journal.bytes),
-                "Failed to encode seal: {}",
-                e
+                journal = redaction::payload(&receipt.journal.bytes),
+                "Failed to encode seal: {}", e
             );
 
             FibchainError::ZkVM(e.to_string())
         })?;
-        let journal = <u128>::abi_decode(&receipt.journal.bytes, true).map_err(|e| {
-            error!(
-                iterations = iterations,
-                input = hex::encode(&input),
-                journal = hex::encode(&receipt.journal.bytes),
-                "Failed to decode journal: {}",
-                e
+        Some(seal)
+    } else {
+        None
+    };
+
+    Ok(FibonacciProof {
+        iterations,
+        input: iterations.abi_encode(),
+        journal,
+        journal_bytes: receipt.journal.bytes.clone(),
+        seal,
+        image_id,
+        receipt_kind,
+        receipt,
+        cycles,
+        prover,
+    })
+}
+
+/// Proves the input on a prover thread, extracts the seal and journal and caches the proof. A
+/// proof running past the proving timeout is stopped at the next segment.
+#[allow(clippy::too_many_arguments)]
+async fn prove_and_cache(
+    pool: Arc<ProverPool>,
+    cache: Arc<ProofCache>,
+    config: ProverConfig,
+    guest: GuestProgram,
+    receipt_kind: ReceiptKind,
+    key: ProofCacheKey,
+    iterations: u16,
+    cancellation: CancellationToken,
+) -> crate::prelude::Result<FibonacciProof> {
+    let input = iterations.abi_encode();
+    let backend = config.backend;
+    let limits = config.execution_limits();
+    let timeout = config.proving_timeout();
+    let proving_cancellation = cancellation.child_token();
+    let started = Instant::now();
+    info!(
+        input = redaction::payload(&input),
+        receipt_kind = receipt_kind.as_str(),
+        version = guest.version,
+        "Proof generation started"
+    );
+    let proving = pool.run({
+        let input = input.clone();
+        let elf = guest.elf.clone();
+        let cancellation = proving_cancellation.clone();
+        move || prove(backend, receipt_kind, limits, &elf, &input, &cancellation)
+    });
+    let (receipt, cycles) = tokio::select! {
+        receipt = proving => receipt??,
+        _ = cancellation.cancelled() => {
+            warn!("Proof generation cancelled, the prover stops at the next segment");
+            return Err(FibchainError::Cancelled);
+        }
+        _ = timed_out(timeout) => {
+            proving_cancellation.cancel();
+            warn!(
+                timeout_secs = config.proving_timeout_secs,
+                "Proof generation timed out, the prover stops at the next segment"
             );
+            return Err(FibchainError::ProvingTimeout {
+                timeout: Duration::from_secs(config.proving_timeout_secs),
+            });
+        }
+    };
 
-            FibchainError::ZkVM(e.to_string())
-        })?;
+    info!(
+        input = redaction::payload(&input),
+        "Proof generation finished. Extracting seal and journal..."
+    );
+    let prover = ProverMetadata {
+        backend: backend.as_str().to_string(),
+        zkvm_version: risc0_zkvm::VERSION.to_string(),
+        generated_at: now_millis(),
+        duration_ms: started.elapsed().as_millis() as u64,
+    };
+    let proof = fibonacci_proof(
+        iterations,
+        guest.image_id,
+        receipt_kind,
+        receipt,
+        cycles,
+        prover,
+    )?;
 
-        info!(
-            iterations = iterations,
-            input = hex::encode(&input),
-            journal = hex::encode(&journal.to_ne_bytes()),
-            "Proof generated"
-        );
-        Ok((seal, journal))
-    }
+    info!(
+        input = redaction::payload(&input),
+        journal = redaction::payload(&proof.journal_bytes),
+        cycles = cycles,
+        "Proof generated"
+    );
+    cache_proof(cache, key, &proof).await?;
+
+    Ok(proof)
 }
 
 #[cfg(test)]
@@ -113,8 +593,13 @@ mod tests {
     use super::*;
     use crate::domain::provider::IFibonacciRiscZeroProvider;
     use color_eyre::Result;
+    use methods::FIBONACCI_ID;
     use tracing_subscriber;
 
+    fn provider() -> FibonacciRiscZeroProvider {
+        FibonacciRiscZeroProvider::new(ProverConfig::default(), Arc::new(GuestRegistry::embedded()))
+    }
+
     #[tokio::test]
     async fn test_generate_proof_success() -> Result<()> {
         tracing_subscriber::fmt()
@@ -122,20 +607,48 @@ mod tests {
             .try_init()
             .ok();
 
-        let provider = FibonacciRiscZeroProvider::new();
+        let provider = provider();
         let iterations: u16 = 5;
 
-        let result = provider.generate_proof(iterations).await;
+        let result = provider
+            .generate_proof(
+                iterations,
+                ProofOptions::default(),
+                CancellationToken::new(),
+            )
+            .await;
 
         assert!(
             result.is_ok(),
             "Expected success, but got an error: {:?}",
             result
         );
-        let (seal, journal) = result?;
+        let proof = result?;
 
-        assert!(!seal.is_empty(), "Seal should not be empty");
-        assert!(journal > 0, "Journal should be a positive value");
+        assert!(
+            proof.seal.as_ref().is_some_and(|seal| !seal.is_empty()),
+            "Seal should not be empty"
+        );
+        assert!(proof.journal > 0, "Journal should be a positive value");
+        assert!(proof.cycles > 0, "Cycles should be counted");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_composite_receipt_has_no_seal() -> Result<()> {
+        let provider = provider();
+        let options = ProofOptions {
+            receipt_kind: ReceiptKind::Composite,
+            ..ProofOptions::default()
+        };
+
+        let proof = provider
+            .generate_proof(5, options, CancellationToken::new())
+            .await?;
+
+        assert!(proof.seal.is_none(), "Only Groth16 receipts have a seal");
+        proof.receipt.verify(FIBONACCI_ID)?;
 
         Ok(())
     }
@@ -147,17 +660,99 @@ mod tests {
             .try_init()
             .ok();
 
-        let provider = FibonacciRiscZeroProvider::new();
+        let provider = provider();
         let iterations: u16 = 0; // Use an invalid edge case value
 
-        let result = provider.generate_proof(iterations).await;
+        let result = provider
+            .generate_proof(
+                iterations,
+                ProofOptions::default(),
+                CancellationToken::new(),
+            )
+            .await;
+
+        assert!(
+            matches!(
+                result,
+                Err(FibchainError::InvalidIterations { iterations: 0, .. })
+            ),
+            "Expected invalid iterations, got: {:?}",
+            result
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_reports_cycles_without_proving() -> Result<()> {
+        let provider = provider();
+
+        let report = provider.dry_run(5, None).await?;
+
+        assert!(report.journal > 0, "Journal should be a positive value");
+        assert!(report.segments > 0, "Segments should be counted");
+        assert!(report.total_cycles >= report.user_cycles);
+        assert!(provider
+            .cached_proof(5, ReceiptKind::Groth16, None)
+            .await
+            .is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cycle_limit_is_reported() -> Result<()> {
+        let config = ProverConfig {
+            session_cycle_limit: 1,
+            ..ProverConfig::default()
+        };
+        let provider = FibonacciRiscZeroProvider::new(config, Arc::new(GuestRegistry::embedded()));
+
+        let result = provider.dry_run(5, None).await;
+
+        assert!(
+            matches!(result, Err(FibchainError::CycleLimitExceeded { limit: 1 })),
+            "Expected the cycle limit to be exceeded, got: {:?}",
+            result
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_unregistered_image_id_is_refused() -> Result<()> {
+        let options = ProofOptions {
+            image_id: Some(B256::repeat_byte(0x02)),
+            ..ProofOptions::default()
+        };
+
+        let result = provider()
+            .generate_proof(5, options, CancellationToken::new())
+            .await;
 
-        assert!(result.is_err(), "Expected an error, but got success");
-        let error = result.unwrap_err();
         assert!(
-            format!("{:?}", error).contains("zero"),
-            "Unexpected error message: {:?}",
-            error
+            matches!(result, Err(FibchainError::UnknownGuestVersion { .. })),
+            "Expected an unknown guest version, got: {:?}",
+            result
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_generate_proof_cancelled() -> Result<()> {
+        let provider = provider();
+        let cancellation = CancellationToken::new();
+        cancellation.cancel();
+
+        let result = provider
+            .generate_proof(5, ProofOptions::default(), cancellation)
+            .await;
+
+        assert!(
+            matches!(result, Err(FibchainError::Cancelled)),
+            "Expected a cancelled proof, got: {:?}",
+            result
         );
 
         Ok(())
@@ -1,2 +1,3 @@
 pub mod fibonacci_ethereum;
+pub mod fibonacci_remote;
 pub mod fibonacci_risc_zero;
@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Fixed window rate limiter counting requests per client over one minute windows.
+///
+/// The limit is passed on every check so it follows configuration reloads.
+pub struct RateLimiter {
+    window: Mutex<Window>,
+}
+
+struct Window {
+    started_at: Instant,
+    requests: HashMap<String, u32>,
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self {
+            window: Mutex::new(Window {
+                started_at: Instant::now(),
+                requests: HashMap::new(),
+            }),
+        }
+    }
+
+    /// Records a request from `client`, returning how long to wait when it is over the limit.
+    /// A limit of `0` lets every request through.
+    pub fn check(&self, client: &str, requests_per_minute: u32) -> Result<(), Duration> {
+        self.check_at(client, requests_per_minute, Instant::now())
+    }
+
+    fn check_at(
+        &self,
+        client: &str,
+        requests_per_minute: u32,
+        now: Instant,
+    ) -> Result<(), Duration> {
+        if requests_per_minute == 0 {
+            return Ok(());
+        }
+
+        let mut window = self.window.lock().unwrap_or_else(|e| e.into_inner());
+        if now.duration_since(window.started_at) >= WINDOW {
+            window.started_at = now;
+            window.requests.clear();
+        }
+
+        let requests = window.requests.entry(client.to_string()).or_default();
+        if *requests >= requests_per_minute {
+            return Err(WINDOW.saturating_sub(now.duration_since(window.started_at)));
+        }
+
+        *requests += 1;
+        Ok(())
+    }
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limit_is_enforced_per_client() {
+        let limiter = RateLimiter::new();
+        let now = Instant::now();
+
+        assert!(limiter.check_at("a", 2, now).is_ok());
+        assert!(limiter.check_at("a", 2, now).is_ok());
+        assert!(limiter.check_at("a", 2, now).is_err());
+        assert!(limiter.check_at("b", 2, now).is_ok());
+    }
+
+    #[test]
+    fn test_window_resets_after_a_minute() {
+        let limiter = RateLimiter::new();
+        let now = Instant::now();
+
+        assert!(limiter.check_at("a", 1, now).is_ok());
+        let retry_after = limiter
+            .check_at("a", 1, now + Duration::from_secs(15))
+            .unwrap_err();
+        assert!(retry_after <= Duration::from_secs(45));
+        assert!(limiter.check_at("a", 1, now + WINDOW).is_ok());
+    }
+
+    #[test]
+    fn test_zero_disables_the_limit() {
+        let limiter = RateLimiter::new();
+
+        for _ in 0..100 {
+            assert!(limiter.check("a", 0).is_ok());
+        }
+    }
+}
@@ -0,0 +1,355 @@
+use crate::domain::job::{now_millis, JobId};
+use crate::domain::provider::{FibonacciProof, ProverMetadata, ReceiptKind};
+use alloy_primitives::{keccak256, TxHash, B256};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+use tracing::{info, warn};
+
+const RECEIPT_FILE: &str = "receipt.bin";
+const SEAL_FILE: &str = "seal.bin";
+const METADATA_FILE: &str = "metadata.toml";
+
+/// Record of a submitted proof, kept next to its receipt.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchiveMetadata {
+    pub image_id: B256,
+    /// Keccak-256 of the raw journal.
+    pub journal_digest: B256,
+    pub iterations: u16,
+    /// Hex of the ABI encoded guest input.
+    pub input: String,
+    pub fibonacci_number: String,
+    pub receipt_kind: ReceiptKind,
+    /// Unix timestamp in milliseconds.
+    pub archived_at: u64,
+    pub prover: ProverMetadata,
+    /// Every transaction the proof was sent in, oldest first.
+    pub submissions: Vec<ArchivedSubmission>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchivedSubmission {
+    pub job_id: JobId,
+    pub transaction_hash: TxHash,
+    /// Configured chain id, unset when `chain.chain_id` is not configured.
+    pub chain_id: Option<u64>,
+    /// Unix timestamp in milliseconds.
+    pub sent_at: u64,
+}
+
+/// An archived receipt with the seal that was derived from it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArchivedReceipt {
+    pub metadata: ArchiveMetadata,
+    /// The bincode encoded `risc0_zkvm::Receipt`.
+    pub receipt: Vec<u8>,
+    pub seal: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveLookup {
+    Job(JobId),
+    Transaction(TxHash),
+}
+
+/// Keeps the receipt of every submitted proof so it can be reproduced for audits.
+///
+/// Entries are content addressed under `receipts/{image_id}/{journal_digest}`, as any receipt
+/// for the same image and journal proves the same statement, and the first receipt archived for
+/// them is kept. `jobs/{job_id}` and `transactions/{hash}` index the entries by submission.
+/// Files are written synchronously, so call it off the runtime.
+pub struct ReceiptArchive {
+    directory: PathBuf,
+    chain_id: Option<u64>,
+    /// Entries untouched for longer are pruned, `None` keeps them forever.
+    retention: Option<Duration>,
+    writing: Mutex<()>,
+}
+
+impl ReceiptArchive {
+    pub fn new(directory: PathBuf, chain_id: Option<u64>, retention: Option<Duration>) -> Self {
+        Self {
+            directory,
+            chain_id,
+            retention,
+            writing: Mutex::new(()),
+        }
+    }
+
+    /// Archives the receipt of a proof the job sent in `transaction_hash`.
+    pub fn insert(
+        &self,
+        proof: &FibonacciProof,
+        job_id: JobId,
+        transaction_hash: TxHash,
+    ) -> io::Result<()> {
+        let _writing = self.writing();
+        let entry = format!(
+            "{}/{}",
+            hex::encode(proof.image_id),
+            hex::encode(keccak256(&proof.journal_bytes))
+        );
+        let directory = self.directory.join("receipts").join(&entry);
+        fs::create_dir_all(&directory)?;
+
+        let mut metadata = match self.read_metadata(&directory)? {
+            Some(metadata) => metadata,
+            None => {
+                let receipt = bincode::serialize(&proof.receipt)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                write_atomically(&directory.join(RECEIPT_FILE), &receipt)?;
+                if let Some(seal) = &proof.seal {
+                    write_atomically(&directory.join(SEAL_FILE), seal)?;
+                }
+
+                ArchiveMetadata {
+                    image_id: proof.image_id,
+                    journal_digest: keccak256(&proof.journal_bytes),
+                    iterations: proof.iterations,
+                    input: hex::encode(&proof.input),
+                    fibonacci_number: proof.journal.to_string(),
+                    receipt_kind: proof.receipt_kind,
+                    archived_at: now_millis(),
+                    prover: proof.prover.clone(),
+                    submissions: Vec::new(),
+                }
+            }
+        };
+        metadata.submissions.push(ArchivedSubmission {
+            job_id,
+            transaction_hash,
+            chain_id: self.chain_id,
+            sent_at: now_millis(),
+        });
+        let rendered = toml::to_string_pretty(&metadata)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        write_atomically(&directory.join(METADATA_FILE), rendered.as_bytes())?;
+
+        for index in [
+            self.index_path(ArchiveLookup::Job(job_id)),
+            self.index_path(ArchiveLookup::Transaction(transaction_hash)),
+        ] {
+            if let Some(parent) = index.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            write_atomically(&index, entry.as_bytes())?;
+        }
+
+        info!(
+            monotonic_counter.receipts_archived = 1_u64,
+            job_id = %job_id,
+            entry = entry,
+            "Receipt archived"
+        );
+        Ok(())
+    }
+
+    /// The archived receipt of a job, or of the job that sent a transaction.
+    pub fn get(&self, lookup: ArchiveLookup) -> io::Result<Option<ArchivedReceipt>> {
+        let entry = match fs::read_to_string(self.index_path(lookup)) {
+            Ok(entry) => entry,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let directory = self.directory.join("receipts").join(entry.trim());
+        let Some(metadata) = self.read_metadata(&directory)? else {
+            return Ok(None);
+        };
+
+        Ok(Some(ArchivedReceipt {
+            metadata,
+            receipt: fs::read(directory.join(RECEIPT_FILE))?,
+            seal: read_optional(&directory.join(SEAL_FILE))?,
+        }))
+    }
+
+    /// Removes the entries whose last submission is older than the retention, along with their
+    /// index files, returning how many were removed.
+    pub fn prune(&self) -> io::Result<usize> {
+        let Some(retention) = self.retention else {
+            return Ok(0);
+        };
+        let _writing = self.writing();
+        let cutoff = now_millis().saturating_sub(retention.as_millis() as u64);
+        let mut pruned = 0;
+
+        for image in read_dirs(&self.directory.join("receipts"))? {
+            for directory in read_dirs(&image)? {
+                let metadata = match self.read_metadata(&directory) {
+                    Ok(Some(metadata)) => metadata,
+                    Ok(None) => continue,
+                    Err(e) => {
+                        warn!(path = %directory.display(), "Skipping unreadable archive entry: {}", e);
+                        continue;
+                    }
+                };
+                let last_used = metadata
+                    .submissions
+                    .iter()
+                    .map(|submission| submission.sent_at)
+                    .max()
+                    .unwrap_or(metadata.archived_at);
+                if last_used >= cutoff {
+                    continue;
+                }
+
+                for submission in &metadata.submissions {
+                    for lookup in [
+                        ArchiveLookup::Job(submission.job_id),
+                        ArchiveLookup::Transaction(submission.transaction_hash),
+                    ] {
+                        remove_optional(&self.index_path(lookup))?;
+                    }
+                }
+                fs::remove_dir_all(&directory)?;
+                pruned += 1;
+            }
+            // Only succeeds once the image has no entry left.
+            fs::remove_dir(&image).ok();
+        }
+
+        if pruned > 0 {
+            info!(
+                monotonic_counter.receipts_pruned = pruned as u64,
+                "Pruned archived receipts"
+            );
+        }
+        Ok(pruned)
+    }
+
+    fn index_path(&self, lookup: ArchiveLookup) -> PathBuf {
+        match lookup {
+            ArchiveLookup::Job(job_id) => self.directory.join("jobs").join(job_id.to_string()),
+            ArchiveLookup::Transaction(transaction_hash) => self
+                .directory
+                .join("transactions")
+                .join(hex::encode(transaction_hash)),
+        }
+    }
+
+    fn read_metadata(&self, directory: &Path) -> io::Result<Option<ArchiveMetadata>> {
+        let Some(contents) = read_optional(&directory.join(METADATA_FILE))? else {
+            return Ok(None);
+        };
+
+        toml::from_str(&String::from_utf8_lossy(&contents))
+            .map(Some)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    fn writing(&self) -> MutexGuard<'_, ()> {
+        self.writing.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let partial = path.with_extension("partial");
+    fs::write(&partial, contents)?;
+    fs::rename(&partial, path)
+}
+
+fn read_optional(path: &Path) -> io::Result<Option<Vec<u8>>> {
+    match fs::read(path) {
+        Ok(contents) => Ok(Some(contents)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+fn remove_optional(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+fn read_dirs(path: &Path) -> io::Result<Vec<PathBuf>> {
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    let mut directories = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            directories.push(entry.path());
+        }
+    }
+
+    Ok(directories)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn directory() -> PathBuf {
+        std::env::temp_dir().join(format!("fibchain-archive-{}", JobId::random()))
+    }
+
+    #[test]
+    fn test_receipt_is_found_by_job_and_transaction() {
+        let directory = directory();
+        let archive = ReceiptArchive::new(directory.clone(), Some(31337), None);
+        let proof = FibonacciProof::fake(10, ReceiptKind::Groth16, 65_536);
+        let (first, second) = (JobId::random(), JobId::random());
+        archive
+            .insert(&proof, first, TxHash::repeat_byte(0x01))
+            .unwrap();
+        archive
+            .insert(&proof, second, TxHash::repeat_byte(0x02))
+            .unwrap();
+
+        let by_job = archive.get(ArchiveLookup::Job(first)).unwrap().unwrap();
+        let by_transaction = archive
+            .get(ArchiveLookup::Transaction(TxHash::repeat_byte(0x02)))
+            .unwrap()
+            .unwrap();
+        let unknown = archive.get(ArchiveLookup::Job(JobId::random())).unwrap();
+        fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(by_job, by_transaction);
+        assert_eq!(by_job.seal, proof.seal);
+        assert_eq!(by_job.receipt, bincode::serialize(&proof.receipt).unwrap());
+        assert_eq!(by_job.metadata.image_id, proof.image_id);
+        assert_eq!(by_job.metadata.fibonacci_number, "10");
+        assert_eq!(by_job.metadata.submissions.len(), 2);
+        assert_eq!(by_job.metadata.submissions[1].job_id, second);
+        assert_eq!(by_job.metadata.submissions[1].chain_id, Some(31337));
+        assert_eq!(unknown, None);
+    }
+
+    #[test]
+    fn test_only_expired_entries_are_pruned() {
+        let directory = directory();
+        let archive = ReceiptArchive::new(directory.clone(), None, Some(Duration::ZERO));
+        let job_id = JobId::random();
+        archive
+            .insert(
+                &FibonacciProof::fake(10, ReceiptKind::Groth16, 0),
+                job_id,
+                TxHash::repeat_byte(0x01),
+            )
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(5));
+
+        let kept = ReceiptArchive::new(directory.clone(), None, Some(Duration::from_secs(60)))
+            .prune()
+            .unwrap();
+        let pruned = archive.prune().unwrap();
+        let lookup = archive.get(ArchiveLookup::Job(job_id)).unwrap();
+        let receipts_left = read_dirs(&directory.join("receipts")).unwrap();
+        fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(kept, 0);
+        assert_eq!(pruned, 1);
+        assert_eq!(lookup, None);
+        assert!(receipts_left.is_empty());
+    }
+}
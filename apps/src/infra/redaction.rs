@@ -0,0 +1,148 @@
+use alloy_primitives::keccak256;
+use std::sync::OnceLock;
+
+static REDACTION_POLICY: OnceLock<RedactionPolicy> = OnceLock::new();
+
+/// Controls how sensitive or bulky values are rendered in tracing fields.
+#[derive(Debug, Clone)]
+pub struct RedactionPolicy {
+    /// Maximum number of hex characters kept when recording seals, journals and inputs.
+    pub max_payload_len: usize,
+    /// Salt mixed into client identifiers before hashing them. Without one, hashed IP addresses
+    /// could be reversed by hashing every address.
+    pub client_id_salt: String,
+}
+
+/// Uses a random salt, so client ids only correlate within one run.
+impl Default for RedactionPolicy {
+    fn default() -> Self {
+        Self {
+            max_payload_len: 64,
+            client_id_salt: random_salt(),
+        }
+    }
+}
+
+fn random_salt() -> String {
+    uuid::Uuid::new_v4().simple().to_string()
+}
+
+impl RedactionPolicy {
+    /// Keeps the configured salt, generating a random one when it is empty.
+    pub fn new(max_payload_len: usize, client_id_salt: &str) -> Self {
+        Self {
+            max_payload_len,
+            client_id_salt: match client_id_salt {
+                "" => random_salt(),
+                salt => salt.to_string(),
+            },
+        }
+    }
+
+    /// Hex encodes a payload, truncating it once it exceeds `max_payload_len` characters.
+    pub fn payload(&self, bytes: &[u8]) -> String {
+        let encoded = hex::encode(bytes);
+        if encoded.len() <= self.max_payload_len {
+            return encoded;
+        }
+
+        format!(
+            "{}...({} bytes)",
+            &encoded[..self.max_payload_len],
+            bytes.len()
+        )
+    }
+
+    /// Masks a key, keeping at most a short prefix so different keys can still be told apart.
+    pub fn key(&self, key: &str) -> String {
+        match key.get(..4) {
+            Some(prefix) if key.len() >= 16 => format!("{}****", prefix),
+            _ => "****".to_string(),
+        }
+    }
+
+    /// Hashes a client identifier so requests can be correlated without recording who made them.
+    pub fn client_id(&self, client_id: &str) -> String {
+        let digest = keccak256([self.client_id_salt.as_bytes(), client_id.as_bytes()].concat());
+        hex::encode(&digest[..8])
+    }
+}
+
+/// Installs the process wide redaction policy. Only the first call has any effect.
+pub fn init(policy: RedactionPolicy) {
+    let _ = REDACTION_POLICY.set(policy);
+}
+
+pub fn policy() -> &'static RedactionPolicy {
+    REDACTION_POLICY.get_or_init(RedactionPolicy::default)
+}
+
+pub fn payload(bytes: &[u8]) -> String {
+    policy().payload(bytes)
+}
+
+pub fn key(key: &str) -> String {
+    policy().key(key)
+}
+
+pub fn client_id(client_id: &str) -> String {
+    policy().client_id(client_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payload_is_kept_when_short() {
+        let policy = RedactionPolicy::default();
+
+        assert_eq!(policy.payload(&[0xab; 4]), "abababab");
+    }
+
+    #[test]
+    fn test_payload_is_truncated_when_long() {
+        let policy = RedactionPolicy {
+            max_payload_len: 8,
+            ..Default::default()
+        };
+
+        assert_eq!(policy.payload(&[0xab; 256]), "abababab...(256 bytes)");
+    }
+
+    #[test]
+    fn test_key_is_masked() {
+        let policy = RedactionPolicy::default();
+
+        assert_eq!(policy.key("sk_live_0123456789abcdef"), "sk_l****");
+        assert_eq!(policy.key("short"), "****");
+    }
+
+    #[test]
+    fn test_missing_salt_is_generated() {
+        let policy = RedactionPolicy::new(64, "");
+
+        assert!(!policy.client_id_salt.is_empty());
+        assert_ne!(
+            policy.client_id_salt,
+            RedactionPolicy::new(64, "").client_id_salt
+        );
+        assert_eq!(RedactionPolicy::new(64, "pepper").client_id_salt, "pepper");
+    }
+
+    #[test]
+    fn test_client_id_is_hashed_with_salt() {
+        let policy = RedactionPolicy::default();
+        let salted = RedactionPolicy {
+            client_id_salt: "pepper".to_string(),
+            ..Default::default()
+        };
+
+        let hashed = policy.client_id("127.0.0.1");
+
+        assert_eq!(hashed.len(), 16);
+        assert!(!hashed.contains("127.0.0.1"));
+        assert_eq!(hashed, policy.client_id("127.0.0.1"));
+        assert_ne!(hashed, salted.client_id("127.0.0.1"));
+    }
+}
@@ -0,0 +1,72 @@
+use crate::infra::config::{Cli, Config};
+use crate::infra::error::ConfigError;
+use crate::infra::observability::LogFilterHandle;
+use tokio::sync::watch;
+use tracing::{error, info, instrument};
+
+/// Re-reads the configuration and publishes the reloadable subset of it to the running service.
+pub struct ConfigReloader {
+    cli: Cli,
+    config: watch::Sender<Config>,
+    log_filter: LogFilterHandle,
+}
+
+impl ConfigReloader {
+    pub fn new(cli: Cli, config: watch::Sender<Config>, log_filter: LogFilterHandle) -> Self {
+        Self {
+            cli,
+            config,
+            log_filter,
+        }
+    }
+
+    /// Loads the configuration again and applies it, rejecting the whole reload when it is
+    /// invalid or changes a setting that needs a restart.
+    #[instrument(skip(self))]
+    pub fn reload(&self) -> Result<(), ConfigError> {
+        let reloaded = Config::load(&self.cli)?;
+        let current = self.config.borrow().clone();
+
+        let rejected = current.non_reloadable_changes(&reloaded);
+        if !rejected.is_empty() {
+            return Err(ConfigError::NotReloadable(rejected));
+        }
+
+        if reloaded.telemetry.log_filter != current.telemetry.log_filter {
+            self.log_filter
+                .reload(&reloaded.telemetry.log_filter)
+                .map_err(|e| ConfigError::Invalid(vec![e]))?;
+        }
+
+        self.config.send_replace(reloaded);
+        Ok(())
+    }
+
+    /// Reloads the configuration every time the process receives `SIGHUP`.
+    #[cfg(unix)]
+    pub async fn reload_on_sighup(self) {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut hangups = match signal(SignalKind::hangup()) {
+            Ok(hangups) => hangups,
+            Err(e) => {
+                error!(
+                    "Failed to listen for SIGHUP, configuration reload is disabled: {}",
+                    e
+                );
+                return;
+            }
+        };
+
+        while hangups.recv().await.is_some() {
+            info!("SIGHUP received, reloading configuration");
+            match self.reload() {
+                Ok(()) => info!("Configuration reloaded"),
+                Err(e) => error!(
+                    "Configuration reload rejected, keeping the current one: {}",
+                    e
+                ),
+            }
+        }
+    }
+}
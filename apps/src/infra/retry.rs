@@ -0,0 +1,111 @@
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+/// Job stages whose operations are retried after retryable errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetryStage {
+    Proving,
+    Send,
+    Confirmation,
+}
+
+impl RetryStage {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Proving => "proving",
+            Self::Send => "send",
+            Self::Confirmation => "confirmation",
+        }
+    }
+}
+
+/// How an operation is retried after a retryable error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetryPolicy {
+    /// Attempts including the first one, `1` disables retries.
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled for every following one.
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+    /// Time the stage may spend on all of its attempts, in seconds.
+    pub budget_secs: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new(3, 500, 30_000, 120)
+    }
+}
+
+impl RetryPolicy {
+    pub const fn new(
+        max_attempts: u32,
+        initial_backoff_ms: u64,
+        max_backoff_ms: u64,
+        budget_secs: u64,
+    ) -> Self {
+        Self {
+            max_attempts,
+            initial_backoff_ms,
+            max_backoff_ms,
+            budget_secs,
+        }
+    }
+
+    /// Delay before the attempt following `attempt`, or `None` once the attempts or the budget
+    /// are used up. Half of the delay is random so jobs failing together do not retry together.
+    pub fn next_backoff(&self, attempt: u32, elapsed: Duration) -> Option<Duration> {
+        if attempt >= self.max_attempts {
+            return None;
+        }
+
+        let exponential = self
+            .initial_backoff_ms
+            .saturating_mul(1 << attempt.saturating_sub(1).min(32))
+            .min(self.max_backoff_ms);
+        let half = exponential / 2;
+        let backoff = Duration::from_millis(exponential - half + jitter(half));
+
+        (elapsed + backoff <= Duration::from_secs(self.budget_secs)).then_some(backoff)
+    }
+}
+
+/// A random value between `0` and `max`, inclusive.
+fn jitter(max: u64) -> u64 {
+    if max == 0 {
+        return 0;
+    }
+
+    RandomState::new().build_hasher().finish() % (max + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_grows_until_the_maximum() {
+        let policy = RetryPolicy::new(10, 100, 1_000, 3_600);
+
+        let backoffs: Vec<Duration> = (1..6)
+            .map(|attempt| policy.next_backoff(attempt, Duration::ZERO).unwrap())
+            .collect();
+
+        for (backoff, ceiling) in backoffs.iter().zip([100, 200, 400, 800, 1_000]) {
+            assert!(*backoff <= Duration::from_millis(ceiling));
+            assert!(*backoff >= Duration::from_millis(ceiling / 2));
+        }
+    }
+
+    #[test]
+    fn test_attempts_and_budget_are_limited() {
+        let policy = RetryPolicy::new(3, 1_000, 1_000, 10);
+
+        assert!(policy.next_backoff(2, Duration::ZERO).is_some());
+        assert_eq!(policy.next_backoff(3, Duration::ZERO), None);
+        assert_eq!(policy.next_backoff(1, Duration::from_secs(10)), None);
+    }
+}
@@ -0,0 +1,136 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+use url::Url;
+use zeroize::Zeroizing;
+
+const REDACTED: &str = "[REDACTED]";
+
+/// Raw private key bytes used to sign transactions.
+///
+/// The buffer is wiped when dropped and neither `Debug` nor `Display` ever print its contents.
+#[derive(Clone)]
+pub struct PrivateKey(Zeroizing<Vec<u8>>);
+
+impl PrivateKey {
+    /// Decodes a hex encoded private key, with or without the `0x` prefix.
+    pub fn from_hex(value: &str) -> Result<Self, hex::FromHexError> {
+        let value = value.trim();
+        let value = value.strip_prefix("0x").unwrap_or(value);
+        hex::decode(value).map(|bytes| Self(Zeroizing::new(bytes)))
+    }
+
+    pub fn expose_secret(&self) -> &[u8] {
+        self.0.as_slice()
+    }
+}
+
+impl fmt::Debug for PrivateKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("PrivateKey([REDACTED])")
+    }
+}
+
+impl fmt::Display for PrivateKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl PartialEq for PrivateKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.expose_secret() == other.expose_secret()
+    }
+}
+
+impl Eq for PrivateKey {}
+
+impl FromStr for PrivateKey {
+    type Err = hex::FromHexError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Self::from_hex(value)
+    }
+}
+
+impl Serialize for PrivateKey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(REDACTED)
+    }
+}
+
+impl<'de> Deserialize<'de> for PrivateKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = Zeroizing::new(String::deserialize(deserializer)?);
+        Self::from_hex(&value).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Replaces a secret string by a redaction marker, keeping empty values visible as unset.
+pub fn redact(value: &str) -> &'static str {
+    if value.is_empty() {
+        ""
+    } else {
+        REDACTED
+    }
+}
+
+/// Serializes a secret string as a redaction marker, keeping empty values visible as unset.
+pub fn serialize_redacted<S: Serializer>(value: &str, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(redact(value))
+}
+
+/// Serializes a list of secrets as one redaction marker per entry.
+pub fn serialize_redacted_list<S: Serializer>(
+    values: &[String],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(values.iter().map(|_| REDACTED))
+}
+
+/// Serializes an optional URL with its credentials, path and query replaced by a redaction
+/// marker, since RPC providers commonly embed API keys in them.
+pub fn serialize_redacted_url<S: Serializer>(
+    value: &Option<Url>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match value {
+        Some(url) => serializer.serialize_str(&redact_url(url)),
+        None => serializer.serialize_none(),
+    }
+}
+
+pub fn redact_url(url: &Url) -> String {
+    let mut redacted = format!("{}://", url.scheme());
+    if !url.username().is_empty() || url.password().is_some() {
+        redacted.push_str(REDACTED);
+        redacted.push('@');
+    }
+    redacted.push_str(url.host_str().unwrap_or_default());
+    if let Some(port) = url.port() {
+        redacted.push_str(&format!(":{}", port));
+    }
+    if url.path() != "/" || url.query().is_some() {
+        redacted.push('/');
+        redacted.push_str(REDACTED);
+    }
+
+    redacted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_private_key_is_never_printed() {
+        let private_key = PrivateKey::from_hex(
+            "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+        )
+        .unwrap();
+
+        assert_eq!(private_key.expose_secret().len(), 32);
+        assert!(!format!("{:?}", private_key).contains("ac0974"));
+        assert!(!format!("{}", private_key).contains("ac0974"));
+    }
+}
@@ -0,0 +1,195 @@
+use alloy_primitives::TxHash;
+use std::collections::HashSet;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::task_tracker::TrackedFuture;
+use tokio_util::task::TaskTracker;
+use tracing::error;
+
+/// Coordinates a graceful shutdown between the HTTP server, running jobs and sent transactions.
+#[derive(Clone, Default)]
+pub struct Shutdown {
+    inner: Arc<ShutdownInner>,
+}
+
+#[derive(Default)]
+struct ShutdownInner {
+    draining: CancellationToken,
+    aborting: CancellationToken,
+    jobs: TaskTracker,
+    pending_transactions: Mutex<HashSet<TxHash>>,
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stops accepting new work. Jobs that are already running keep going.
+    pub fn begin(&self) {
+        self.inner.draining.cancel();
+        self.inner.jobs.close();
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.inner.draining.is_cancelled()
+    }
+
+    /// Resolves once the shutdown has begun.
+    pub async fn draining(&self) {
+        self.inner.draining.cancelled().await
+    }
+
+    /// Stops the work running jobs still do, for when draining takes longer than allowed. Jobs
+    /// keep the stage they reached and resume from it on the next start.
+    pub fn abort(&self) {
+        self.inner.aborting.cancel();
+    }
+
+    pub fn is_aborting(&self) -> bool {
+        self.inner.aborting.is_cancelled()
+    }
+
+    /// A token for a job's work that is cancelled when the shutdown aborts.
+    pub fn cancellation(&self) -> CancellationToken {
+        self.inner.aborting.child_token()
+    }
+
+    /// Tracks a job so the shutdown waits for it to finish.
+    pub fn track<F: Future>(&self, job: F) -> TrackedFuture<F> {
+        self.inner.jobs.track_future(job)
+    }
+
+    pub fn running_jobs(&self) -> usize {
+        self.inner.jobs.len()
+    }
+
+    /// Waits for every tracked job to finish, returning `false` when the deadline passes first.
+    pub async fn drain(&self, deadline: Duration) -> bool {
+        tokio::time::timeout(deadline, self.inner.jobs.wait())
+            .await
+            .is_ok()
+    }
+
+    pub fn transaction_sent(&self, transaction_hash: TxHash) {
+        self.pending_transactions().insert(transaction_hash);
+    }
+
+    pub fn transaction_settled(&self, transaction_hash: &TxHash) {
+        self.pending_transactions().remove(transaction_hash);
+    }
+
+    /// Logs every transaction that was sent but never confirmed so it can be followed up on.
+    pub fn report_pending_transactions(&self) -> Vec<TxHash> {
+        let pending: Vec<TxHash> = self.pending_transactions().iter().copied().collect();
+        for transaction_hash in &pending {
+            error!(
+                transaction_hash = hex::encode(transaction_hash.0),
+                "Transaction was sent but its confirmation was never observed"
+            );
+        }
+
+        pending
+    }
+
+    fn pending_transactions(&self) -> std::sync::MutexGuard<'_, HashSet<TxHash>> {
+        self.inner
+            .pending_transactions
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Resolves on Ctrl-C or, on unix, `SIGTERM`.
+pub async fn signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            error!("Failed to listen for Ctrl-C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                terminate.recv().await;
+            }
+            Err(e) => {
+                error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_drain_waits_for_running_jobs() {
+        let shutdown = Shutdown::new();
+        let job = tokio::spawn(shutdown.track(tokio::time::sleep(Duration::from_millis(50))));
+
+        shutdown.begin();
+
+        assert!(shutdown.is_draining());
+        assert!(shutdown.drain(Duration::from_secs(5)).await);
+        assert!(job.is_finished());
+    }
+
+    #[tokio::test]
+    async fn test_drain_gives_up_at_the_deadline() {
+        let shutdown = Shutdown::new();
+        let _job = tokio::spawn(shutdown.track(tokio::time::sleep(Duration::from_secs(60))));
+
+        shutdown.begin();
+
+        assert!(!shutdown.drain(Duration::from_millis(50)).await);
+        assert_eq!(shutdown.running_jobs(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_abort_cancels_running_work() {
+        let shutdown = Shutdown::new();
+        let cancellation = shutdown.cancellation();
+        let job = tokio::spawn(shutdown.track({
+            let cancellation = cancellation.clone();
+            async move { cancellation.cancelled().await }
+        }));
+
+        shutdown.begin();
+        assert!(!shutdown.drain(Duration::from_millis(20)).await);
+        shutdown.abort();
+
+        assert!(shutdown.drain(Duration::from_secs(5)).await);
+        assert!(cancellation.is_cancelled());
+        assert!(job.is_finished());
+    }
+
+    #[test]
+    fn test_settled_transactions_are_not_reported() {
+        let shutdown = Shutdown::new();
+        let settled = TxHash::repeat_byte(1);
+        let pending = TxHash::repeat_byte(2);
+
+        shutdown.transaction_sent(settled);
+        shutdown.transaction_sent(pending);
+        shutdown.transaction_settled(&settled);
+
+        assert_eq!(shutdown.report_pending_transactions(), vec![pending]);
+    }
+}
@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, Instrument};
+
+/// Coalesces concurrent calls for the same key so the work runs once and every caller receives
+/// the same result.
+///
+/// The work runs in its own task, so a caller that gives up does not stop it for the others. It
+/// is only cancelled once every caller has given up.
+pub struct SingleFlight<K, V> {
+    flights: Arc<Mutex<HashMap<K, Arc<Flight<V>>>>>,
+}
+
+struct Flight<V> {
+    result: watch::Receiver<Option<V>>,
+    cancellation: CancellationToken,
+    /// Callers still waiting, only changed while holding the flights lock.
+    waiters: AtomicUsize,
+}
+
+impl<K, V> Default for SingleFlight<K, V> {
+    fn default() -> Self {
+        Self {
+            flights: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+}
+
+impl<K, V> SingleFlight<K, V>
+where
+    K: Eq + Hash + Clone + Send + 'static,
+    V: Clone + Send + Sync + 'static,
+{
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Joins the work already running for `key`, or starts `work` when there is none. `work`
+    /// receives a token that is cancelled once every caller has given up.
+    ///
+    /// Returns `None` when `cancellation` is cancelled first or the work stopped without a result.
+    pub async fn run<F, Fut>(&self, key: K, cancellation: &CancellationToken, work: F) -> Option<V>
+    where
+        F: FnOnce(CancellationToken) -> Fut,
+        Fut: Future<Output = V> + Send + 'static,
+    {
+        let (flight, started) = {
+            let mut flights = lock(&self.flights);
+            match flights.get(&key) {
+                Some(flight) => {
+                    flight.waiters.fetch_add(1, Ordering::SeqCst);
+                    (flight.clone(), None)
+                }
+                None => {
+                    let (sender, result) = watch::channel(None);
+                    let flight = Arc::new(Flight {
+                        result,
+                        cancellation: CancellationToken::new(),
+                        waiters: AtomicUsize::new(1),
+                    });
+                    flights.insert(key.clone(), flight.clone());
+                    (flight, Some(sender))
+                }
+            }
+        };
+
+        match started {
+            Some(sender) => {
+                let work = work(flight.cancellation.clone());
+                let flights = self.flights.clone();
+                let key = key.clone();
+                let own = flight.clone();
+
+                tokio::spawn(
+                    async move {
+                        let result = work.await;
+                        let _ = sender.send(Some(result));
+                        remove(&mut lock(&flights), &key, &own);
+                    }
+                    .in_current_span(),
+                );
+            }
+            None => {
+                info!(
+                    monotonic_counter.single_flight_joins = 1_u64,
+                    "Joined work already in flight"
+                );
+            }
+        }
+
+        let waiter = Waiter {
+            flights: &self.flights,
+            key,
+            flight: flight.clone(),
+        };
+        let mut receiver = flight.result.clone();
+        let wait = async {
+            loop {
+                let current = receiver.borrow_and_update().clone();
+                if current.is_some() {
+                    return current;
+                }
+                if receiver.changed().await.is_err() {
+                    return receiver.borrow().clone();
+                }
+            }
+        };
+
+        let result = tokio::select! {
+            result = wait => result,
+            _ = cancellation.cancelled() => None,
+        };
+        drop(waiter);
+
+        result
+    }
+}
+
+/// Leaves the flight when the caller stops waiting, cancelling the work when it was the last one.
+struct Waiter<'a, K: Eq + Hash, V> {
+    flights: &'a Mutex<HashMap<K, Arc<Flight<V>>>>,
+    key: K,
+    flight: Arc<Flight<V>>,
+}
+
+impl<K: Eq + Hash, V> Drop for Waiter<'_, K, V> {
+    fn drop(&mut self) {
+        let mut flights = lock(self.flights);
+        if !flights
+            .get(&self.key)
+            .is_some_and(|flight| Arc::ptr_eq(flight, &self.flight))
+        {
+            return;
+        }
+
+        if self.flight.waiters.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.flight.cancellation.cancel();
+            remove(&mut flights, &self.key, &self.flight);
+        }
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Removes the flight for `key` unless a newer one already replaced it.
+fn remove<K: Eq + Hash, V>(
+    flights: &mut HashMap<K, Arc<Flight<V>>>,
+    key: &K,
+    own: &Arc<Flight<V>>,
+) {
+    if flights
+        .get(key)
+        .is_some_and(|flight| Arc::ptr_eq(flight, own))
+    {
+        flights.remove(key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicBool;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_concurrent_calls_share_one_run() {
+        let flights = Arc::new(SingleFlight::<u16, u128>::new());
+        let runs = Arc::new(AtomicUsize::new(0));
+
+        let calls: Vec<_> = (0..3)
+            .map(|_| {
+                let flights = flights.clone();
+                let runs = runs.clone();
+                tokio::spawn(async move {
+                    flights
+                        .run(5, &CancellationToken::new(), |_| async move {
+                            runs.fetch_add(1, Ordering::SeqCst);
+                            tokio::time::sleep(Duration::from_millis(50)).await;
+                            5
+                        })
+                        .await
+                })
+            })
+            .collect();
+
+        for call in calls {
+            assert_eq!(call.await.unwrap(), Some(5));
+        }
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_caller_does_not_stop_the_others() {
+        let flights = Arc::new(SingleFlight::<u16, bool>::new());
+        let impatient = CancellationToken::new();
+        let work = |shared: CancellationToken| async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            shared.is_cancelled()
+        };
+
+        let first = tokio::spawn({
+            let flights = flights.clone();
+            let impatient = impatient.clone();
+            async move { flights.run(5, &impatient, work).await }
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let second = tokio::spawn({
+            let flights = flights.clone();
+            async move { flights.run(5, &CancellationToken::new(), work).await }
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        impatient.cancel();
+
+        assert_eq!(first.await.unwrap(), None);
+        assert_eq!(second.await.unwrap(), Some(false));
+    }
+
+    #[tokio::test]
+    async fn test_work_is_cancelled_once_every_caller_left() {
+        let flights = SingleFlight::<u16, ()>::new();
+        let cancellation = CancellationToken::new();
+        let stopped = Arc::new(AtomicBool::new(false));
+
+        let call = flights.run(5, &cancellation, |shared| {
+            let stopped = stopped.clone();
+            async move {
+                shared.cancelled().await;
+                stopped.store(true, Ordering::SeqCst);
+            }
+        });
+        let result = tokio::join!(call, async {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            cancellation.cancel();
+        })
+        .0;
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        assert_eq!(result, None);
+        assert!(stopped.load(Ordering::SeqCst));
+    }
+}
@@ -0,0 +1,143 @@
+use crate::domain::job::{Job, JobId, JobTransition};
+use crate::domain::store::IJobStore;
+use crate::prelude::*;
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+
+/// Keeps jobs in memory. Nothing survives a restart, so it is meant for development and tests.
+#[derive(Default)]
+pub struct InMemoryJobStore {
+    jobs: Mutex<HashMap<JobId, StoredJob>>,
+}
+
+struct StoredJob {
+    job: Job,
+    transitions: Vec<JobTransition>,
+}
+
+impl InMemoryJobStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn jobs(&self) -> MutexGuard<'_, HashMap<JobId, StoredJob>> {
+        self.jobs.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[async_trait::async_trait]
+impl IJobStore for InMemoryJobStore {
+    async fn create(&self, job: &Job) -> Result<()> {
+        let mut jobs = self.jobs();
+        if jobs.contains_key(&job.id) {
+            return Err(FibchainError::Storage(format!(
+                "job {} already exists",
+                job.id
+            )));
+        }
+
+        let transition = JobTransition {
+            status: job.status,
+            at: job.updated_at,
+        };
+        jobs.insert(
+            job.id,
+            StoredJob {
+                job: job.clone(),
+                transitions: vec![transition],
+            },
+        );
+
+        Ok(())
+    }
+
+    async fn update(&self, job: &Job) -> Result<()> {
+        let mut jobs = self.jobs();
+        let stored = jobs
+            .get_mut(&job.id)
+            .ok_or_else(|| FibchainError::Storage(format!("job {} does not exist", job.id)))?;
+
+        if stored.job.status != job.status {
+            stored.transitions.push(JobTransition {
+                status: job.status,
+                at: job.updated_at,
+            });
+        }
+        stored.job = job.clone();
+
+        Ok(())
+    }
+
+    async fn get(&self, id: JobId) -> Result<Option<Job>> {
+        Ok(self.jobs().get(&id).map(|stored| stored.job.clone()))
+    }
+
+    async fn transitions(&self, id: JobId) -> Result<Vec<JobTransition>> {
+        Ok(self
+            .jobs()
+            .get(&id)
+            .map(|stored| stored.transitions.clone())
+            .unwrap_or_default())
+    }
+
+    async fn unfinished(&self) -> Result<Vec<Job>> {
+        let mut unfinished: Vec<Job> = self
+            .jobs()
+            .values()
+            .filter(|stored| !stored.job.status.is_finished())
+            .map(|stored| stored.job.clone())
+            .collect();
+        unfinished.sort_by_key(|job| job.created_at);
+
+        Ok(unfinished)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::job::JobStatus;
+
+    #[tokio::test]
+    async fn test_update_records_status_transitions() {
+        let store = InMemoryJobStore::new();
+        let mut job = Job::new(5);
+        store.create(&job).await.unwrap();
+
+        job.transition(JobStatus::Proving).unwrap();
+        store.update(&job).await.unwrap();
+        job.queue_wait_ms = Some(5);
+        store.update(&job).await.unwrap();
+        job.transition(JobStatus::Failed).unwrap();
+        store.update(&job).await.unwrap();
+
+        let statuses: Vec<JobStatus> = store
+            .transitions(job.id)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|transition| transition.status)
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![JobStatus::Requested, JobStatus::Proving, JobStatus::Failed]
+        );
+        assert_eq!(store.get(job.id).await.unwrap(), Some(job));
+    }
+
+    #[tokio::test]
+    async fn test_unfinished_skips_confirmed_and_failed_jobs() {
+        let store = InMemoryJobStore::new();
+        let requested = Job::new(1);
+        let mut confirmed = Job::new(2);
+        confirmed.status = JobStatus::Confirmed;
+        let mut failed = Job::new(3);
+        failed.status = JobStatus::Failed;
+
+        for job in [&requested, &confirmed, &failed] {
+            store.create(job).await.unwrap();
+        }
+
+        assert_eq!(store.unfinished().await.unwrap(), vec![requested]);
+    }
+}
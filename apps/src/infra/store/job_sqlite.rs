@@ -0,0 +1,477 @@
+use crate::domain::job::{Job, JobId, JobStatus, JobTransition};
+use crate::domain::provider::{ProofOptions, ReceiptKind};
+use crate::domain::store::IJobStore;
+use crate::prelude::*;
+use alloy_primitives::{TxHash, B256};
+use rusqlite::types::Type;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// Schema changes, applied in order. `PRAGMA user_version` records how many were applied.
+const MIGRATIONS: &[&str] = &[
+    "
+    CREATE TABLE IF NOT EXISTS jobs (
+        id TEXT PRIMARY KEY NOT NULL,
+        iterations INTEGER NOT NULL,
+        input BLOB NOT NULL,
+        status TEXT NOT NULL,
+        seal BLOB,
+        journal TEXT,
+        transaction_hash BLOB,
+        error TEXT,
+        created_at INTEGER NOT NULL,
+        updated_at INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS jobs_status ON jobs (status);
+    CREATE TABLE IF NOT EXISTS job_transitions (
+        job_id TEXT NOT NULL REFERENCES jobs (id),
+        status TEXT NOT NULL,
+        at INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS job_transitions_job_id ON job_transitions (job_id);
+    ",
+    "
+    ALTER TABLE jobs ADD COLUMN block_number INTEGER;
+    ALTER TABLE jobs ADD COLUMN gas_used INTEGER;
+    ALTER TABLE job_transitions ADD COLUMN transaction_hash BLOB;
+    UPDATE jobs SET status = 'requested' WHERE status = 'pending';
+    UPDATE jobs SET status = CASE WHEN seal IS NULL THEN 'proving' ELSE 'proved' END
+        WHERE status = 'running';
+    UPDATE jobs SET status = 'confirmed' WHERE status = 'completed';
+    UPDATE job_transitions SET status = 'requested' WHERE status = 'pending';
+    UPDATE job_transitions SET status = 'proving' WHERE status = 'running';
+    UPDATE job_transitions SET status = 'confirmed' WHERE status = 'completed';
+    ",
+    "
+    ALTER TABLE jobs ADD COLUMN queue_wait_ms INTEGER;
+    ",
+    "
+    ALTER TABLE jobs ADD COLUMN bypass_cache INTEGER NOT NULL DEFAULT 0;
+    ",
+    "
+    ALTER TABLE jobs ADD COLUMN attempts INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE jobs ADD COLUMN retries INTEGER NOT NULL DEFAULT 0;
+    ",
+    "
+    ALTER TABLE jobs ADD COLUMN proof BLOB;
+    ",
+    "
+    ALTER TABLE jobs ADD COLUMN image_id BLOB;
+    ",
+    // Jobs proved before `proof` stored the whole proof only kept its seal and journal, which
+    // cannot be submitted or archived without the receipt, so they are proved again.
+    "
+    UPDATE jobs SET status = 'proving'
+        WHERE proof IS NULL AND status IN ('proved', 'submitting', 'awaiting_chain');
+    ",
+    "
+    ALTER TABLE jobs ADD COLUMN signed_transaction BLOB;
+    ",
+];
+
+/// The `seal` and `journal` columns of the first schema are no longer read. `image_id` is unset
+/// for jobs created before guest versions could be pinned, which prove the default version.
+const JOB_COLUMNS: &str = "id, iterations, input, status, proof, transaction_hash, block_number, \
+                           gas_used, error, queue_wait_ms, created_at, updated_at, bypass_cache, \
+                           attempts, retries, image_id, signed_transaction";
+
+/// Keeps jobs in a SQLite database. Queries run on the blocking thread pool.
+pub struct SqliteJobStore {
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl SqliteJobStore {
+    /// Opens the database at `path`, creating it or migrating its tables when needed.
+    pub fn open(path: &Path) -> Result<Self> {
+        let connection = Connection::open(path).map_err(|e| {
+            FibchainError::Storage(format!("failed to open database {}: {}", path.display(), e))
+        })?;
+
+        Self::with_connection(connection)
+    }
+
+    fn with_connection(mut connection: Connection) -> Result<Self> {
+        migrate(&mut connection)
+            .map_err(|e| FibchainError::Storage(format!("failed to migrate database: {}", e)))?;
+
+        Ok(Self {
+            connection: Arc::new(Mutex::new(connection)),
+        })
+    }
+
+    /// A store migrated from the first schema, holding a job that was proved with it.
+    #[cfg(test)]
+    pub(crate) fn with_first_schema_job(iterations: u16) -> Self {
+        let connection = Connection::open_in_memory().unwrap();
+        connection.execute_batch(MIGRATIONS[0]).unwrap();
+        connection
+            .execute(
+                "INSERT INTO jobs (id, iterations, input, status, seal, journal, created_at, \
+                 updated_at) VALUES (?1, ?2, x'07', 'running', x'0102', '13', 1, 2)",
+                params![JobId::random().to_string(), iterations],
+            )
+            .unwrap();
+
+        Self::with_connection(connection).unwrap()
+    }
+
+    async fn run<T, F>(&self, query: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> rusqlite::Result<T> + Send + 'static,
+    {
+        let connection = self.connection.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let mut connection = connection.lock().unwrap_or_else(|e| e.into_inner());
+            query(&mut connection)
+        })
+        .await
+        .map_err(|e| FibchainError::Storage(e.to_string()))?
+        .map_err(|e| FibchainError::Storage(e.to_string()))
+    }
+}
+
+fn migrate(connection: &mut Connection) -> rusqlite::Result<()> {
+    let applied: usize = connection.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+
+    for (version, migration) in MIGRATIONS.iter().enumerate().skip(applied) {
+        let transaction = connection.transaction()?;
+        transaction.execute_batch(migration)?;
+        transaction.pragma_update(None, "user_version", version + 1)?;
+        transaction.commit()?;
+    }
+
+    Ok(())
+}
+
+fn job_from_row(row: &Row<'_>) -> rusqlite::Result<Job> {
+    let id: String = row.get("id")?;
+    let status: String = row.get("status")?;
+    let proof: Option<Vec<u8>> = row.get("proof")?;
+    let signed_transaction: Option<Vec<u8>> = row.get("signed_transaction")?;
+    let image_id: Option<Vec<u8>> = row.get("image_id")?;
+    let transaction_hash = transaction_hash_from_row(row)?;
+
+    Ok(Job {
+        id: id.parse().map_err(|e| conversion_error(0, Type::Text, e))?,
+        iterations: row.get("iterations")?,
+        input: row.get("input")?,
+        options: ProofOptions {
+            bypass_cache: row.get("bypass_cache")?,
+            // Jobs are submitted on-chain, which only accepts Groth16 receipts.
+            receipt_kind: ReceiptKind::Groth16,
+            image_id: image_id
+                .map(|image_id| B256::try_from(image_id.as_slice()))
+                .transpose()
+                .map_err(|e| conversion_error(15, Type::Blob, e))?,
+        },
+        status: JobStatus::from_parts(&status, transaction_hash)
+            .map_err(|e| conversion_error(3, Type::Text, e))?,
+        proof: proof
+            .map(|proof| bincode::deserialize(&proof))
+            .transpose()
+            .map_err(|e| conversion_error(4, Type::Blob, e))?,
+        transaction_hash,
+        signed_transaction: signed_transaction
+            .map(|transaction| bincode::deserialize(&transaction))
+            .transpose()
+            .map_err(|e| conversion_error(16, Type::Blob, e))?,
+        block_number: row.get("block_number")?,
+        gas_used: row.get("gas_used")?,
+        error: row.get("error")?,
+        queue_wait_ms: row.get("queue_wait_ms")?,
+        attempts: row.get("attempts")?,
+        retries: row.get("retries")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+fn encode<T: serde::Serialize>(value: Option<&T>) -> rusqlite::Result<Option<Vec<u8>>> {
+    value
+        .map(bincode::serialize)
+        .transpose()
+        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(e))
+}
+
+fn transaction_hash_from_row(row: &Row<'_>) -> rusqlite::Result<Option<TxHash>> {
+    let index = row.as_ref().column_index("transaction_hash")?;
+    let transaction_hash: Option<Vec<u8>> = row.get(index)?;
+
+    transaction_hash
+        .map(|hash| TxHash::try_from(hash.as_slice()))
+        .transpose()
+        .map_err(|e| conversion_error(index, Type::Blob, e))
+}
+
+fn conversion_error(
+    column: usize,
+    column_type: Type,
+    error: impl Into<Box<dyn std::error::Error + Send + Sync>>,
+) -> rusqlite::Error {
+    rusqlite::Error::FromSqlConversionFailure(column, column_type, error.into())
+}
+
+fn record_transition(connection: &Connection, job: &Job) -> rusqlite::Result<usize> {
+    connection.execute(
+        "INSERT INTO job_transitions (job_id, status, transaction_hash, at) \
+         VALUES (?1, ?2, ?3, ?4)",
+        params![
+            job.id.to_string(),
+            job.status.as_str(),
+            job.status.transaction_hash().map(|hash| hash.to_vec()),
+            job.updated_at,
+        ],
+    )
+}
+
+#[async_trait::async_trait]
+impl IJobStore for SqliteJobStore {
+    async fn create(&self, job: &Job) -> Result<()> {
+        let job = job.clone();
+
+        self.run(move |connection| {
+            let transaction = connection.transaction()?;
+            transaction.execute(
+                &format!(
+                    "INSERT INTO jobs ({}) \
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, \
+                     ?16, ?17)",
+                    JOB_COLUMNS
+                ),
+                params![
+                    job.id.to_string(),
+                    job.iterations,
+                    job.input,
+                    job.status.as_str(),
+                    encode(job.proof.as_ref())?,
+                    job.transaction_hash.map(|hash| hash.to_vec()),
+                    job.block_number,
+                    job.gas_used,
+                    job.error,
+                    job.queue_wait_ms,
+                    job.created_at,
+                    job.updated_at,
+                    job.options.bypass_cache,
+                    job.attempts,
+                    job.retries,
+                    job.options.image_id.map(|image_id| image_id.to_vec()),
+                    encode(job.signed_transaction.as_ref())?,
+                ],
+            )?;
+            record_transition(&transaction, &job)?;
+            transaction.commit()
+        })
+        .await
+    }
+
+    async fn update(&self, job: &Job) -> Result<()> {
+        let job = job.clone();
+
+        self.run(move |connection| {
+            let transaction = connection.transaction()?;
+            let previous_status: String = transaction.query_row(
+                "SELECT status FROM jobs WHERE id = ?1",
+                params![job.id.to_string()],
+                |row| row.get(0),
+            )?;
+
+            transaction.execute(
+                "UPDATE jobs SET status = ?2, proof = ?3, transaction_hash = ?4, \
+                 block_number = ?5, gas_used = ?6, error = ?7, queue_wait_ms = ?8, \
+                 updated_at = ?9, attempts = ?10, retries = ?11, signed_transaction = ?12 \
+                 WHERE id = ?1",
+                params![
+                    job.id.to_string(),
+                    job.status.as_str(),
+                    encode(job.proof.as_ref())?,
+                    job.transaction_hash.map(|hash| hash.to_vec()),
+                    job.block_number,
+                    job.gas_used,
+                    job.error,
+                    job.queue_wait_ms,
+                    job.updated_at,
+                    job.attempts,
+                    job.retries,
+                    encode(job.signed_transaction.as_ref())?,
+                ],
+            )?;
+            if previous_status != job.status.as_str() {
+                record_transition(&transaction, &job)?;
+            }
+            transaction.commit()
+        })
+        .await
+    }
+
+    async fn get(&self, id: JobId) -> Result<Option<Job>> {
+        self.run(move |connection| {
+            connection
+                .query_row(
+                    &format!("SELECT {} FROM jobs WHERE id = ?1", JOB_COLUMNS),
+                    params![id.to_string()],
+                    job_from_row,
+                )
+                .optional()
+        })
+        .await
+    }
+
+    async fn transitions(&self, id: JobId) -> Result<Vec<JobTransition>> {
+        self.run(move |connection| {
+            let mut statement = connection.prepare(
+                "SELECT status, transaction_hash, at FROM job_transitions \
+                 WHERE job_id = ?1 ORDER BY rowid",
+            )?;
+            let transitions = statement
+                .query_map(params![id.to_string()], |row| {
+                    let status: String = row.get("status")?;
+                    let transaction_hash = transaction_hash_from_row(row)?;
+
+                    Ok(JobTransition {
+                        status: JobStatus::from_parts(&status, transaction_hash)
+                            .map_err(|e| conversion_error(0, Type::Text, e))?,
+                        at: row.get("at")?,
+                    })
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+
+            Ok(transitions)
+        })
+        .await
+    }
+
+    async fn unfinished(&self) -> Result<Vec<Job>> {
+        self.run(move |connection| {
+            let mut statement = connection.prepare(&format!(
+                "SELECT {} FROM jobs WHERE status NOT IN (?1, ?2, ?3) ORDER BY created_at",
+                JOB_COLUMNS
+            ))?;
+            let jobs = statement
+                .query_map(
+                    params![
+                        JobStatus::Confirmed.as_str(),
+                        JobStatus::Failed.as_str(),
+                        JobStatus::Cancelled.as_str()
+                    ],
+                    job_from_row,
+                )?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+
+            Ok(jobs)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::provider::{FibonacciProof, SignedTransaction};
+
+    fn store() -> SqliteJobStore {
+        SqliteJobStore::with_connection(Connection::open_in_memory().unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_job_round_trips() {
+        let store = store();
+        let transaction_hash = TxHash::repeat_byte(0xab);
+        let mut job = Job::new(10);
+        job.options.bypass_cache = true;
+        job.options.image_id = Some(B256::repeat_byte(0x01));
+        store.create(&job).await.unwrap();
+
+        for next in [
+            JobStatus::Proving,
+            JobStatus::Proved,
+            JobStatus::Submitting,
+            JobStatus::Sent(transaction_hash),
+            JobStatus::Confirmed,
+        ] {
+            job.transition(next).unwrap();
+            store.update(&job).await.unwrap();
+        }
+        job.proof = Some(FibonacciProof::fake(10, ReceiptKind::Groth16, 65_536));
+        job.signed_transaction = Some(SignedTransaction {
+            transaction_hash,
+            nonce: 3,
+            raw: vec![0x02, 0xf8],
+        });
+        job.block_number = Some(42);
+        job.gas_used = Some(21_000);
+        job.queue_wait_ms = Some(1_500);
+        job.attempts = 2;
+        job.retries = 3;
+        store.update(&job).await.unwrap();
+
+        assert_eq!(store.get(job.id).await.unwrap(), Some(job.clone()));
+        assert_eq!(store.get(JobId::random()).await.unwrap(), None);
+
+        let statuses: Vec<JobStatus> = store
+            .transitions(job.id)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|transition| transition.status)
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                JobStatus::Requested,
+                JobStatus::Proving,
+                JobStatus::Proved,
+                JobStatus::Submitting,
+                JobStatus::Sent(transaction_hash),
+                JobStatus::Confirmed,
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unfinished_jobs_survive_reopening() {
+        let directory = std::env::temp_dir().join(format!("fibchain-{}", JobId::random()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("jobs.db");
+
+        let sent = {
+            let store = SqliteJobStore::open(&path).unwrap();
+            let mut sent = Job::new(3);
+            store.create(&sent).await.unwrap();
+            for next in [
+                JobStatus::Proving,
+                JobStatus::Proved,
+                JobStatus::Submitting,
+                JobStatus::Sent(TxHash::repeat_byte(0x01)),
+            ] {
+                sent.transition(next).unwrap();
+            }
+            store.update(&sent).await.unwrap();
+
+            let mut failed = Job::new(4);
+            failed.transition(JobStatus::Failed).unwrap();
+            store.create(&failed).await.unwrap();
+
+            sent
+        };
+
+        let store = SqliteJobStore::open(&path).unwrap();
+        assert_eq!(store.unfinished().await.unwrap(), vec![sent]);
+
+        std::fs::remove_dir_all(&directory).ok();
+    }
+
+    #[tokio::test]
+    async fn test_first_schema_is_migrated() {
+        let store = SqliteJobStore::with_first_schema_job(7);
+        let jobs = store.unfinished().await.unwrap();
+
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].status, JobStatus::Proving);
+        assert_eq!(jobs[0].proof, None);
+        assert!(!jobs[0].options.bypass_cache);
+        assert_eq!(jobs[0].options.image_id, None);
+    }
+}
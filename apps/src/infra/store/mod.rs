@@ -0,0 +1,2 @@
+pub mod job_memory;
+pub mod job_sqlite;
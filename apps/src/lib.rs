@@ -0,0 +1,4 @@
+pub mod app;
+pub mod domain;
+pub mod infra;
+pub mod prelude;
@@ -1,27 +1,151 @@
-use crate::app::resources::fibonacci::FibonacciResource;
-use crate::app::resources::Resource;
-use infra::{app_state, observability};
-
-mod app;
-mod domain;
-mod infra;
-mod prelude;
+use apps::app::resources::fibonacci::FibonacciResource;
+use apps::app::resources::health::HealthResource;
+use apps::app::resources::middleware;
+use apps::app::resources::Resource;
+use apps::infra::config::{Cli, Config, ProverBackend};
+use apps::infra::error::FibchainError;
+use apps::infra::reload::ConfigReloader;
+use apps::infra::shutdown::{self, Shutdown};
+use apps::infra::{app_state, command, event_metrics, observability};
+use clap::Parser;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::{error, info, warn};
 
 #[tokio::main]
-pub async fn main() -> Result<(), Box<dyn std::error::Error>> {
+pub async fn main() -> color_eyre::Result<()> {
     dotenvy::dotenv().ok();
     color_eyre::install().ok();
-    let _guard = observability::setup_tracing();
+    let cli = Cli::parse();
+    if cli.print_config {
+        return command::print_config(&cli);
+    }
+    if cli.check_config {
+        return command::check_config(&cli).await;
+    }
+
+    let config = Config::load(&cli)?;
+    let guard = observability::setup_tracing(&config.telemetry);
+    if config.prover.backend == ProverBackend::DevMode {
+        command::ensure_local_devnet(&config).await?;
+        warn!("Proofs are faked by the dev_mode prover backend, only a mock verifier accepts them");
+    }
+    let listen_addr = config.server.listen_addr;
+    let shutdown_timeout = config.server.shutdown_timeout();
+    let resubmit_interval = config.chain.resubmit_interval();
+    let prune_interval = config.archive.prune_interval();
 
-    let state = app_state::create_state();
+    let shutdown = Shutdown::new();
+    let (config_sender, config_receiver) = watch::channel(config);
+    let state = app_state::create_state(config_receiver, shutdown.clone())?;
+    tokio::spawn(event_metrics::record(
+        state.fibonacci_number_generator.subscribe(),
+    ));
+    match state.fibonacci_number_generator.check_image_ids().await {
+        Ok(deployed) => {
+            for guest in deployed {
+                info!(
+                    contract = guest.contract.map(|contract| contract.to_string()),
+                    version = guest.version,
+                    image_id = %guest.image_id,
+                    "The contract accepts the proofs of this guest version"
+                );
+            }
+        }
+        Err(e @ FibchainError::ImageIdMismatch { .. }) => {
+            error!("{}, submissions are refused until it is fixed", e)
+        }
+        Err(e) => warn!(
+            "Failed to read the image id the contract accepts, checking again on readiness: {}",
+            e
+        ),
+    }
+    let resumed = state.fibonacci_number_generator.resume_unfinished().await?;
+    if resumed > 0 {
+        info!(resumed = resumed, "Resumed unfinished jobs");
+    }
+    tokio::spawn(
+        state
+            .fibonacci_number_generator
+            .clone()
+            .run_submitter(resubmit_interval),
+    );
+    tokio::spawn(
+        state
+            .fibonacci_number_generator
+            .clone()
+            .run_archive_pruner(prune_interval),
+    );
 
-    let listener = tokio::net::TcpListener::bind("127.0.0.1:8080").await?;
-    let router = axum::Router::new()
-        .nest("/fibonacci", FibonacciResource::routes())
+    #[cfg(unix)]
+    tokio::spawn(ConfigReloader::new(cli, config_sender, guard.log_filter()).reload_on_sighup());
+
+    let listener = tokio::net::TcpListener::bind(listen_addr).await?;
+    let fibonacci = axum::Router::new().nest("/fibonacci", FibonacciResource::routes());
+    let router = middleware::protect(fibonacci, state.clone())
+        .merge(HealthResource::routes())
         .with_state(state);
 
-    axum::serve(listener, router).await.map_err(|e| {
-        tracing::error!("server error: {}", e);
-        e.into()
-    })
+    let mut server = tokio::spawn({
+        let shutdown = shutdown.clone();
+        async move {
+            axum::serve(
+                listener,
+                router.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .with_graceful_shutdown(async move { shutdown.draining().await })
+            .await
+        }
+    });
+
+    tokio::select! {
+        result = &mut server => {
+            return match result {
+                Ok(Ok(())) => Ok(()),
+                Ok(Err(e)) => {
+                    error!("server error: {}", e);
+                    Err(e.into())
+                }
+                Err(e) => Err(e.into()),
+            };
+        }
+        _ = shutdown::signal() => {}
+    }
+
+    info!(
+        running_jobs = shutdown.running_jobs(),
+        timeout_secs = shutdown_timeout.as_secs(),
+        "Shutdown requested, draining running jobs"
+    );
+    shutdown.begin();
+    if shutdown.drain(shutdown_timeout).await {
+        info!("All running jobs finished");
+    } else {
+        warn!(
+            running_jobs = shutdown.running_jobs(),
+            "Shutdown timeout reached before all running jobs finished, cancelling them"
+        );
+        shutdown.abort();
+        if !shutdown.drain(Duration::from_secs(5)).await {
+            warn!(
+                running_jobs = shutdown.running_jobs(),
+                "Jobs did not stop after being cancelled"
+            );
+        }
+    }
+
+    if tokio::time::timeout(Duration::from_secs(5), &mut server)
+        .await
+        .is_err()
+    {
+        warn!("Server did not stop in time, closing remaining connections");
+        server.abort();
+    }
+
+    shutdown.report_pending_transactions();
+    info!("Shutdown complete");
+    drop(guard);
+
+    Ok(())
 }
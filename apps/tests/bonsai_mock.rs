@@ -0,0 +1,84 @@
+//! Runs the `bonsai` prover backend against the Bonsai mock, proving in dev mode.
+
+use apps::domain::provider::{IFibonacciRiscZeroProvider, ProofOptions, ReceiptKind};
+use apps::infra::bonsai_mock::BonsaiMock;
+use apps::infra::config::{ProverBackend, ProverConfig};
+use apps::infra::guest_registry::GuestRegistry;
+use apps::infra::provider::fibonacci_risc_zero::FibonacciRiscZeroProvider;
+use methods::FIBONACCI_ID;
+use std::sync::{mpsc, Arc, OnceLock};
+use tokio_util::sync::CancellationToken;
+use url::Url;
+
+/// Starts the mock once for every test, on its own runtime so it outlives each test's runtime.
+/// The Bonsai client reads where to reach it from the environment.
+fn start_bonsai_mock() {
+    static MOCK: OnceLock<Url> = OnceLock::new();
+
+    MOCK.get_or_init(|| {
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            let runtime = tokio::runtime::Runtime::new().unwrap();
+            runtime.block_on(async move {
+                let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+                let url =
+                    Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+                let mock = Arc::new(BonsaiMock::new(url.clone(), ProverBackend::DevMode));
+                sender.send(url).unwrap();
+                axum::serve(listener, mock.routes()).await.unwrap();
+            });
+        });
+        let url = receiver.recv().unwrap();
+
+        std::env::set_var("BONSAI_API_URL", url.as_str());
+        std::env::set_var("BONSAI_API_KEY", "bonsai-mock-key");
+        // The client checks the integrity of the receipts it downloads, fake ones included.
+        std::env::set_var("RISC0_DEV_MODE", "1");
+        url
+    });
+}
+
+fn provider() -> FibonacciRiscZeroProvider {
+    let config = ProverConfig {
+        backend: ProverBackend::Bonsai,
+        cache_capacity: 0,
+        ..ProverConfig::default()
+    };
+
+    FibonacciRiscZeroProvider::new(config, Arc::new(GuestRegistry::embedded()))
+}
+
+#[tokio::test]
+async fn test_bonsai_groth16_proof_has_a_seal() {
+    start_bonsai_mock();
+
+    let proof = provider()
+        .generate_proof(5, ProofOptions::default(), CancellationToken::new())
+        .await
+        .unwrap();
+
+    assert!(
+        proof.seal.as_ref().is_some_and(|seal| !seal.is_empty()),
+        "Groth16 proofs should have a seal"
+    );
+    assert!(proof.journal > 0, "Journal should be a positive value");
+    assert!(proof.cycles > 0, "Cycles should be reported by the session");
+    assert_eq!(proof.prover.backend, "bonsai");
+}
+
+#[tokio::test]
+async fn test_bonsai_succinct_proof_is_not_converted() {
+    start_bonsai_mock();
+    let options = ProofOptions {
+        receipt_kind: ReceiptKind::Succinct,
+        ..ProofOptions::default()
+    };
+
+    let proof = provider()
+        .generate_proof(8, options, CancellationToken::new())
+        .await
+        .unwrap();
+
+    assert!(proof.seal.is_none(), "Only Groth16 receipts have a seal");
+    proof.receipt.verify(FIBONACCI_ID).unwrap();
+}
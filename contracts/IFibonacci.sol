@@ -9,4 +9,7 @@ interface IFibonacci {
 
     /// @notice Returns the count (current validated fibonacci numbers) stored.
     function get() external view returns (uint256);
+
+    /// @notice Image ID of the zkVM guest program whose proofs are accepted.
+    function imageId() external view returns (bytes32);
 }